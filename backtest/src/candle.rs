@@ -0,0 +1,294 @@
+//! OHLCV candles read from CSV exports of the `price_feeds` table.
+//!
+//! The expected input is the output of
+//! `COPY (SELECT ... FROM price_feeds ORDER BY timestamp) TO STDOUT WITH CSV HEADER`
+//! for one interval and source. Columns are located by header name, so extra
+//! columns and any column order are accepted. Fields are split on commas and
+//! surrounding double quotes are stripped; no exported column contains a
+//! comma.
+
+use std::fmt;
+use std::io::BufRead;
+
+/// One OHLCV candle. Volume is in base token units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candle {
+    /// Candle open time in Unix seconds
+    pub timestamp: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// Errors returned by [`read_candles`].
+#[derive(Debug)]
+pub enum CsvError {
+    Io(std::io::Error),
+    /// The input has no header line.
+    Empty,
+    /// A required column is absent from the header.
+    MissingColumn(&'static str),
+    /// A field could not be parsed; `line` is 1-based.
+    InvalidField {
+        line: usize,
+        column: &'static str,
+    },
+    /// A timestamp is not after the previous candle's.
+    Unordered {
+        line: usize,
+    },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(error) => write!(f, "read failed: {error}"),
+            CsvError::Empty => write!(f, "input is empty"),
+            CsvError::MissingColumn(column) => write!(f, "missing column `{column}`"),
+            CsvError::InvalidField { line, column } => {
+                write!(f, "line {line}: invalid `{column}` field")
+            }
+            CsvError::Unordered { line } => {
+                write!(f, "line {line}: timestamp is not after the previous candle")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl From<std::io::Error> for CsvError {
+    fn from(error: std::io::Error) -> Self {
+        CsvError::Io(error)
+    }
+}
+
+const COLUMNS: [&str; 6] = ["timestamp", "open", "high", "low", "close", "volume"];
+
+/// Reads candles, keeping only rows for `pair` when given.
+///
+/// Rows must be in strictly increasing timestamp order after filtering.
+pub fn read_candles(reader: impl BufRead, pair: Option<&str>) -> Result<Vec<Candle>, CsvError> {
+    let mut lines = reader.lines();
+    let header = lines.next().ok_or(CsvError::Empty)??;
+    let names: Vec<&str> = header.split(',').map(unquote).collect();
+    let mut indices = [0; 6];
+    for (index, column) in indices.iter_mut().zip(COLUMNS) {
+        *index = names
+            .iter()
+            .position(|name| *name == column)
+            .ok_or(CsvError::MissingColumn(column))?;
+    }
+    let pair_index = match pair {
+        Some(_) => Some(
+            names
+                .iter()
+                .position(|name| *name == "token_pair")
+                .ok_or(CsvError::MissingColumn("token_pair"))?,
+        ),
+        None => None,
+    };
+
+    let mut candles: Vec<Candle> = Vec::new();
+    for (offset, line) in lines.enumerate() {
+        let line = line?;
+        let number = offset + 2;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(unquote).collect();
+        let field = |column: usize| {
+            fields
+                .get(indices[column])
+                .copied()
+                .ok_or(CsvError::InvalidField {
+                    line: number,
+                    column: COLUMNS[column],
+                })
+        };
+        if let (Some(index), Some(pair)) = (pair_index, pair)
+            && fields.get(index) != Some(&pair)
+        {
+            continue;
+        }
+        let timestamp = parse_timestamp(field(0)?).ok_or(CsvError::InvalidField {
+            line: number,
+            column: "timestamp",
+        })?;
+        let mut values = [0.0; 5];
+        for (column, value) in (1..6).zip(values.iter_mut()) {
+            *value = field(column)?
+                .parse()
+                .ok()
+                .filter(|value: &f64| value.is_finite())
+                .ok_or(CsvError::InvalidField {
+                    line: number,
+                    column: COLUMNS[column],
+                })?;
+        }
+        if candles
+            .last()
+            .is_some_and(|last| timestamp <= last.timestamp)
+        {
+            return Err(CsvError::Unordered { line: number });
+        }
+        let [open, high, low, close, volume] = values;
+        candles.push(Candle {
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+            volume,
+        });
+    }
+    Ok(candles)
+}
+
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field
+        .strip_prefix('"')
+        .and_then(|field| field.strip_suffix('"'))
+        .unwrap_or(field)
+}
+
+/// Parses Unix seconds or an ISO 8601 / Postgres timestamp.
+///
+/// Accepts `YYYY-MM-DD[T| ]HH:MM:SS[.ffffff][Z|±HH[:MM]]`; a missing offset
+/// means UTC and fractional seconds are truncated.
+fn parse_timestamp(field: &str) -> Option<i64> {
+    if let Ok(seconds) = field.parse::<i64>() {
+        return Some(seconds);
+    }
+    let (date, rest) = field.split_at_checked(10)?;
+    let rest = rest.strip_prefix(['T', ' '])?;
+    let mut date = date.split('-');
+    let year: i64 = date.next()?.parse().ok()?;
+    let month: i64 = date.next()?.parse().ok()?;
+    let day: i64 = date.next()?.parse().ok()?;
+
+    let zone_start = rest.find(['Z', '+', '-']).unwrap_or(rest.len());
+    let (time, zone) = rest.split_at(zone_start);
+    let time = time.split('.').next()?;
+    let mut time = time.split(':');
+    let hour: i64 = time.next()?.parse().ok()?;
+    let minute: i64 = time.next()?.parse().ok()?;
+    let second: i64 = time.next()?.parse().ok()?;
+    let valid = (1..=12).contains(&month)
+        && (1..=31).contains(&day)
+        && (0..24).contains(&hour)
+        && (0..60).contains(&minute)
+        && (0..61).contains(&second);
+    if !valid || time.next().is_some() {
+        return None;
+    }
+
+    let offset = match zone {
+        "" | "Z" => 0,
+        _ => {
+            let sign = if zone.starts_with('-') { -1 } else { 1 };
+            let mut parts = zone[1..].split(':');
+            let hours: i64 = parts.next()?.parse().ok()?;
+            let minutes: i64 = parts.next().map_or(Some(0), |m| m.parse().ok())?;
+            sign * (hours * 3600 + minutes * 60)
+        }
+    };
+    Some(days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const EXPORT: &str = "\
+id,token_pair,timestamp,open,high,low,close,volume,interval,source
+1,SOL/USDC,2026-02-14 00:00:00+00,100.0,101.5,99.0,101.0,1200.5,1h,birdeye
+2,BTC/USDC,2026-02-14 00:00:00+00,50000,50100,49900,50050,3.2,1h,birdeye
+3,SOL/USDC,2026-02-14 01:00:00.25+00,101.0,102.0,100.5,\"101.75\",980,1h,birdeye
+";
+
+    #[rstest]
+    #[case::unix_seconds("1771027200", 1_771_027_200)]
+    #[case::postgres("2026-02-14 00:00:00+00", 1_771_027_200)]
+    #[case::iso_utc("2026-02-14T00:00:00Z", 1_771_027_200)]
+    #[case::fraction_no_zone("2026-02-14T00:00:00.999999", 1_771_027_200)]
+    #[case::positive_offset("2026-02-14T09:00:00+09:00", 1_771_027_200)]
+    #[case::negative_offset("2026-02-13T19:30:00-04:30", 1_771_027_200)]
+    #[case::leap_day("2024-02-29 12:00:00", 1_709_208_000)]
+    #[case::before_epoch("1969-12-31T23:59:59Z", -1)]
+    fn test_parse_timestamp(#[case] field: &str, #[case] expected: i64) {
+        assert_eq!(parse_timestamp(field), Some(expected));
+    }
+
+    #[rstest]
+    #[case::bad_month("2026-13-01 00:00:00")]
+    #[case::bad_hour("2026-01-01 24:00:00")]
+    #[case::missing_time("2026-01-01")]
+    #[case::garbage("yesterday")]
+    fn test_parse_timestamp_rejects(#[case] field: &str) {
+        assert_eq!(parse_timestamp(field), None);
+    }
+
+    #[test]
+    fn test_read_candles_filters_pair() {
+        let candles = read_candles(EXPORT.as_bytes(), Some("SOL/USDC")).unwrap();
+        assert_eq!(candles.len(), 2);
+        assert_eq!(
+            candles[1],
+            Candle {
+                timestamp: 1_771_030_800,
+                open: 101.0,
+                high: 102.0,
+                low: 100.5,
+                close: 101.75,
+                volume: 980.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_candles_rejects_unordered() {
+        let result = read_candles(EXPORT.as_bytes(), None);
+        assert!(matches!(result, Err(CsvError::Unordered { line: 3 })));
+    }
+
+    #[rstest]
+    #[case::missing_column("timestamp,open,high,low,close\n", "volume")]
+    #[case::missing_pair_column("timestamp,open,high,low,close,volume\n", "token_pair")]
+    fn test_read_candles_missing_column(#[case] input: &str, #[case] expected: &str) {
+        let result = read_candles(input.as_bytes(), Some("SOL/USDC"));
+        assert!(matches!(result, Err(CsvError::MissingColumn(column)) if column == expected));
+    }
+
+    #[test]
+    fn test_read_candles_invalid_field() {
+        let input = "timestamp,open,high,low,close,volume\n0,1,1,1,NaN,1\n";
+        let result = read_candles(input.as_bytes(), None);
+        assert!(matches!(
+            result,
+            Err(CsvError::InvalidField {
+                line: 2,
+                column: "close"
+            })
+        ));
+        assert!(matches!(
+            read_candles("".as_bytes(), None),
+            Err(CsvError::Empty)
+        ));
+    }
+}
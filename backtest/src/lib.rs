@@ -0,0 +1,13 @@
+//! Offline backtesting of the joltshark strategy against historical candles.
+//!
+//! Candles exported from the `price_feeds` table are replayed in order: each
+//! close feeds the derivative estimator, `evaluate_clmm_position` decides on
+//! the R_fee range, and the three-range positions are established and
+//! withdrawn with exact CLMM liquidity math. Runs are pure functions of the
+//! candles and the configuration, so results are reproducible.
+
+pub mod candle;
+pub mod simulation;
+
+pub use candle::{Candle, CsvError, read_candles};
+pub use simulation::{BacktestConfig, BacktestError, Report, run};
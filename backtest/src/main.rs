@@ -0,0 +1,55 @@
+//! Command line entry point.
+//!
+//! Usage: `backtest <candles.csv> [--pair TOKEN_PAIR]`
+
+use backtest::{BacktestConfig, read_candles, run};
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: backtest <candles.csv> [--pair TOKEN_PAIR]";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    let mut pair = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pair" => pair = args.next(),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let candles = match File::open(&path)
+        .map_err(Into::into)
+        .and_then(|file| read_candles(BufReader::new(file), pair.as_deref()))
+    {
+        Ok(candles) => candles,
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&BacktestConfig::default(), &candles) {
+        Ok(report) => {
+            println!("{report}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("backtest failed: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
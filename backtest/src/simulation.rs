@@ -0,0 +1,469 @@
+//! Candle-by-candle replay of the three-range strategy.
+//!
+//! The capital starts as equal values of meme coin (token0) and stable coin
+//! (token1) at the first close. For each candle:
+//!
+//! 1. The log close feeds a constant-jerk Kalman estimator, with time in hours,
+//!    so jolt is scale free.
+//! 2. Positions active at the close earn the pool fee on the candle volume, in
+//!    proportion to their share of the active liquidity.
+//! 3. `evaluate_clmm_position` runs against R_fee. `Exit` withdraws every
+//!    range for a cool-down period, and `Rebalance` re-centers the layout on
+//!    the range it returns.
+//! 4. The portfolio is marked to the close for the drawdown.
+//!
+//! Before every establishment, the wallet is swapped to equal values at the
+//! close, paying the pool fee. Deposits and withdrawals use the exact integer
+//! liquidity math, so the rounding matches what the program would do.
+
+use joltshark::establishment::plan_establishment;
+use joltshark::estimator::Estimator;
+use joltshark::filter::Kalman;
+use joltshark::liquidity_math::amounts_from_liquidity;
+use joltshark::swap::FEE_RATE_DENOMINATOR;
+use joltshark::three_range::{PerRange, RangeKind, ThreeRange};
+use joltshark::{
+    CLMMCommand, JoltError, Liquidity, PoolState, Position, SqrtPriceX64, Tick, TickRounding,
+    TickSpacing, TokenAmount, TokenOrder, evaluate_clmm_position,
+};
+use std::fmt;
+
+use crate::candle::Candle;
+
+/// Strategy and market parameters for a run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BacktestConfig {
+    /// Starting capital in stable coin units, split evenly at the first close
+    pub initial_stable: u64,
+    /// Pool fee rate in units of `FEE_RATE_DENOMINATOR`
+    pub fee_rate: u32,
+    pub tick_spacing: i32,
+    /// Half the R_fee width in ticks
+    pub fee_half_width: i32,
+    /// Width of R_restock and R_exit in ticks
+    pub outer_width: i32,
+    /// Share of each token offered to R_fee, in basis points
+    pub fee_share_bps: u16,
+    /// Liquidity of other providers, assumed active at every price
+    pub background_liquidity: u128,
+    /// |jolt| of the log price, per hour cubed, that triggers `Exit`
+    pub jolt_limit: f64,
+    /// Candles to stay withdrawn after an `Exit`
+    pub cooldown_candles: usize,
+    pub process_noise: f64,
+    pub measurement_noise: f64,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        BacktestConfig {
+            initial_stable: 1_000_000_000_000,
+            fee_rate: 2500,
+            tick_spacing: 10,
+            fee_half_width: 500,
+            outer_width: 2000,
+            fee_share_bps: 5000,
+            background_liquidity: 10_000_000_000_000,
+            jolt_limit: 0.05,
+            cooldown_candles: 24,
+            process_noise: 1e-8,
+            measurement_noise: 1e-6,
+        }
+    }
+}
+
+/// Errors returned by [`run`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BacktestError {
+    /// There are no candles to replay.
+    NoCandles,
+    /// A close price is not positive and finite.
+    InvalidPrice { timestamp: i64, price: f64 },
+    /// The fee rate, range layout or pool math was rejected by joltshark.
+    Strategy(JoltError),
+}
+
+impl fmt::Display for BacktestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BacktestError::NoCandles => write!(f, "no candles to replay"),
+            BacktestError::InvalidPrice { timestamp, price } => {
+                write!(f, "invalid close price {price} at {timestamp}")
+            }
+            BacktestError::Strategy(error) => write!(f, "strategy failed: {error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for BacktestError {}
+
+impl From<JoltError> for BacktestError {
+    fn from(error: JoltError) -> Self {
+        BacktestError::Strategy(error)
+    }
+}
+
+/// Results of a run. Values are in stable coin units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Report {
+    pub candles: usize,
+    pub initial_value: f64,
+    /// Portfolio value at the last close, fees included
+    pub final_value: f64,
+    /// Value at the last close of the starting tokens, never deployed
+    pub hold_value: f64,
+    pub fee_income: f64,
+    /// Fees paid swapping the wallet to equal values before establishing
+    pub swap_costs: f64,
+    /// `hold_value - (final_value - fee_income)`, including swap costs
+    pub impermanent_loss: f64,
+    pub rebalances: usize,
+    pub exits: usize,
+    /// Largest peak-to-trough decline of the portfolio value, as a fraction
+    pub max_drawdown: f64,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "candles           {}", self.candles)?;
+        writeln!(f, "initial value     {:.2}", self.initial_value)?;
+        writeln!(f, "final value       {:.2}", self.final_value)?;
+        writeln!(f, "hold value        {:.2}", self.hold_value)?;
+        writeln!(f, "fee income        {:.2}", self.fee_income)?;
+        writeln!(f, "swap costs        {:.2}", self.swap_costs)?;
+        writeln!(f, "impermanent loss  {:.2}", self.impermanent_loss)?;
+        writeln!(f, "rebalances        {}", self.rebalances)?;
+        writeln!(f, "exits             {}", self.exits)?;
+        write!(f, "max drawdown      {:.4}%", self.max_drawdown * 100.0)
+    }
+}
+
+/// Q64.64 square root price of a close.
+fn sqrt_price_x64(price: f64) -> Result<SqrtPriceX64, JoltError> {
+    SqrtPriceX64::new((price.sqrt() * 18_446_744_073_709_551_616.0) as u128)
+}
+
+/// Wallet, positions and counters while replaying.
+struct Portfolio {
+    config: BacktestConfig,
+    meme: u64,
+    stable: u64,
+    layout: Option<ThreeRange>,
+    liquidity: PerRange<Liquidity>,
+    fee_income: f64,
+    swap_costs: f64,
+}
+
+impl Portfolio {
+    /// Mark-to-market value at `price`, fees included.
+    fn value(&self, price: f64, sqrt_price: SqrtPriceX64) -> Result<f64, BacktestError> {
+        let (mut meme, mut stable) = (self.meme as f64, self.stable as f64);
+        if let Some(layout) = self.layout {
+            for kind in RangeKind::ALL {
+                let (amount_0, amount_1) =
+                    self.amounts(&layout, kind, sqrt_price, *self.liquidity.get(kind))?;
+                meme += amount_0 as f64;
+                stable += amount_1 as f64;
+            }
+        }
+        Ok(meme * price + stable + self.fee_income)
+    }
+
+    fn amounts(
+        &self,
+        layout: &ThreeRange,
+        kind: RangeKind,
+        sqrt_price: SqrtPriceX64,
+        liquidity: Liquidity,
+    ) -> Result<(u64, u64), BacktestError> {
+        let range = layout.range(kind);
+        Ok(amounts_from_liquidity(
+            sqrt_price,
+            SqrtPriceX64::from_tick(range.tick_lower),
+            SqrtPriceX64::from_tick(range.tick_upper),
+            liquidity,
+            false,
+        )?)
+    }
+
+    /// Credits the fee earned by the active range on `volume` at `price`.
+    fn earn(&mut self, tick: Tick, price: f64, volume: f64) {
+        let Some(kind) = self.layout.and_then(|layout| layout.active_range(tick)) else {
+            return;
+        };
+        let ours = self.liquidity.get(kind).get() as f64;
+        let share = ours / (ours + self.config.background_liquidity as f64);
+        let rate = self.config.fee_rate as f64 / FEE_RATE_DENOMINATOR as f64;
+        self.fee_income += volume * price * rate * share;
+    }
+
+    /// Swaps the wallet to equal values, then deposits into `layout`.
+    fn establish(
+        &mut self,
+        layout: ThreeRange,
+        price: f64,
+        sqrt_price: SqrtPriceX64,
+    ) -> Result<(), BacktestError> {
+        let rate = self.config.fee_rate as f64 / FEE_RATE_DENOMINATOR as f64;
+        let excess = (self.meme as f64 * price - self.stable as f64) / 2.0;
+        if excess > 0.0 {
+            let sold = (excess / price) as u64;
+            self.meme -= sold;
+            self.stable += (sold as f64 * price * (1.0 - rate)) as u64;
+            self.swap_costs += sold as f64 * price * rate;
+        } else {
+            let sold = (-excess) as u64;
+            self.stable -= sold;
+            self.meme += (sold as f64 * (1.0 - rate) / price) as u64;
+            self.swap_costs += sold as f64 * rate;
+        }
+
+        let plan = plan_establishment(
+            TokenAmount::new(self.stable),
+            TokenAmount::new(self.meme),
+            &layout,
+            sqrt_price,
+            TokenOrder::MemeFirst,
+            self.config.fee_share_bps,
+        )?;
+        self.liquidity = plan.deposits.map(|deposit| deposit.liquidity);
+        let (meme, stable) = plan.undeployed();
+        self.meme = meme.get();
+        self.stable = stable.get();
+        self.layout = Some(layout);
+        Ok(())
+    }
+
+    /// Removes all liquidity back into the wallet.
+    fn withdraw(&mut self, sqrt_price: SqrtPriceX64) -> Result<(), BacktestError> {
+        let Some(layout) = self.layout.take() else {
+            return Ok(());
+        };
+        for kind in RangeKind::ALL {
+            let (meme, stable) =
+                self.amounts(&layout, kind, sqrt_price, *self.liquidity.get(kind))?;
+            self.meme += meme;
+            self.stable += stable;
+        }
+        self.liquidity = PerRange::default();
+        Ok(())
+    }
+
+    /// Layout with R_fee at `[fee_lower, fee_upper)` and the outer ranges around it.
+    fn layout(&self, fee_lower: i32, fee_upper: i32) -> Result<ThreeRange, BacktestError> {
+        let width = self.config.outer_width;
+        Ok(ThreeRange::from_bounds(
+            [fee_lower - width, fee_lower, fee_upper, fee_upper + width],
+            self.config.tick_spacing,
+        )?)
+    }
+}
+
+/// Replays `candles` through the strategy.
+pub fn run(config: &BacktestConfig, candles: &[Candle]) -> Result<Report, BacktestError> {
+    if config.fee_rate >= FEE_RATE_DENOMINATOR {
+        return Err(JoltError::InvalidFeeRate(config.fee_rate).into());
+    }
+    let first = candles.first().ok_or(BacktestError::NoCandles)?;
+    for candle in candles {
+        if !(candle.close.is_finite() && candle.close > 0.0) {
+            return Err(BacktestError::InvalidPrice {
+                timestamp: candle.timestamp,
+                price: candle.close,
+            });
+        }
+    }
+
+    let initial_stable = config.initial_stable / 2;
+    let initial_meme = (initial_stable as f64 / first.close) as u64;
+    let initial_value = initial_stable as f64 + initial_meme as f64 * first.close;
+    let mut portfolio = Portfolio {
+        config: *config,
+        meme: initial_meme,
+        stable: initial_stable,
+        layout: None,
+        liquidity: PerRange::default(),
+        fee_income: 0.0,
+        swap_costs: 0.0,
+    };
+    let mut estimator: Kalman<f64, 4> =
+        Kalman::new(config.process_noise, config.measurement_noise, 1.0)?;
+    let (mut rebalances, mut exits) = (0, 0);
+    let mut withdrawn_until = 0;
+    let (mut peak, mut max_drawdown) = (initial_value, 0.0_f64);
+    let mut value = initial_value;
+
+    for (index, candle) in candles.iter().enumerate() {
+        let sqrt_price = sqrt_price_x64(candle.close)?;
+        let tick = sqrt_price.to_tick()?;
+        let hours = (candle.timestamp - first.timestamp) as f64 / 3600.0;
+        estimator.push(hours, candle.close.ln());
+        portfolio.earn(tick, candle.close, candle.volume);
+
+        match portfolio.layout {
+            Some(layout) => {
+                let fee = layout.fee();
+                let pool = PoolState::new(
+                    sqrt_price,
+                    Liquidity::new(config.background_liquidity),
+                    config.fee_rate,
+                    TickSpacing::new(config.tick_spacing)?,
+                )?;
+                let position = Position::new(
+                    fee.tick_lower,
+                    fee.tick_upper,
+                    *portfolio.liquidity.get(RangeKind::Fee),
+                )?;
+                let command = match estimator.state() {
+                    Some(state) => {
+                        evaluate_clmm_position(&state, &pool, &position, config.jolt_limit)?
+                    }
+                    None => CLMMCommand::Hold,
+                };
+                match command {
+                    CLMMCommand::Exit => {
+                        portfolio.withdraw(sqrt_price)?;
+                        exits += 1;
+                        withdrawn_until = index + config.cooldown_candles;
+                    }
+                    CLMMCommand::Rebalance {
+                        new_tick_lower,
+                        new_tick_upper,
+                    } => {
+                        let layout =
+                            portfolio.layout(new_tick_lower.get(), new_tick_upper.get())?;
+                        portfolio.withdraw(sqrt_price)?;
+                        portfolio.establish(layout, candle.close, sqrt_price)?;
+                        rebalances += 1;
+                    }
+                    _ => {}
+                }
+            }
+            None if withdrawn_until <= index => {
+                let spacing = TickSpacing::new(config.tick_spacing)?;
+                let center = spacing.align(tick.get(), TickRounding::Floor).get();
+                let half = config.fee_half_width;
+                let layout = portfolio.layout(center - half, center + half)?;
+                portfolio.establish(layout, candle.close, sqrt_price)?;
+            }
+            None => {}
+        }
+
+        value = portfolio.value(candle.close, sqrt_price)?;
+        peak = peak.max(value);
+        max_drawdown = max_drawdown.max((peak - value) / peak);
+    }
+
+    let last = candles[candles.len() - 1].close;
+    let hold_value = initial_stable as f64 + initial_meme as f64 * last;
+    Ok(Report {
+        candles: candles.len(),
+        initial_value,
+        final_value: value,
+        hold_value,
+        fee_income: portfolio.fee_income,
+        swap_costs: portfolio.swap_costs,
+        impermanent_loss: hold_value - (value - portfolio.fee_income),
+        rebalances,
+        exits,
+        max_drawdown,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /// Hourly candles following `price(hour)` with constant volume.
+    fn candles(count: usize, price: impl Fn(f64) -> f64) -> Vec<Candle> {
+        (0..count)
+            .map(|hour| {
+                let close = price(hour as f64);
+                Candle {
+                    timestamp: 1_771_027_200 + hour as i64 * 3600,
+                    open: close,
+                    high: close,
+                    low: close,
+                    close,
+                    volume: 10_000.0,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flat_market_earns_fees_without_loss() {
+        let report = run(&BacktestConfig::default(), &candles(48, |_| 150.0)).unwrap();
+        assert_eq!((report.rebalances, report.exits), (0, 0));
+        assert!(report.fee_income > 0.0);
+        // Only deposit rounding separates the position from holding
+        assert!(report.impermanent_loss.abs() / report.initial_value < 1e-9);
+    }
+
+    #[test]
+    fn test_trend_forces_rebalances() {
+        let report = run(
+            &BacktestConfig::default(),
+            &candles(200, |hour| 100.0 * 1.002_f64.powf(hour)),
+        )
+        .unwrap();
+        assert!(report.rebalances > 0);
+        assert!(report.impermanent_loss > 0.0);
+        assert!(report.max_drawdown >= 0.0 && report.max_drawdown < 1.0);
+    }
+
+    #[test]
+    fn test_crash_triggers_exit() {
+        let config = BacktestConfig {
+            jolt_limit: 0.01,
+            cooldown_candles: 5,
+            ..BacktestConfig::default()
+        };
+        let crash = |hour: f64| if hour < 30.0 { 100.0 } else { 60.0 };
+        let report = run(&config, &candles(60, crash)).unwrap();
+        assert!(report.exits > 0);
+        assert!(report.max_drawdown > 0.1);
+    }
+
+    #[test]
+    fn test_runs_are_deterministic() {
+        let candles = candles(300, |hour| 120.0 + 10.0 * (hour / 7.0).sin());
+        let config = BacktestConfig::default();
+        assert_eq!(run(&config, &candles), run(&config, &candles));
+    }
+
+    #[rstest]
+    #[case::no_candles(&[], BacktestError::NoCandles)]
+    #[case::zero_price(&[0.0], BacktestError::InvalidPrice { timestamp: 1_771_027_200, price: 0.0 })]
+    fn test_invalid_input(#[case] prices: &[f64], #[case] expected: BacktestError) {
+        let candles = candles(prices.len(), |hour| prices[hour as usize]);
+        assert_eq!(run(&BacktestConfig::default(), &candles), Err(expected));
+    }
+
+    #[test]
+    fn test_invalid_noise() {
+        let config = BacktestConfig {
+            measurement_noise: 0.0,
+            ..BacktestConfig::default()
+        };
+        assert_eq!(
+            run(&config, &candles(1, |_| 1.0)),
+            Err(BacktestError::Strategy(JoltError::InvalidNoise))
+        );
+    }
+
+    #[test]
+    fn test_invalid_fee_rate() {
+        let config = BacktestConfig {
+            fee_rate: FEE_RATE_DENOMINATOR,
+            ..BacktestConfig::default()
+        };
+        assert_eq!(
+            run(&config, &candles(1, |_| 1.0)),
+            Err(BacktestError::Strategy(JoltError::InvalidFeeRate(
+                FEE_RATE_DENOMINATOR
+            )))
+        );
+    }
+}
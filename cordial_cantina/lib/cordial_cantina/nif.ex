@@ -4,12 +4,58 @@ defmodule CordialCantina.Nif do
 
   This module provides Erlang NIF bindings to Rust functions from the joltshark
   library. The NIF is compiled via Rustler during mix compile.
+
+  CLMM functions take a config map with `:tick_lower`, `:tick_upper`,
+  `:tick_spacing`, `:current_tick` and `:base_price`, and states as
+  `[position, velocity, acceleration, jolt]` lists. They return
+  `{:ok, value}` or `{:error, reason}`, where `reason` is one of:
+
+    * `:non_finite` - an input or result is not finite
+    * `:non_positive_price` - a price is zero or negative
+    * `:tick_out_of_bounds` - a tick is outside the pool's tick range
+    * `:invalid_base_price` - the base price is not positive
+    * `:invalid_spacing` - the tick spacing is not positive
+    * `:range_inverted` - the lower tick is not below the upper tick
+    * `:range_beyond_bounds` - a range lies entirely beyond the outermost
+      multiples of the tick spacing
+    * `:tick_misaligned` - a range bound is not a multiple of the tick spacing
+    * `:ranges_unordered` - a range of a layout does not start below the next
+    * `:ranges_overlap` - a range of a layout extends past the start of the
+      next
+    * `:ranges_gap` - a range of a layout ends before the next one starts
+    * `:sqrt_price_out_of_bounds` - a sqrt price is outside the pool's range
+    * `:empty_range` - both sqrt price bounds of a range are equal
+    * `:tick_price_mismatch` - the pool's current tick is not the tick at its
+      sqrt price
+    * `:invalid_fee_rate` - the pool's fee rate is not below the fee rate
+      denominator
+    * `:invalid_interval` - a time interval, window or cool-down is negative
+    * `:invalid_threshold` - a detector threshold is negative
+    * `:invalid_noise` - a filter noise is negative, or the measurement noise
+      is zero
+    * `:invalid_share` - a share is above 10_000 basis points
+    * `:invalid_price_limit` - a swap price limit is out of bounds or on the
+      wrong side of the price
+    * `:liquidity_underflow` - more liquidity is removed than was provided
+    * `:tick_map_full` - the tick map has no room for another tick
+    * `:overflow` - an integer result does not fit its type
+    * `:division_by_zero` - a divisor such as the liquidity is zero
+    * `:conversion` - a value does not fit the numeric type
+    * `:busy` - a pair handle is locked by another call
+
+  Malformed terms, such as a state of the wrong length, and invalid range
+  function arguments, such as a degenerate range to normalize against,
+  raise `ArgumentError`.
   """
 
   use Rustler,
     otp_app: :cordial_cantina,
     crate: "nif"
 
+  # Batches with more updates than this run on a dirty CPU scheduler so a
+  # call stays within the 1ms NIF guideline.
+  @dirty_threshold 1_000
+
   @doc """
   A no-operation function used to verify NIF loading.
 
@@ -21,4 +67,210 @@ defmodule CordialCantina.Nif do
       :ok
   """
   def nop, do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns the command for a CLMM position.
+
+  Returns `{:ok, command}`. Commands are atoms (`:hold`, `:collect_fees`,
+  `:exit`) or tagged tuples:
+
+    * `{:add_liquidity, %{tick_lower: _, tick_upper: _, amount: _}}`
+    * `{:remove_liquidity, %{amount: _}}`
+    * `{:rebalance, %{new_tick_lower: _, new_tick_upper: _}}`
+    * `{:wait, reason}`
+
+  Liquidity amounts are integers in the pool's liquidity units.
+
+  ## Examples
+
+      iex> config = %{tick_lower: -100, tick_upper: 100, tick_spacing: 10, current_tick: 200, base_price: 1.0}
+      iex> CordialCantina.Nif.evaluate_clmm_position([0.0, 0.0, 0.0, 0.0], config, 1.0)
+      {:ok, {:rebalance, %{new_tick_lower: 100, new_tick_upper: 300}}}
+  """
+  def evaluate_clmm_position(_state, _config, _jolt_limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Decides on a CLMM position and explains the decision.
+
+  `state` is `[position, velocity, acceleration, jolt]` and `config` is a map
+  with `:tick_lower`, `:tick_upper`, `:tick_spacing`, `:current_tick` and
+  `:base_price`. Returns `{:ok, decision}` with a map of the `:command`, the
+  `:rule` that fired and the diagnostics it was judged on: `:jolt`, `:jolt_limit`, `:range_position`,
+  `:current_tick`, `:tick_lower` and `:tick_upper`.
+
+  Commands are atoms such as `:hold` and `:exit` or tagged tuples such as
+  `{:rebalance, %{new_tick_lower: -100, new_tick_upper: 100}}` and
+  `{:wait, :dead_band}`.
+
+  ## Examples
+
+      iex> config = %{tick_lower: -100, tick_upper: 100, tick_spacing: 10, current_tick: 50, base_price: 1.0}
+      iex> {:ok, decision} = CordialCantina.Nif.decide_clmm_position([0.0, 0.0, 0.0, 2.0], config, 1.0)
+      iex> {decision.command, decision.rule}
+      {:exit, :jolt_limit}
+  """
+  def decide_clmm_position(_state, _config, _jolt_limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Returns true if the current tick is within the config's range."
+  def clmm_in_range(_config), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Rounds `tick` to the nearest multiple of the config's tick spacing.
+
+  Ties round up, and ticks beyond the valid range clamp to the outermost
+  multiple of the spacing inside it.
+  """
+  def clmm_round_tick(_config, _tick), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns the tick at `price`, truncated toward zero.
+
+  Returns `{:error, :non_positive_price}` for a price at or below zero,
+  `{:error, :non_finite}` for a NaN or infinite price and
+  `{:error, :tick_out_of_bounds}` for one outside the pool's tick range.
+  """
+  def clmm_tick_at_price(_config, _price), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Returns the price at `tick`."
+  def clmm_price_at_tick(_config, _tick), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Returns the current tick's position within the range, from 0.0 to 1.0."
+  def clmm_range_position(_config), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Clamps `position` to the range."
+  def range_clamp(_position, _range_start, _range_end),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Maps `position` from the range to 0.0..1.0."
+  def range_normalize(_position, _range_start, _range_end),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Like `range_normalize/3`, clamped to 0.0..1.0."
+  def range_normalize_clamped(_position, _range_start, _range_end),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Maps `position` from the range onto the blend range."
+  def range_map(_position, _range_start, _range_end, _blend_start, _blend_end),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Like `range_map/5`, clamped to the blend range."
+  def range_map_clamped(_position, _range_start, _range_end, _blend_start, _blend_end),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc "Blends two signals by the cosine and sine of `phase`."
+  def cycle_interpolate(_phase, _primary_signal, _alternative_signal),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns the `{cos, sin}` pulse of an event window within a cycle.
+
+  Phases are in radians; the pulse blends in over `blend_outer` before the
+  event and `blend_inner` after its start, and back out symmetrically. An
+  event whose ends coincide modulo 2π raises `ArgumentError`.
+  """
+  def event_pulse(_phase, _event_start, _event_end, _blend_outer, _blend_inner),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Creates a handle holding the estimator and strategy state for one token pair.
+
+  `options` is a map with `:process_noise` and `:measurement_noise` for the
+  estimator, `:jolt_limit`, `:dead_band` in ticks, and `:min_interval` and
+  `:window` in milliseconds for the rebalance limits. The handle is a
+  reference that can be shared between processes; updates to one pair are
+  serialized. Pair functions do not wait while another call holds the
+  pair: they return `:busy` or `{:error, :busy}` without effect, and the
+  caller may retry.
+  """
+  def pair_new(_options), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Feeds a price at a Unix millisecond timestamp into a pair handle.
+
+  Returns `:ok`, `:stale` when the timestamp is not after the previous one,
+  `:busy` when another call holds the pair, `{:error, :non_positive_price}`
+  for a price at or below zero, or `{:error, :non_finite}` for a NaN or
+  infinite price.
+  """
+  def pair_push(_pair, _timestamp, _price), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns the pair's `[log_price, velocity, acceleration, jolt]` per hour,
+  `nil` before the first price, or `:busy` when another call holds the pair.
+  """
+  def pair_state(_pair), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Decides on a position in `config` at the pair's latest price.
+
+  Returns `{:ok, decision}` as `decide_clmm_position/3` does, `{:ok, nil}`
+  before the first price, or `{:error, reason}` for an invalid config and
+  `{:error, :busy}` when another call holds the pair. An issued rebalance
+  counts against the pair's rate limits.
+  """
+  def pair_decide(_pair, _config), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Pushes a batch of price updates into pair handles in one call.
+
+  `entries` is a list of `{pair, updates, config}` with `updates` a list of
+  `{timestamp, price, volume}` tuples, timestamps in Unix milliseconds.
+  Returns `{:ok, results}` with one `{accepted, result}` per entry: the
+  number of updates that were not stale and the decision at the pair's
+  latest price, as `pair_decide/2` returns. The whole batch is validated
+  first, so an invalid price or config returns `{:error, reason}` without
+  updating any pair. A volume that is not a finite non-negative number
+  raises `ArgumentError`.
+
+  Batches of more than #{@dirty_threshold} updates run on a dirty CPU
+  scheduler, waiting for each pair's lock. Smaller batches skip an entry
+  whose pair another call holds, returning `{0, {:error, :busy}}` for it.
+  """
+  def push_batch(entries) do
+    size =
+      Enum.reduce(entries, 0, fn {_pair, updates, _config}, acc ->
+        acc + length(updates)
+      end)
+
+    if size > @dirty_threshold,
+      do: push_batch_dirty(entries),
+      else: push_batch_nif(entries)
+  end
+
+  @doc """
+  Like `push_batch/1` with each entry's updates packed by `pack_updates/1`.
+  """
+  def push_packed_batch(entries) do
+    size =
+      Enum.reduce(entries, 0, fn {_pair, packed, _config}, acc ->
+        acc + div(byte_size(packed), 24)
+      end)
+
+    if size > @dirty_threshold,
+      do: push_packed_batch_dirty(entries),
+      else: push_packed_batch_nif(entries)
+  end
+
+  @doc """
+  Packs `{timestamp, price, volume}` updates into 24-byte little-endian records.
+  """
+  def pack_updates(updates) do
+    for {timestamp, price, volume} <- updates, into: <<>> do
+      <<timestamp::little-signed-64, price::little-float-64, volume::little-float-64>>
+    end
+  end
+
+  @doc false
+  def push_batch_nif(_entries), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def push_batch_dirty(_entries), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def push_packed_batch_nif(_entries), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def push_packed_batch_dirty(_entries), do: :erlang.nif_error(:nif_not_loaded)
 end
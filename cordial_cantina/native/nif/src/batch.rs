@@ -0,0 +1,294 @@
+//! Batch ingestion of price updates into pair handles.
+//!
+//! A batch is a list of `{pair, updates, config}` entries, one per pair, so a
+//! Broadway batch crosses the NIF boundary once. Updates are either a list of
+//! `{timestamp, price, volume}` tuples or a packed binary of the same fields
+//! as 24-byte little-endian records: signed 64-bit milliseconds then two
+//! 64-bit floats. Volume is accepted for the feed format but not used by the
+//! estimator.
+//!
+//! A batch returns `{:ok, results}` with one `{accepted, result}` per entry,
+//! the number of updates that were not stale and `{:ok, decision}` at the
+//! pair's latest price or `{:error, reason}`. The whole batch is validated
+//! before any pair is updated, so an invalid price or config returns
+//! `{:error, reason}` without partial effects. Malformed records, including
+//! a volume that is not a finite non-negative number, raise `ArgumentError`.
+//! Each NIF has a dirty-CPU twin that the Elixir side uses for batches too
+//! large for a normal scheduler slice. The normal-scheduler NIFs never wait
+//! for a pair's lock: an entry whose pair is held by another call is skipped
+//! with `{0, {:error, :busy}}`. The dirty NIFs wait, and release the lock
+//! every [`LOCK_CHUNK`] updates so a long batch does not hold up other
+//! callers of the same pair.
+
+use std::ops::Deref;
+
+use joltshark::check_price;
+use rustler::{Binary, Error, NifResult, ResourceArc};
+
+use crate::clmm::{finite, Config, ConfigTerm};
+use crate::decision::DecisionTerm;
+use crate::error::ErrorReason;
+use crate::pair::{Pair, PairState};
+
+/// Bytes per packed update record.
+pub const RECORD_SIZE: usize = 24;
+
+/// Updates pushed per lock on a dirty scheduler.
+pub const LOCK_CHUNK: usize = 256;
+
+/// One price update: Unix milliseconds, price, volume.
+pub type Update = (i64, f64, f64);
+
+/// One batch entry: a pair, its updates and the config to decide on.
+pub type Entry<U> = (ResourceArc<Pair>, U, ConfigTerm);
+
+/// Result for one batch entry: updates accepted and the resulting decision.
+pub type EntryResult = (usize, Result<Option<DecisionTerm>, ErrorReason>);
+
+/// Results for every entry, or the first invalid price or config.
+pub type BatchResult = Result<Vec<EntryResult>, ErrorReason>;
+
+/// How [`apply`] takes each pair's lock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locking {
+    /// Skips an entry whose pair is locked, reporting `ErrorReason::Busy`.
+    Try,
+    /// Waits for the lock, taking it again every [`LOCK_CHUNK`] updates.
+    Chunked,
+}
+
+/// Decodes packed update records.
+pub fn unpack(bytes: &[u8]) -> NifResult<Vec<Update>> {
+    if !bytes.len().is_multiple_of(RECORD_SIZE) {
+        return Err(Error::BadArg);
+    }
+    bytes
+        .chunks_exact(RECORD_SIZE)
+        .map(|record| {
+            let field = |index: usize| -> NifResult<[u8; 8]> {
+                record[index * 8..(index + 1) * 8]
+                    .try_into()
+                    .map_err(|_| Error::BadArg)
+            };
+            Ok((
+                i64::from_le_bytes(field(0)?),
+                f64::from_le_bytes(field(1)?),
+                f64::from_le_bytes(field(2)?),
+            ))
+        })
+        .collect()
+}
+
+/// Validates every entry, then applies them in order.
+pub fn apply<P: Deref<Target = Pair>, U: AsRef<[Update]>>(
+    batch: &[(P, U, ConfigTerm)],
+    locking: Locking,
+) -> NifResult<BatchResult> {
+    let mut configs = Vec::with_capacity(batch.len());
+    for (_, updates, config) in batch {
+        for &(_, price, volume) in updates.as_ref() {
+            if finite(volume)? < 0.0 {
+                return Err(Error::BadArg);
+            }
+            if let Err(error) = check_price(price) {
+                return Ok(Err(error.into()));
+            }
+        }
+        match Config::try_from(*config) {
+            Ok(config) => configs.push(config),
+            Err(error) => return Ok(Err(error.into())),
+        }
+    }
+    let mut results = Vec::with_capacity(batch.len());
+    for ((pair, updates, _), config) in batch.iter().zip(&configs) {
+        let updates = updates.as_ref();
+        let (accepted, mut state) = match locking {
+            Locking::Try => {
+                let Some(mut state) = pair.try_lock() else {
+                    results.push((0, Err(ErrorReason::Busy)));
+                    continue;
+                };
+                (push(&mut state, updates), state)
+            }
+            Locking::Chunked => {
+                let accepted: usize = updates
+                    .chunks(LOCK_CHUNK)
+                    .map(|chunk| push(&mut pair.lock(), chunk))
+                    .sum();
+                (accepted, pair.lock())
+            }
+        };
+        let decision = state
+            .decide(&config.pool, &config.position)
+            .and_then(|decision| decision.map(DecisionTerm::try_from).transpose())
+            .map_err(Into::into);
+        results.push((accepted, decision));
+    }
+    Ok(Ok(results))
+}
+
+/// Pushes updates into a locked pair, returning how many were not stale.
+fn push(state: &mut PairState, updates: &[Update]) -> usize {
+    updates
+        .iter()
+        .filter(|&&(timestamp, price, _)| state.push(timestamp, price) == Ok(true))
+        .count()
+}
+
+fn unpack_batch(batch: Vec<Entry<Binary<'_>>>) -> NifResult<Vec<Entry<Vec<Update>>>> {
+    batch
+        .into_iter()
+        .map(|(pair, bytes, config)| Ok((pair, unpack(bytes.as_slice())?, config)))
+        .collect()
+}
+
+#[rustler::nif]
+fn push_batch_nif(batch: Vec<Entry<Vec<Update>>>) -> NifResult<BatchResult> {
+    apply(&batch, Locking::Try)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn push_batch_dirty(batch: Vec<Entry<Vec<Update>>>) -> NifResult<BatchResult> {
+    apply(&batch, Locking::Chunked)
+}
+
+#[rustler::nif]
+fn push_packed_batch_nif<'a>(batch: Vec<Entry<Binary<'a>>>) -> NifResult<BatchResult> {
+    apply(&unpack_batch(batch)?, Locking::Try)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn push_packed_batch_dirty<'a>(batch: Vec<Entry<Binary<'a>>>) -> NifResult<BatchResult> {
+    apply(&unpack_batch(batch)?, Locking::Chunked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decision::RuleTerm;
+    use crate::pair::PairOptions;
+    use std::sync::Arc;
+
+    const HOUR: i64 = 3_600_000;
+
+    fn pair() -> Arc<Pair> {
+        let options = PairOptions {
+            process_noise: 1e-8,
+            measurement_noise: 1e-6,
+            jolt_limit: 1.0,
+            dead_band: 0,
+            min_interval: 0,
+            window: 24 * HOUR,
+        };
+        Arc::new(Pair::new(PairState::new(options).unwrap()))
+    }
+
+    fn config(current_tick: i32) -> ConfigTerm {
+        ConfigTerm {
+            tick_lower: -100,
+            tick_upper: 100,
+            tick_spacing: 10,
+            current_tick,
+            base_price: 1.0,
+        }
+    }
+
+    #[test]
+    fn unpack_records() {
+        let mut bytes = Vec::new();
+        for (timestamp, price, volume) in [(1_i64, 2.5_f64, 10.0_f64), (-2, 3.0, 0.0)] {
+            bytes.extend(timestamp.to_le_bytes());
+            bytes.extend(price.to_le_bytes());
+            bytes.extend(volume.to_le_bytes());
+        }
+        assert_eq!(
+            unpack(&bytes).unwrap(),
+            vec![(1, 2.5, 10.0), (-2, 3.0, 0.0)]
+        );
+        assert!(unpack(&bytes[..RECORD_SIZE + 1]).is_err());
+        assert!(unpack(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn apply_updates_each_pair() {
+        let (first, second) = (pair(), pair());
+        let batch = [
+            (
+                first.clone(),
+                vec![(0, 1.0, 1.0), (HOUR, 1.0, 1.0)],
+                config(0),
+            ),
+            (
+                second.clone(),
+                vec![(0, 1.0, 1.0), (0, 1.0, 1.0)],
+                config(200),
+            ),
+            (first.clone(), vec![], config(0)),
+        ];
+        let results = apply(&batch, Locking::Try).unwrap().unwrap();
+        assert_eq!(results[0].0, 2);
+        let decision = |index: usize| results[index].1.clone().unwrap().unwrap();
+        assert_eq!(decision(0).rule, RuleTerm::InRange);
+        assert_eq!(results[1].0, 1);
+        assert_eq!(decision(1).rule, RuleTerm::OutOfRange);
+        assert_eq!(results[2].0, 0);
+        assert!(results[2].1.as_ref().unwrap().is_some());
+    }
+
+    #[test]
+    fn apply_validates_before_updating() {
+        let first = pair();
+        let batch = [
+            (first.clone(), vec![(0, 1.0, 1.0)], config(0)),
+            (pair(), vec![(0, f64::NAN, 1.0)], config(0)),
+        ];
+        assert_eq!(
+            apply(&batch, Locking::Try).unwrap(),
+            Err(ErrorReason::NonFinite)
+        );
+        let batch = [
+            (first.clone(), vec![(0, 1.0, 1.0)], config(0)),
+            (
+                pair(),
+                vec![(0, 1.0, 1.0)],
+                ConfigTerm {
+                    tick_lower: -105,
+                    ..config(0)
+                },
+            ),
+        ];
+        assert_eq!(
+            apply(&batch, Locking::Try).unwrap(),
+            Err(ErrorReason::TickMisaligned)
+        );
+        let batch = [(first.clone(), vec![(0, 1.0, -1.0)], config(0))];
+        assert!(apply(&batch, Locking::Chunked).is_err());
+        assert_eq!(first.lock().state(), None);
+    }
+
+    #[test]
+    fn apply_skips_locked_pairs() {
+        let (first, second) = (pair(), pair());
+        let batch = [
+            (first.clone(), vec![(0, 1.0, 1.0)], config(0)),
+            (second.clone(), vec![(0, 1.0, 1.0)], config(0)),
+        ];
+        let state = first.lock();
+        let results = apply(&batch, Locking::Try).unwrap().unwrap();
+        drop(state);
+        assert_eq!(results[0], (0, Err(ErrorReason::Busy)));
+        assert_eq!(results[1].0, 1);
+        assert_eq!(first.lock().state(), None);
+    }
+
+    #[test]
+    fn apply_chunks_long_updates() {
+        let pair = pair();
+        let count = 2 * LOCK_CHUNK + 1;
+        let updates = (0..count as i64).map(|hour| (hour * HOUR, 1.0, 1.0));
+        let batch = [(pair.clone(), updates.collect::<Vec<_>>(), config(0))];
+        let results = apply(&batch, Locking::Chunked).unwrap().unwrap();
+        assert_eq!(results[0].0, count);
+        assert!(results[0].1.as_ref().unwrap().is_some());
+    }
+}
@@ -0,0 +1,234 @@
+//! CLMM range and position evaluation NIFs.
+//!
+//! Configs arrive as maps with `:tick_lower`, `:tick_upper`, `:tick_spacing`,
+//! `:current_tick` and `:base_price`, split into a joltshark `PoolState` and
+//! `Position`, and states as `[position, velocity, acceleration, jolt]`
+//! lists. Each NIF returns
+//! `{:ok, value}`, or `{:error, reason}` when joltshark rejects the config or
+//! an argument, rather than producing a meaningless tick or price.
+
+use joltshark::decision::decide_clmm_position as decide;
+use joltshark::{
+    evaluate_clmm_position as evaluate, price_at_tick, tick_at_price, JoltError, Liquidity,
+    PoolState, Position, StateVector, Tick, TickRounding, TickSpacing,
+};
+use rustler::{Error, NifMap, NifResult};
+
+use crate::decision::{CommandTerm, DecisionTerm};
+use crate::error::ErrorReason;
+
+/// A position config decoded from an Elixir map.
+#[derive(NifMap, Clone, Copy, Debug, PartialEq)]
+pub struct ConfigTerm {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub tick_spacing: i32,
+    pub current_tick: i32,
+    pub base_price: f64,
+}
+
+/// A checked config: the pool at the current tick, the position's range
+/// and the price at tick 0. The pool has no liquidity or fee and the
+/// position no liquidity, since the map carries none.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    pub pool: PoolState,
+    pub position: Position,
+    pub base_price: f64,
+}
+
+impl TryFrom<ConfigTerm> for Config {
+    type Error = JoltError;
+
+    /// Checks the ticks, spacing, range and base price.
+    fn try_from(config: ConfigTerm) -> Result<Self, JoltError> {
+        let position = Position::new(
+            Tick::new(config.tick_lower)?,
+            Tick::new(config.tick_upper)?,
+            Liquidity::ZERO,
+        )?;
+        let pool = PoolState::at_tick(
+            Tick::new(config.current_tick)?,
+            Liquidity::ZERO,
+            0,
+            TickSpacing::new(config.tick_spacing)?,
+        )?;
+        position.validate(&pool)?;
+        if !config.base_price.is_finite() || config.base_price <= 0.0 {
+            return Err(JoltError::InvalidBasePrice);
+        }
+        Ok(Config {
+            pool,
+            position,
+            base_price: config.base_price,
+        })
+    }
+}
+
+/// Rejects NaN and infinite arguments.
+pub fn finite(value: f64) -> NifResult<f64> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(Error::BadArg)
+    }
+}
+
+/// Decodes a `[position, velocity, acceleration, jolt]` list.
+///
+/// Values are checked by joltshark, so only the length is checked here.
+pub fn state_vector(state: &[f64]) -> NifResult<StateVector<f64, 4>> {
+    let state: [f64; 4] = state.try_into().map_err(|_| Error::BadArg)?;
+    Ok(StateVector(state))
+}
+
+#[rustler::nif]
+fn clmm_in_range(config: ConfigTerm) -> Result<bool, ErrorReason> {
+    let config = Config::try_from(config)?;
+    Ok(config.position.is_in_range(&config.pool))
+}
+
+#[rustler::nif]
+fn clmm_round_tick(config: ConfigTerm, tick: i32) -> Result<i32, ErrorReason> {
+    let spacing = Config::try_from(config)?.pool.tick_spacing;
+    Ok(spacing.align(tick, TickRounding::Nearest).get())
+}
+
+#[rustler::nif]
+fn clmm_tick_at_price(config: ConfigTerm, price: f64) -> Result<i32, ErrorReason> {
+    let config = Config::try_from(config)?;
+    Ok(tick_at_price(config.base_price, price)?.get())
+}
+
+#[rustler::nif]
+fn clmm_price_at_tick(config: ConfigTerm, tick: i32) -> Result<f64, ErrorReason> {
+    let config = Config::try_from(config)?;
+    Ok(price_at_tick(config.base_price, Tick::new(tick)?)?)
+}
+
+#[rustler::nif]
+fn clmm_range_position(config: ConfigTerm) -> Result<f64, ErrorReason> {
+    let config = Config::try_from(config)?;
+    Ok(config.position.range_position(&config.pool)?)
+}
+
+#[rustler::nif]
+fn evaluate_clmm_position(
+    state: Vec<f64>,
+    config: ConfigTerm,
+    jolt_limit: f64,
+) -> NifResult<Result<CommandTerm, ErrorReason>> {
+    let state = state_vector(&state)?;
+    Ok(evaluate_term(&state, config, jolt_limit).map_err(Into::into))
+}
+
+/// Like `evaluate_clmm_position`, also returning the rule that fired.
+#[rustler::nif]
+fn decide_clmm_position(
+    state: Vec<f64>,
+    config: ConfigTerm,
+    jolt_limit: f64,
+) -> NifResult<Result<DecisionTerm, ErrorReason>> {
+    let state = state_vector(&state)?;
+    Ok(decide_term(&state, config, jolt_limit).map_err(Into::into))
+}
+
+fn evaluate_term(
+    state: &StateVector<f64, 4>,
+    config: ConfigTerm,
+    jolt_limit: f64,
+) -> Result<CommandTerm, JoltError> {
+    let config = Config::try_from(config)?;
+    Ok(evaluate(state, &config.pool, &config.position, jolt_limit)?.into())
+}
+
+fn decide_term(
+    state: &StateVector<f64, 4>,
+    config: ConfigTerm,
+    jolt_limit: f64,
+) -> Result<DecisionTerm, JoltError> {
+    let config = Config::try_from(config)?;
+    DecisionTerm::try_from(decide(state, &config.pool, &config.position, jolt_limit)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ConfigTerm {
+        ConfigTerm {
+            tick_lower: -100,
+            tick_upper: 100,
+            tick_spacing: 10,
+            current_tick: 0,
+            base_price: 1.0,
+        }
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(Config::try_from(config()).is_ok());
+        let invalid = [
+            (
+                ConfigTerm {
+                    tick_spacing: 0,
+                    ..config()
+                },
+                ErrorReason::InvalidSpacing,
+            ),
+            (
+                ConfigTerm {
+                    tick_lower: 100,
+                    ..config()
+                },
+                ErrorReason::RangeInverted,
+            ),
+            (
+                ConfigTerm {
+                    tick_upper: 105,
+                    ..config()
+                },
+                ErrorReason::TickMisaligned,
+            ),
+            (
+                ConfigTerm {
+                    base_price: 0.0,
+                    ..config()
+                },
+                ErrorReason::InvalidBasePrice,
+            ),
+            (
+                ConfigTerm {
+                    base_price: f64::NAN,
+                    ..config()
+                },
+                ErrorReason::InvalidBasePrice,
+            ),
+        ];
+        for (config, reason) in invalid {
+            let error = Config::try_from(config).map(|_| ()).unwrap_err();
+            assert_eq!(ErrorReason::from(error), reason, "{config:?}");
+        }
+    }
+
+    #[test]
+    fn state_vector_validation() {
+        assert!(state_vector(&[0.0, 1.0, 2.0, 3.0]).is_ok());
+        assert!(state_vector(&[0.0, 1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn evaluation_errors() {
+        let state = state_vector(&[0.0, 1.0, f64::INFINITY, 3.0]).unwrap();
+        assert_eq!(
+            evaluate_term(&state, config(), 1.0),
+            Err(JoltError::NonFinite)
+        );
+        let state = state_vector(&[0.0; 4]).unwrap();
+        assert_eq!(
+            decide_term(&state, config(), f64::NAN).map(|_| ()),
+            Err(JoltError::NonFinite)
+        );
+        assert_eq!(evaluate_term(&state, config(), 1.0), Ok(CommandTerm::Hold));
+    }
+}
@@ -0,0 +1,185 @@
+//! Elixir encodings of joltshark decisions.
+//!
+//! Commands encode as atoms or tagged tuples, for example `:hold`,
+//! `{:wait, :dead_band}` or `{:rebalance, %{new_tick_lower: -100, new_tick_upper: 100}}`.
+//! A decision encodes as a map of the command, the rule that fired and the
+//! diagnostics it was judged on.
+
+use joltshark::decision::{Decision, Rule};
+use joltshark::{CLMMCommand, JoltError, WaitReason};
+use rustler::{NifMap, NifTaggedEnum, NifUnitEnum};
+
+/// Why a rebalance was replaced by a wait.
+#[derive(NifUnitEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaitReasonTerm {
+    TransactionCost,
+    DeadBand,
+    MinInterval,
+    WindowLimit,
+}
+
+impl From<WaitReason> for WaitReasonTerm {
+    fn from(reason: WaitReason) -> Self {
+        match reason {
+            WaitReason::TransactionCost => WaitReasonTerm::TransactionCost,
+            WaitReason::DeadBand => WaitReasonTerm::DeadBand,
+            WaitReason::MinInterval => WaitReasonTerm::MinInterval,
+            WaitReason::WindowLimit => WaitReasonTerm::WindowLimit,
+        }
+    }
+}
+
+/// A `CLMMCommand` as an Elixir term.
+#[derive(NifTaggedEnum, Clone, Debug, PartialEq)]
+pub enum CommandTerm {
+    AddLiquidity {
+        tick_lower: i32,
+        tick_upper: i32,
+        amount: u128,
+    },
+    RemoveLiquidity {
+        amount: u128,
+    },
+    Rebalance {
+        new_tick_lower: i32,
+        new_tick_upper: i32,
+    },
+    CollectFees,
+    Hold,
+    Wait(WaitReasonTerm),
+    Exit,
+}
+
+impl From<CLMMCommand> for CommandTerm {
+    fn from(command: CLMMCommand) -> Self {
+        match command {
+            CLMMCommand::AddLiquidity {
+                tick_lower,
+                tick_upper,
+                amount,
+            } => CommandTerm::AddLiquidity {
+                tick_lower: tick_lower.get(),
+                tick_upper: tick_upper.get(),
+                amount: amount.get(),
+            },
+            CLMMCommand::RemoveLiquidity { amount } => CommandTerm::RemoveLiquidity {
+                amount: amount.get(),
+            },
+            CLMMCommand::Rebalance {
+                new_tick_lower,
+                new_tick_upper,
+            } => CommandTerm::Rebalance {
+                new_tick_lower: new_tick_lower.get(),
+                new_tick_upper: new_tick_upper.get(),
+            },
+            CLMMCommand::CollectFees => CommandTerm::CollectFees,
+            CLMMCommand::Hold => CommandTerm::Hold,
+            CLMMCommand::Wait(reason) => CommandTerm::Wait(reason.into()),
+            CLMMCommand::Exit => CommandTerm::Exit,
+        }
+    }
+}
+
+/// The rule that fired, flattened to a single atom.
+#[derive(NifUnitEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleTerm {
+    JoltLimit,
+    OutOfRange,
+    InRange,
+    TransactionCost,
+    DeadBand,
+    MinInterval,
+    WindowLimit,
+}
+
+impl From<Rule> for RuleTerm {
+    fn from(rule: Rule) -> Self {
+        match rule {
+            Rule::JoltLimit => RuleTerm::JoltLimit,
+            Rule::OutOfRange => RuleTerm::OutOfRange,
+            Rule::InRange => RuleTerm::InRange,
+            Rule::Suppressed(WaitReason::TransactionCost) => RuleTerm::TransactionCost,
+            Rule::Suppressed(WaitReason::DeadBand) => RuleTerm::DeadBand,
+            Rule::Suppressed(WaitReason::MinInterval) => RuleTerm::MinInterval,
+            Rule::Suppressed(WaitReason::WindowLimit) => RuleTerm::WindowLimit,
+        }
+    }
+}
+
+/// A `Decision` as an Elixir map. `jolt` is `nil` when the state has none.
+#[derive(NifMap, Clone, Debug, PartialEq)]
+pub struct DecisionTerm {
+    pub command: CommandTerm,
+    pub rule: RuleTerm,
+    pub jolt: Option<f64>,
+    pub jolt_limit: f64,
+    pub range_position: f64,
+    pub current_tick: i32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+}
+
+impl TryFrom<Decision<f64>> for DecisionTerm {
+    type Error = JoltError;
+
+    fn try_from(decision: Decision<f64>) -> Result<Self, JoltError> {
+        let diagnostics = decision.diagnostics;
+        Ok(DecisionTerm {
+            command: decision.command.into(),
+            rule: decision.rule.into(),
+            jolt: diagnostics.jolt,
+            jolt_limit: diagnostics.jolt_limit,
+            range_position: diagnostics.range_position()?,
+            current_tick: diagnostics.current_tick.get(),
+            tick_lower: diagnostics.tick_lower.get(),
+            tick_upper: diagnostics.tick_upper.get(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joltshark::decision::decide_clmm_position;
+    use joltshark::StateVector;
+
+    use crate::clmm::{Config, ConfigTerm};
+
+    fn config(current_tick: i32) -> Config {
+        Config::try_from(ConfigTerm {
+            tick_lower: -100,
+            tick_upper: 100,
+            tick_spacing: 10,
+            current_tick,
+            base_price: 1.0,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn decision_term_carries_diagnostics() {
+        let state = StateVector([0.0, 0.0, 0.0, 2.0]);
+        let config = config(50);
+        let decision = decide_clmm_position(&state, &config.pool, &config.position, 1.0).unwrap();
+        let term = DecisionTerm::try_from(decision).unwrap();
+        assert_eq!(term.command, CommandTerm::Exit);
+        assert_eq!(term.rule, RuleTerm::JoltLimit);
+        assert_eq!(term.jolt, Some(2.0));
+        assert_eq!(term.range_position, 0.75);
+    }
+
+    #[test]
+    fn suppressed_rule_flattens_to_reason() {
+        let state = StateVector([0.0; 4]);
+        let config = config(200);
+        let decision = decide_clmm_position(&state, &config.pool, &config.position, 1.0)
+            .unwrap()
+            .check_cost(0.0, 1.0);
+        let term = DecisionTerm::try_from(decision).unwrap();
+        assert_eq!(
+            term.command,
+            CommandTerm::Wait(WaitReasonTerm::TransactionCost)
+        );
+        assert_eq!(term.rule, RuleTerm::TransactionCost);
+    }
+}
@@ -0,0 +1,72 @@
+//! Elixir encoding of joltshark errors.
+//!
+//! Each `JoltError` variant maps to one atom, so callers can match on
+//! `{:error, :non_positive_price}` and the like; `:busy` is added for a pair
+//! handle locked by another call. Malformed terms, such as a config that is
+//! not a map or a state of the wrong length, still raise `ArgumentError`.
+
+use joltshark::JoltError;
+use rustler::NifUnitEnum;
+
+/// The reason in an `{:error, reason}` tuple.
+#[derive(NifUnitEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorReason {
+    NonFinite,
+    NonPositivePrice,
+    TickOutOfBounds,
+    InvalidBasePrice,
+    InvalidSpacing,
+    RangeInverted,
+    RangeBeyondBounds,
+    TickMisaligned,
+    RangesUnordered,
+    RangesOverlap,
+    RangesGap,
+    SqrtPriceOutOfBounds,
+    EmptyRange,
+    TickPriceMismatch,
+    InvalidFeeRate,
+    InvalidInterval,
+    InvalidThreshold,
+    InvalidNoise,
+    InvalidShare,
+    InvalidPriceLimit,
+    LiquidityUnderflow,
+    TickMapFull,
+    Overflow,
+    DivisionByZero,
+    Conversion,
+    Busy,
+}
+
+impl From<JoltError> for ErrorReason {
+    fn from(error: JoltError) -> Self {
+        match error {
+            JoltError::NonFinite => ErrorReason::NonFinite,
+            JoltError::NonPositivePrice => ErrorReason::NonPositivePrice,
+            JoltError::TickOutOfBounds(_) => ErrorReason::TickOutOfBounds,
+            JoltError::InvalidBasePrice => ErrorReason::InvalidBasePrice,
+            JoltError::InvalidSpacing(_) => ErrorReason::InvalidSpacing,
+            JoltError::RangeInverted { .. } => ErrorReason::RangeInverted,
+            JoltError::RangeBeyondBounds { .. } => ErrorReason::RangeBeyondBounds,
+            JoltError::TickMisaligned { .. } => ErrorReason::TickMisaligned,
+            JoltError::RangesUnordered(..) => ErrorReason::RangesUnordered,
+            JoltError::RangesOverlap(..) => ErrorReason::RangesOverlap,
+            JoltError::RangesGap(..) => ErrorReason::RangesGap,
+            JoltError::SqrtPriceOutOfBounds(_) => ErrorReason::SqrtPriceOutOfBounds,
+            JoltError::EmptyRange => ErrorReason::EmptyRange,
+            JoltError::TickPriceMismatch { .. } => ErrorReason::TickPriceMismatch,
+            JoltError::InvalidFeeRate(_) => ErrorReason::InvalidFeeRate,
+            JoltError::InvalidInterval => ErrorReason::InvalidInterval,
+            JoltError::InvalidThreshold => ErrorReason::InvalidThreshold,
+            JoltError::InvalidNoise => ErrorReason::InvalidNoise,
+            JoltError::InvalidShare(_) => ErrorReason::InvalidShare,
+            JoltError::InvalidPriceLimit(_) => ErrorReason::InvalidPriceLimit,
+            JoltError::LiquidityUnderflow => ErrorReason::LiquidityUnderflow,
+            JoltError::TickMapFull => ErrorReason::TickMapFull,
+            JoltError::Overflow => ErrorReason::Overflow,
+            JoltError::DivisionByZero => ErrorReason::DivisionByZero,
+            JoltError::Conversion => ErrorReason::Conversion,
+        }
+    }
+}
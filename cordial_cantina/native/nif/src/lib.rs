@@ -3,9 +3,19 @@
 //! This crate provides Erlang NIF bindings for the Cordial Cantina trading system.
 //! It exposes Rust functions from joltshark to the Elixir application via Rustler.
 
+mod batch;
+mod clmm;
+mod decision;
+mod error;
+mod pair;
+mod signal;
+
 mod atoms {
     rustler::atoms! {
+        busy,
+        error,
         ok,
+        stale,
     }
 }
 
@@ -26,6 +36,6 @@ mod tests {
     #[test]
     fn joltshark_accessible() {
         // Verify joltshark types are accessible
-        let _: joltshark::GridCommand<f64> = joltshark::GridCommand::Hold;
+        let _: joltshark::CLMMCommand = joltshark::CLMMCommand::Hold;
     }
 }
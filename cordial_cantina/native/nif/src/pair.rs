@@ -0,0 +1,330 @@
+//! Per-token-pair estimator and strategy state held in Rust.
+//!
+//! A pair handle is a `ResourceArc` owning a constant-jerk Kalman estimator
+//! over the log price and a `PositionEvaluator`, so each tick crosses the NIF
+//! boundary once instead of resending the price history. Time is fed to the
+//! estimator in hours, as in the backtest, so jolt limits carry over. The
+//! state sits behind a mutex and handles can be shared freely between the
+//! Broadway processes; updates to one pair are serialized. These NIFs run on
+//! a normal scheduler and never wait for the lock: a call that finds it held
+//! returns `:busy` and leaves the pair unchanged, for the caller to retry.
+//!
+//! Invalid options raise `ArgumentError`. Invalid prices and configs return
+//! `{:error, reason}` as in the CLMM NIFs.
+
+use std::sync::{Mutex, MutexGuard, PoisonError, TryLockError};
+
+use joltshark::decision::Decision;
+use joltshark::estimator::Estimator;
+use joltshark::evaluator::{EvaluatorConfig, PositionEvaluator};
+use joltshark::filter::Kalman;
+use joltshark::{check_price, JoltError, PoolState, Position, StateVector};
+use rustler::{Atom, Error, NifMap, NifResult, NifUntaggedEnum, Resource, ResourceArc};
+
+use crate::atoms;
+use crate::clmm::{finite, Config, ConfigTerm};
+use crate::decision::DecisionTerm;
+use crate::error::ErrorReason;
+
+/// Rebalances allowed within one evaluator window.
+pub const MAX_REBALANCES_PER_WINDOW: usize = 4;
+
+const MILLISECONDS_PER_HOUR: f64 = 3_600_000.0;
+
+/// Options for a new pair handle. Intervals are in milliseconds.
+#[derive(NifMap, Clone, Copy, Debug, PartialEq)]
+pub struct PairOptions {
+    pub process_noise: f64,
+    pub measurement_noise: f64,
+    pub jolt_limit: f64,
+    /// Ticks beyond a range boundary before a rebalance is considered
+    pub dead_band: u32,
+    pub min_interval: i64,
+    pub window: i64,
+}
+
+/// Estimator and evaluator for one token pair.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PairState {
+    estimator: Kalman<f64, 4>,
+    evaluator: PositionEvaluator<f64, MAX_REBALANCES_PER_WINDOW>,
+    last_timestamp: Option<i64>,
+}
+
+impl PairState {
+    /// Rejects a negative jolt limit and the noise and intervals the
+    /// estimator and evaluator reject. A negative dead band fails to decode.
+    pub fn new(options: PairOptions) -> NifResult<Self> {
+        if finite(options.jolt_limit)? < 0.0 {
+            return Err(Error::BadArg);
+        }
+        Ok(PairState {
+            estimator: Kalman::new(options.process_noise, options.measurement_noise, 1.0)
+                .map_err(|_| Error::BadArg)?,
+            evaluator: PositionEvaluator::new(EvaluatorConfig {
+                jolt_limit: options.jolt_limit,
+                dead_band: options.dead_band,
+                min_interval: hours(options.min_interval),
+                window: hours(options.window),
+            })
+            .map_err(|_| Error::BadArg)?,
+            last_timestamp: None,
+        })
+    }
+
+    /// Feeds a price at a Unix millisecond timestamp.
+    ///
+    /// Returns `false` for a timestamp not after the previous one.
+    pub fn push(&mut self, timestamp: i64, price: f64) -> Result<bool, JoltError> {
+        if !self
+            .estimator
+            .push(hours(timestamp), check_price(price)?.ln())
+        {
+            return Ok(false);
+        }
+        self.last_timestamp = Some(timestamp);
+        Ok(true)
+    }
+
+    /// Log price and its derivatives per hour at the latest tick.
+    pub fn state(&self) -> Option<StateVector<f64, 4>> {
+        self.estimator.state()
+    }
+
+    /// Decides on `position` in `pool` at the latest tick, or `None` before
+    /// any tick.
+    ///
+    /// An issued rebalance counts against the evaluator's rate limits.
+    pub fn decide(
+        &mut self,
+        pool: &PoolState,
+        position: &Position,
+    ) -> Result<Option<Decision<f64>>, JoltError> {
+        let (Some(state), Some(timestamp)) = (self.state(), self.last_timestamp) else {
+            return Ok(None);
+        };
+        self.evaluator
+            .decide(hours(timestamp), &state, pool, position)
+            .map(Some)
+    }
+}
+
+fn hours(milliseconds: i64) -> f64 {
+    milliseconds as f64 / MILLISECONDS_PER_HOUR
+}
+
+/// Shared handle to a [`PairState`].
+pub struct Pair {
+    state: Mutex<PairState>,
+}
+
+#[rustler::resource_impl]
+impl Resource for Pair {}
+
+impl Pair {
+    pub fn new(state: PairState) -> Self {
+        Pair {
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Locks the state. Updates cannot panic partway through, so a poisoned
+    /// lock is recovered rather than propagated.
+    pub fn lock(&self) -> MutexGuard<'_, PairState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the state without waiting, or returns `None` while another call
+    /// holds it.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, PairState>> {
+        match self.state.try_lock() {
+            Ok(state) => Some(state),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+}
+
+#[rustler::nif]
+fn pair_new(options: PairOptions) -> NifResult<ResourceArc<Pair>> {
+    Ok(ResourceArc::new(Pair::new(PairState::new(options)?)))
+}
+
+/// `:ok`, `:stale`, `:busy` or `{:error, reason}` for one pushed price.
+#[derive(NifUntaggedEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PushTerm {
+    Status(Atom),
+    Error((Atom, ErrorReason)),
+}
+
+impl From<Result<bool, JoltError>> for PushTerm {
+    fn from(result: Result<bool, JoltError>) -> Self {
+        match result {
+            Ok(true) => PushTerm::Status(atoms::ok()),
+            Ok(false) => PushTerm::Status(atoms::stale()),
+            Err(error) => PushTerm::Error((atoms::error(), error.into())),
+        }
+    }
+}
+
+/// The state vector, `nil` before any tick, or `:busy`.
+#[derive(NifUntaggedEnum, Clone, Debug, PartialEq)]
+pub enum StateTerm {
+    Busy(Atom),
+    State(Option<Vec<f64>>),
+}
+
+/// Returns `:ok`, or `:stale` when the timestamp is not after the last one.
+#[rustler::nif]
+fn pair_push(pair: ResourceArc<Pair>, timestamp: i64, price: f64) -> PushTerm {
+    match pair.try_lock() {
+        Some(mut state) => state.push(timestamp, price).into(),
+        None => PushTerm::Status(atoms::busy()),
+    }
+}
+
+#[rustler::nif]
+fn pair_state(pair: ResourceArc<Pair>) -> StateTerm {
+    match pair.try_lock() {
+        Some(state) => StateTerm::State(state.state().map(|state| state.0.to_vec())),
+        None => StateTerm::Busy(atoms::busy()),
+    }
+}
+
+#[rustler::nif]
+fn pair_decide(
+    pair: ResourceArc<Pair>,
+    config: ConfigTerm,
+) -> Result<Option<DecisionTerm>, ErrorReason> {
+    let config = Config::try_from(config)?;
+    let decision = pair
+        .try_lock()
+        .ok_or(ErrorReason::Busy)?
+        .decide(&config.pool, &config.position)?;
+    Ok(decision.map(DecisionTerm::try_from).transpose()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use joltshark::decision::Rule;
+    use joltshark::CLMMCommand;
+
+    const HOUR: i64 = 3_600_000;
+
+    fn options() -> PairOptions {
+        PairOptions {
+            process_noise: 1e-8,
+            measurement_noise: 1e-6,
+            jolt_limit: 1.0,
+            dead_band: 0,
+            min_interval: 2 * HOUR,
+            window: 24 * HOUR,
+        }
+    }
+
+    fn config(current_tick: i32) -> Config {
+        Config::try_from(ConfigTerm {
+            tick_lower: -100,
+            tick_upper: 100,
+            tick_spacing: 10,
+            current_tick,
+            base_price: 1.0,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        assert!(PairState::new(options()).is_ok());
+        let invalid = [
+            PairOptions {
+                measurement_noise: 0.0,
+                ..options()
+            },
+            PairOptions {
+                jolt_limit: f64::NAN,
+                ..options()
+            },
+            PairOptions {
+                window: -1,
+                ..options()
+            },
+        ];
+        for options in invalid {
+            assert!(PairState::new(options).is_err(), "{options:?}");
+        }
+    }
+
+    #[test]
+    fn push_tracks_log_price() {
+        let mut pair = PairState::new(options()).unwrap();
+        assert_eq!(pair.state(), None);
+        let config = config(0);
+        assert_eq!(pair.decide(&config.pool, &config.position), Ok(None));
+        for hour in 0..10 {
+            assert!(pair.push(hour * HOUR, 2.0).unwrap());
+        }
+        assert!(!pair.push(5 * HOUR, 2.0).unwrap());
+        assert_eq!(pair.push(11 * HOUR, 0.0), Err(JoltError::NonPositivePrice));
+        assert_eq!(pair.push(12 * HOUR, f64::NAN), Err(JoltError::NonFinite));
+        let state = pair.state().unwrap();
+        assert!((state.0[0] - 2.0_f64.ln()).abs() < 1e-6);
+        assert!(state.0[1].abs() < 1e-6);
+    }
+
+    #[test]
+    fn decide_applies_min_interval() {
+        let mut pair = PairState::new(options()).unwrap();
+        let config = config(200);
+        pair.push(0, 1.0).unwrap();
+        let decision = pair
+            .decide(&config.pool, &config.position)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(decision.command, CLMMCommand::Rebalance { .. }));
+        pair.push(HOUR, 1.0).unwrap();
+        let decision = pair
+            .decide(&config.pool, &config.position)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            decision.rule,
+            Rule::Suppressed(joltshark::WaitReason::MinInterval)
+        );
+    }
+
+    #[test]
+    fn handle_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Pair>();
+        let pair = Pair::new(PairState::new(options()).unwrap());
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let pair = &pair;
+                scope.spawn(move || {
+                    pair.lock().push(thread * HOUR, 1.0).unwrap();
+                });
+            }
+        });
+        assert!(pair.lock().state().is_some());
+    }
+
+    #[test]
+    fn try_lock_fails_while_held() {
+        let pair = Pair::new(PairState::new(options()).unwrap());
+        let state = pair.lock();
+        assert!(pair.try_lock().is_none());
+        drop(state);
+        assert!(pair.try_lock().is_some());
+    }
+
+    #[test]
+    fn push_term_encodes_status() {
+        assert_eq!(PushTerm::from(Ok(true)), PushTerm::Status(atoms::ok()));
+        assert_eq!(
+            PushTerm::from(Err(JoltError::NonFinite)),
+            PushTerm::Error((atoms::error(), ErrorReason::NonFinite))
+        );
+    }
+}
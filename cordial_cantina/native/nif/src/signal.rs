@@ -0,0 +1,150 @@
+//! Range mapping and cyclic signal NIFs.
+//!
+//! Every argument must be finite and every range must have distinct ends,
+//! and an event window must not span a whole number of cycles; anything else
+//! raises `ArgumentError`, as does a result that overflows or is undefined,
+//! such as a position far outside a narrow range.
+
+use std::f64::consts::TAU;
+
+use rustler::{Error, NifResult};
+
+use crate::clmm::finite;
+
+/// Validates a `[start, end]` range that is divided by.
+fn span(start: f64, end: f64) -> NifResult<(f64, f64)> {
+    if finite(finite(end)? - finite(start)?)? == 0.0 {
+        return Err(Error::BadArg);
+    }
+    Ok((start, end))
+}
+
+/// Validates an event window whose length is taken modulo τ.
+fn cycle_span(start: f64, end: f64) -> NifResult<(f64, f64)> {
+    let (start, end) = span(start, end)?;
+    if (end - start).rem_euclid(TAU) == 0.0 {
+        return Err(Error::BadArg);
+    }
+    Ok((start, end))
+}
+
+#[rustler::nif]
+fn range_clamp(position: f64, range_start: f64, range_end: f64) -> NifResult<f64> {
+    finite(joltshark::range_clamp(
+        finite(position)?,
+        finite(range_start)?,
+        finite(range_end)?,
+    ))
+}
+
+#[rustler::nif]
+fn range_normalize(position: f64, range_start: f64, range_end: f64) -> NifResult<f64> {
+    let (start, end) = span(range_start, range_end)?;
+    finite(joltshark::range_normalize(finite(position)?, start, end))
+}
+
+#[rustler::nif]
+fn range_normalize_clamped(position: f64, range_start: f64, range_end: f64) -> NifResult<f64> {
+    let (start, end) = span(range_start, range_end)?;
+    finite(joltshark::range_normalize_clamped(
+        finite(position)?,
+        start,
+        end,
+    ))
+}
+
+#[rustler::nif]
+fn range_map(
+    position: f64,
+    range_start: f64,
+    range_end: f64,
+    blend_start: f64,
+    blend_end: f64,
+) -> NifResult<f64> {
+    let (start, end) = span(range_start, range_end)?;
+    finite(joltshark::range_map(
+        finite(position)?,
+        start,
+        end,
+        finite(blend_start)?,
+        finite(blend_end)?,
+    ))
+}
+
+#[rustler::nif]
+fn range_map_clamped(
+    position: f64,
+    range_start: f64,
+    range_end: f64,
+    blend_start: f64,
+    blend_end: f64,
+) -> NifResult<f64> {
+    let (start, end) = span(range_start, range_end)?;
+    finite(joltshark::range_map_clamped(
+        finite(position)?,
+        start,
+        end,
+        finite(blend_start)?,
+        finite(blend_end)?,
+    ))
+}
+
+#[rustler::nif]
+fn cycle_interpolate(phase: f64, primary_signal: f64, alternative_signal: f64) -> NifResult<f64> {
+    finite(joltshark::cycle_interpolate(
+        finite(phase)?,
+        finite(primary_signal)?,
+        finite(alternative_signal)?,
+    ))
+}
+
+/// Returns the pulse as a `{cos, sin}` tuple.
+#[rustler::nif]
+fn event_pulse(
+    phase: f64,
+    event_start: f64,
+    event_end: f64,
+    blend_outer: f64,
+    blend_inner: f64,
+) -> NifResult<(f64, f64)> {
+    let (start, end) = cycle_span(event_start, event_end)?;
+    let (cos, sin) = joltshark::event_pulse(
+        finite(phase)?,
+        start,
+        end,
+        finite(blend_outer)?,
+        finite(blend_inner)?,
+    );
+    Ok((finite(cos)?, finite(sin)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_rejects_degenerate_ranges() {
+        assert!(span(0.0, 1.0).is_ok());
+        assert!(span(1.0, 1.0).is_err());
+        assert!(span(0.0, f64::NAN).is_err());
+        assert!(span(-f64::MAX, f64::MAX).is_err());
+    }
+
+    #[test]
+    fn cycle_span_rejects_whole_cycles() {
+        assert!(cycle_span(1.0, 2.0).is_ok());
+        assert!(cycle_span(6.0, 1.0).is_ok());
+        assert!(cycle_span(1.0, 1.0 - TAU).is_err());
+        assert!(cycle_span(0.0, TAU).is_err());
+        assert!(cycle_span(1.0, 1.0 + 2.0 * TAU).is_err());
+        assert!(cycle_span(-TAU, 0.0).is_err());
+    }
+
+    #[test]
+    fn results_are_checked() {
+        let (start, end) = span(0.0, 1e-300).unwrap();
+        assert!(finite(joltshark::range_normalize(1e10, start, end)).is_err());
+        assert!(finite(joltshark::range_map(1.0, start, end, 0.0, 1e300)).is_err());
+        assert!(finite(joltshark::cycle_interpolate(0.5, f64::MAX, f64::MAX)).is_err());
+    }
+}
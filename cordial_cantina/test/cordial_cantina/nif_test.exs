@@ -6,4 +6,256 @@ defmodule CordialCantina.NifTest do
       assert CordialCantina.Nif.nop() == :ok
     end
   end
+
+  describe "decide_clmm_position/3" do
+    @config %{
+      tick_lower: -100,
+      tick_upper: 100,
+      tick_spacing: 10,
+      current_tick: 200,
+      base_price: 1.0
+    }
+
+    test "explains a rebalance" do
+      {:ok, decision} =
+        CordialCantina.Nif.decide_clmm_position([0.0, 0.0, 0.0, 0.0], @config, 1.0)
+
+      assert decision.command == {:rebalance, %{new_tick_lower: 100, new_tick_upper: 300}}
+      assert decision.rule == :out_of_range
+      assert decision.range_position == 1.0
+      assert decision.jolt == 0.0
+    end
+
+    test "raises on a malformed state" do
+      assert_raise ArgumentError, fn ->
+        CordialCantina.Nif.decide_clmm_position([0.0], @config, 1.0)
+      end
+    end
+  end
+
+  describe "evaluate_clmm_position/3" do
+    @config %{
+      tick_lower: -100,
+      tick_upper: 100,
+      tick_spacing: 10,
+      current_tick: 0,
+      base_price: 1.0
+    }
+
+    test "holds in range and exits on jolt" do
+      assert CordialCantina.Nif.evaluate_clmm_position([0.0, 0.0, 0.0, 0.0], @config, 1.0) ==
+               {:ok, :hold}
+
+      assert CordialCantina.Nif.evaluate_clmm_position([0.0, 0.0, 0.0, 5.0], @config, 1.0) ==
+               {:ok, :exit}
+    end
+
+    test "returns an error for an invalid config" do
+      state = [0.0, 0.0, 0.0, 0.0]
+      zero_spacing = %{@config | tick_spacing: 0}
+      inverted = %{@config | tick_lower: 100}
+      misaligned = %{@config | tick_lower: -105}
+
+      assert CordialCantina.Nif.evaluate_clmm_position(state, zero_spacing, 1.0) ==
+               {:error, :invalid_spacing}
+
+      assert CordialCantina.Nif.evaluate_clmm_position(state, inverted, 1.0) ==
+               {:error, :range_inverted}
+
+      assert CordialCantina.Nif.evaluate_clmm_position(state, misaligned, 1.0) ==
+               {:error, :tick_misaligned}
+    end
+  end
+
+  describe "CLMM config functions" do
+    @config %{
+      tick_lower: -100,
+      tick_upper: 100,
+      tick_spacing: 10,
+      current_tick: 50,
+      base_price: 1.0
+    }
+
+    test "match the range" do
+      assert CordialCantina.Nif.clmm_in_range(@config) == {:ok, true}
+      assert CordialCantina.Nif.clmm_round_tick(@config, 14) == {:ok, 10}
+      assert CordialCantina.Nif.clmm_round_tick(@config, -15) == {:ok, -10}
+      assert CordialCantina.Nif.clmm_round_tick(@config, 1_000_000) == {:ok, 443_630}
+      assert CordialCantina.Nif.clmm_range_position(@config) == {:ok, 0.75}
+      assert {:ok, price} = CordialCantina.Nif.clmm_price_at_tick(@config, 100)
+      assert_in_delta price, 1.0001 ** 100, 1.0e-12
+    end
+
+    test "return distinct errors" do
+      assert CordialCantina.Nif.clmm_tick_at_price(@config, 1.0) == {:ok, 0}
+
+      assert CordialCantina.Nif.clmm_tick_at_price(@config, 0.0) ==
+               {:error, :non_positive_price}
+
+      assert CordialCantina.Nif.clmm_tick_at_price(@config, 1.0e300) ==
+               {:error, :tick_out_of_bounds}
+
+      assert CordialCantina.Nif.clmm_price_at_tick(@config, 1_000_000) ==
+               {:error, :tick_out_of_bounds}
+
+      assert CordialCantina.Nif.clmm_in_range(%{@config | base_price: 0.0}) ==
+               {:error, :invalid_base_price}
+    end
+  end
+
+  describe "range and cycle functions" do
+    test "map positions" do
+      assert CordialCantina.Nif.range_clamp(5.0, 0.0, 1.0) == 1.0
+      assert CordialCantina.Nif.range_normalize(5.0, 0.0, 10.0) == 0.5
+      assert CordialCantina.Nif.range_map_clamped(20.0, 0.0, 10.0, 1.0, 3.0) == 3.0
+      assert CordialCantina.Nif.cycle_interpolate(0.0, 2.0, 3.0) == 2.0
+    end
+
+    test "event_pulse is neutral outside the event" do
+      assert CordialCantina.Nif.event_pulse(:math.pi(), 0.5, 1.0, 0.1, 0.1) == {1.0, 0.0}
+    end
+
+    test "raise on a degenerate range" do
+      assert_raise ArgumentError, fn -> CordialCantina.Nif.range_normalize(1.0, 2.0, 2.0) end
+    end
+
+    test "raise on a whole-cycle event or an overflowing result" do
+      assert_raise ArgumentError, fn ->
+        CordialCantina.Nif.event_pulse(0.5, 0.0, 2 * :math.pi(), 0.1, 0.1)
+      end
+
+      assert_raise ArgumentError, fn ->
+        CordialCantina.Nif.range_map(1.0, 0.0, 1.0e-300, 0.0, 1.0e300)
+      end
+    end
+  end
+
+  describe "pair handles" do
+    @options %{
+      process_noise: 1.0e-8,
+      measurement_noise: 1.0e-6,
+      jolt_limit: 1.0,
+      dead_band: 0,
+      min_interval: 7_200_000,
+      window: 86_400_000
+    }
+    @config %{
+      tick_lower: -100,
+      tick_upper: 100,
+      tick_spacing: 10,
+      current_tick: 200,
+      base_price: 1.0
+    }
+
+    test "track pushed prices" do
+      pair = CordialCantina.Nif.pair_new(@options)
+      assert CordialCantina.Nif.pair_state(pair) == nil
+      assert CordialCantina.Nif.pair_decide(pair, @config) == {:ok, nil}
+
+      assert CordialCantina.Nif.pair_push(pair, 0, 2.0) == :ok
+      assert CordialCantina.Nif.pair_push(pair, 0, 2.0) == :stale
+      assert [log_price, _, _, _] = CordialCantina.Nif.pair_state(pair)
+      assert_in_delta log_price, :math.log(2.0), 1.0e-9
+    end
+
+    test "rate limit rebalances" do
+      pair = CordialCantina.Nif.pair_new(@options)
+      :ok = CordialCantina.Nif.pair_push(pair, 0, 1.0)
+      assert {:ok, %{rule: :out_of_range}} = CordialCantina.Nif.pair_decide(pair, @config)
+
+      :ok = CordialCantina.Nif.pair_push(pair, 3_600_000, 1.0)
+      assert {:ok, %{command: {:wait, :min_interval}}} =
+               CordialCantina.Nif.pair_decide(pair, @config)
+    end
+
+    test "can be shared between processes" do
+      pair = CordialCantina.Nif.pair_new(@options)
+
+      1..4
+      |> Task.async_stream(fn hour ->
+        CordialCantina.Nif.pair_push(pair, hour * 3_600_000, 1.0)
+      end)
+      |> Enum.each(fn {:ok, result} -> assert result in [:ok, :stale, :busy] end)
+
+      assert CordialCantina.Nif.pair_state(pair) != nil
+    end
+
+    test "reject invalid input" do
+      assert_raise ArgumentError, fn ->
+        CordialCantina.Nif.pair_new(%{@options | measurement_noise: 0.0})
+      end
+
+      assert_raise ArgumentError, fn ->
+        CordialCantina.Nif.pair_new(%{@options | dead_band: -1})
+      end
+
+      pair = CordialCantina.Nif.pair_new(@options)
+      assert CordialCantina.Nif.pair_push(pair, 0, -1.0) == {:error, :non_positive_price}
+
+      assert CordialCantina.Nif.pair_decide(pair, %{@config | tick_spacing: 0}) ==
+               {:error, :invalid_spacing}
+    end
+  end
+
+  describe "batches" do
+    @options %{
+      process_noise: 1.0e-8,
+      measurement_noise: 1.0e-6,
+      jolt_limit: 1.0,
+      dead_band: 0,
+      min_interval: 0,
+      window: 86_400_000
+    }
+    @config %{
+      tick_lower: -100,
+      tick_upper: 100,
+      tick_spacing: 10,
+      current_tick: 0,
+      base_price: 1.0
+    }
+
+    test "update every pair in one call" do
+      first = CordialCantina.Nif.pair_new(@options)
+      second = CordialCantina.Nif.pair_new(@options)
+
+      assert {:ok, results} =
+               CordialCantina.Nif.push_batch([
+                 {first, [{0, 1.0, 5.0}, {1_000, 1.0, 5.0}], @config},
+                 {second, [{0, 1.0, 5.0}, {0, 1.0, 5.0}], %{@config | current_tick: 200}}
+               ])
+
+      assert [{2, {:ok, %{rule: :in_range}}}, {1, {:ok, %{rule: :out_of_range}}}] = results
+    end
+
+    test "accept packed updates and large batches" do
+      pair = CordialCantina.Nif.pair_new(@options)
+      updates = for second <- 1..2_000, do: {second * 1_000, 1.0, 0.0}
+
+      assert {:ok, [{2_000, {:ok, %{command: :hold}}}]} =
+               CordialCantina.Nif.push_packed_batch([
+                 {pair, CordialCantina.Nif.pack_updates(updates), @config}
+               ])
+    end
+
+    test "return an error without partial updates" do
+      pair = CordialCantina.Nif.pair_new(@options)
+
+      assert {:error, :non_positive_price} =
+               CordialCantina.Nif.push_batch([
+                 {pair, [{0, 1.0, 0.0}], @config},
+                 {pair, [{1_000, -1.0, 0.0}], @config}
+               ])
+
+      assert {:error, :tick_misaligned} =
+               CordialCantina.Nif.push_batch([
+                 {pair, [{0, 1.0, 0.0}], %{@config | tick_lower: -105}}
+               ])
+
+      assert_raise ArgumentError, fn ->
+        CordialCantina.Nif.push_batch([{pair, [{0, 1.0, -1.0}], @config}])
+      end
+
+      assert CordialCantina.Nif.pair_state(pair) == nil
+    end
+  end
 end
@@ -0,0 +1,283 @@
+//! Position value, impermanent loss and PnL for a CLMM tick range.
+//!
+//! Uses the continuous CLMM model over `[tick_lower, tick_upper)`, with
+//! prices measured against `base_price` at tick 0 as in
+//! [`crate::price_at_tick`]:
+//! liquidity `L` between prices `p_a` and `p_b` holds
+//! `L * (1/sqrt(p) - 1/sqrt(p_b))` of token0 and `L * (sqrt(p) - sqrt(p_a))`
+//! of token1, with `sqrt(p)` clamped to the range. Prices are token1 per
+//! token0 and values are in token1, so with the pool convention of
+//! [`crate::establishment`] values are in stable coin.
+//!
+//! Impermanent loss is what the position is worth less than simply holding
+//! the tokens it started with. Withdrawing is warranted when that loss
+//! exceeds the fees the position earns.
+//!
+//! `liquidity` is the continuous model's liquidity in the units of the
+//! prices, not a position's on-chain liquidity, so the functions take a
+//! tick range rather than a [`crate::Position`]. Every function fails with
+//! [`JoltError::RangeInverted`] for an inverted range, with
+//! [`JoltError::NonFinite`] or [`JoltError::NonPositivePrice`] for a price
+//! that is not positive and finite, and with the [`JoltError`] of the
+//! range's boundary prices when the ticks cannot be converted.
+
+use crate::{JoltError, ScalarExt, Tick, check_price, price_at_tick};
+
+/// Square root of a non-negative scalar.
+fn sqrt<T: ScalarExt>(value: T) -> T {
+    value.powf(T::one() / (T::one() + T::one()))
+}
+
+/// Token amounts `(token0, token1)` held by `liquidity` at `price`.
+pub fn position_amounts<T: ScalarExt>(
+    tick_lower: Tick,
+    tick_upper: Tick,
+    base_price: T,
+    liquidity: T,
+    price: T,
+) -> Result<(T, T), JoltError> {
+    if tick_lower >= tick_upper {
+        return Err(JoltError::RangeInverted {
+            tick_lower: tick_lower.get(),
+            tick_upper: tick_upper.get(),
+        });
+    }
+    let lower = sqrt(price_at_tick(base_price, tick_lower)?);
+    let upper = sqrt(price_at_tick(base_price, tick_upper)?);
+    let current = sqrt(check_price(price)?).max(lower).min(upper);
+    Ok((
+        liquidity * (T::one() / current - T::one() / upper),
+        liquidity * (current - lower),
+    ))
+}
+
+/// Value in token1 of the tokens held by `liquidity` at `price`.
+pub fn position_value<T: ScalarExt>(
+    tick_lower: Tick,
+    tick_upper: Tick,
+    base_price: T,
+    liquidity: T,
+    price: T,
+) -> Result<T, JoltError> {
+    let (amount_0, amount_1) =
+        position_amounts(tick_lower, tick_upper, base_price, liquidity, price)?;
+    Ok(amount_0 * price + amount_1)
+}
+
+/// Value at `price` of the tokens the position held at `entry_price`.
+pub fn hold_value<T: ScalarExt>(
+    tick_lower: Tick,
+    tick_upper: Tick,
+    base_price: T,
+    liquidity: T,
+    entry_price: T,
+    price: T,
+) -> Result<T, JoltError> {
+    let (amount_0, amount_1) =
+        position_amounts(tick_lower, tick_upper, base_price, liquidity, entry_price)?;
+    Ok(amount_0 * check_price(price)? + amount_1)
+}
+
+/// Impermanent loss in token1 from `entry_price` to `price`.
+///
+/// Never negative: a CLMM position is never worth more than holding.
+pub fn impermanent_loss<T: ScalarExt>(
+    tick_lower: Tick,
+    tick_upper: Tick,
+    base_price: T,
+    liquidity: T,
+    entry_price: T,
+    price: T,
+) -> Result<T, JoltError> {
+    Ok(hold_value(
+        tick_lower,
+        tick_upper,
+        base_price,
+        liquidity,
+        entry_price,
+        price,
+    )? - position_value(tick_lower, tick_upper, base_price, liquidity, price)?)
+}
+
+/// Impermanent loss as a fraction of the hold value.
+///
+/// Returns zero when the hold value is zero.
+pub fn impermanent_loss_ratio<T: ScalarExt>(
+    tick_lower: Tick,
+    tick_upper: Tick,
+    base_price: T,
+    liquidity: T,
+    entry_price: T,
+    price: T,
+) -> Result<T, JoltError> {
+    let hold = hold_value(
+        tick_lower,
+        tick_upper,
+        base_price,
+        liquidity,
+        entry_price,
+        price,
+    )?;
+    if hold == T::zero() {
+        return Ok(T::zero());
+    }
+    Ok((hold - position_value(tick_lower, tick_upper, base_price, liquidity, price)?) / hold)
+}
+
+/// Breakdown of a position's performance against holding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionPnl<T> {
+    pub position_value: T,
+    pub hold_value: T,
+    pub impermanent_loss: T,
+    /// Fees earned, in token1
+    pub fees: T,
+    /// `position_value + fees - hold_value`
+    pub net: T,
+}
+
+impl<T: ScalarExt> PositionPnl<T> {
+    /// True when fees have more than paid for the impermanent loss.
+    pub fn fees_cover_loss(&self) -> bool {
+        self.impermanent_loss < self.fees
+    }
+}
+
+/// Net PnL of a position opened at `entry_price`, including `fees` in token1.
+pub fn position_pnl<T: ScalarExt>(
+    tick_lower: Tick,
+    tick_upper: Tick,
+    base_price: T,
+    liquidity: T,
+    entry_price: T,
+    price: T,
+    fees: T,
+) -> Result<PositionPnl<T>, JoltError> {
+    let position_value = position_value(tick_lower, tick_upper, base_price, liquidity, price)?;
+    let hold_value = hold_value(
+        tick_lower,
+        tick_upper,
+        base_price,
+        liquidity,
+        entry_price,
+        price,
+    )?;
+    Ok(PositionPnl {
+        position_value,
+        hold_value,
+        impermanent_loss: hold_value - position_value,
+        fees,
+        net: position_value + fees - hold_value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const BASE_PRICE: f64 = 1.0;
+
+    fn tick(tick: i32) -> Tick {
+        Tick::new(tick).unwrap()
+    }
+
+    #[rstest]
+    #[case::below_range(0.5, true, false)]
+    #[case::in_range(1.0, true, true)]
+    #[case::above_range(2.0, false, true)]
+    fn test_position_amounts_by_side(
+        #[case] price: f64,
+        #[case] has_token_0: bool,
+        #[case] has_token_1: bool,
+    ) {
+        let (amount_0, amount_1) =
+            position_amounts(tick(-2000), tick(2000), BASE_PRICE, 1000.0, price).unwrap();
+        assert_eq!(amount_0 > 1e-9, has_token_0);
+        assert_eq!(amount_1 > 1e-9, has_token_1);
+        assert!(amount_0 >= -1e-9 && amount_1 >= -1e-9);
+    }
+
+    #[test]
+    fn test_position_value_in_range() {
+        let (tick_lower, tick_upper) = (tick(-2000), tick(2000));
+        let lower = price_at_tick(BASE_PRICE, tick_lower).unwrap();
+        let upper = price_at_tick(BASE_PRICE, tick_upper).unwrap();
+        let expected = 1000.0 * (1.0 - 1.0 / upper.sqrt()) + 1000.0 * (1.0 - lower.sqrt());
+        let value = position_value(tick_lower, tick_upper, BASE_PRICE, 1000.0, 1.0).unwrap();
+        assert!((value - expected).abs() < 1e-9);
+    }
+
+    #[rstest]
+    #[case::unchanged(1.0, 0.0)]
+    #[case::doubled(4.0, 1.0 - 2.0 * 2.0 / 5.0)]
+    #[case::halved(0.25, 1.0 - 2.0 * 0.5 / 1.25)]
+    fn test_wide_range_matches_constant_product(#[case] price: f64, #[case] expected: f64) {
+        // A very wide range behaves like a full-range x*y=k position
+        let (tick_lower, tick_upper) = (tick(-400_000), tick(400_000));
+        let ratio =
+            impermanent_loss_ratio(tick_lower, tick_upper, BASE_PRICE, 1e6, 1.0, price).unwrap();
+        assert!((ratio - expected).abs() < 1e-6, "{ratio}");
+    }
+
+    #[test]
+    fn test_concentration_amplifies_loss() {
+        let narrow =
+            impermanent_loss_ratio(tick(-1000), tick(1000), BASE_PRICE, 1.0, 1.0, 1.05).unwrap();
+        let wide =
+            impermanent_loss_ratio(tick(-10000), tick(10000), BASE_PRICE, 1.0, 1.0, 1.05).unwrap();
+        assert!(narrow > wide && wide > 0.0);
+    }
+
+    #[rstest]
+    #[case::both_below(0.5, 0.6)]
+    #[case::both_above(2.0, 3.0)]
+    fn test_no_loss_outside_range_on_same_side(#[case] entry: f64, #[case] price: f64) {
+        let loss =
+            impermanent_loss(tick(-2000), tick(2000), BASE_PRICE, 1000.0, entry, price).unwrap();
+        assert!(loss.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_position_pnl() {
+        let (tick_lower, tick_upper) = (tick(-2000), tick(2000));
+        let pnl = position_pnl(tick_lower, tick_upper, BASE_PRICE, 1000.0, 1.0, 1.1, 0.5).unwrap();
+        assert!(pnl.impermanent_loss > 0.0);
+        assert!((pnl.net - (pnl.fees - pnl.impermanent_loss)).abs() < 1e-9);
+        assert_eq!(pnl.fees_cover_loss(), pnl.net > 0.0);
+        let pnl =
+            position_pnl(tick_lower, tick_upper, BASE_PRICE, 1000.0, 1.0, 1.1, 100.0).unwrap();
+        assert!(pnl.fees_cover_loss());
+    }
+
+    #[rstest]
+    #[case::zero_base(0.0, 1.0, 1.0, JoltError::InvalidBasePrice)]
+    #[case::zero_price(1.0, 1.0, 0.0, JoltError::NonPositivePrice)]
+    #[case::negative_price(1.0, 1.0, -1.0, JoltError::NonPositivePrice)]
+    #[case::nan_price(1.0, 1.0, f64::NAN, JoltError::NonFinite)]
+    #[case::zero_entry(1.0, 0.0, 1.0, JoltError::NonPositivePrice)]
+    #[case::infinite_entry(1.0, f64::INFINITY, 1.0, JoltError::NonFinite)]
+    fn test_invalid_price_is_an_error(
+        #[case] base_price: f64,
+        #[case] entry_price: f64,
+        #[case] price: f64,
+        #[case] expected: JoltError,
+    ) {
+        let (tick_lower, tick_upper) = (tick(-2000), tick(2000));
+        assert_eq!(
+            impermanent_loss(tick_lower, tick_upper, base_price, 1.0, entry_price, price),
+            Err(expected)
+        );
+    }
+
+    #[test]
+    fn test_inverted_range_is_an_error() {
+        assert_eq!(
+            position_value(tick(2000), tick(-2000), BASE_PRICE, 1.0, 1.0),
+            Err(JoltError::RangeInverted {
+                tick_lower: 2000,
+                tick_upper: -2000
+            })
+        );
+    }
+}
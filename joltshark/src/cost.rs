@@ -0,0 +1,364 @@
+//! Solana transaction cost model for position management.
+//!
+//! A transaction pays the base fee for each signature plus a priority fee of
+//! its compute unit limit times the compute unit price. Opening a position
+//! also funds new accounts to rent exemption, and closing one refunds that
+//! rent. Costs are in lamports; [`CostModel::value`] converts them into the
+//! units the expected fee gain is measured in, so a proposed rebalance can be
+//! checked with [`check_rebalance`] and a fee collection with
+//! [`check_collect_fees`].
+
+use core::ops::Add;
+
+use crate::{CLMMCommand, JoltError, Liquidity, Scalar, ScalarExt, WaitReason};
+
+/// Base fee charged per signature, in lamports.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Compute unit prices are quoted in micro-lamports.
+pub const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+/// Rent charged per byte-year, in lamports.
+pub const LAMPORTS_PER_BYTE_YEAR: u64 = 3_480;
+
+/// Years of rent an account must hold to be rent exempt.
+pub const EXEMPTION_THRESHOLD_YEARS: u64 = 2;
+
+/// Bytes of account metadata charged in addition to the data length.
+pub const ACCOUNT_STORAGE_OVERHEAD: u64 = 128;
+
+/// Lamports needed to make an account of `data_len` bytes rent exempt.
+pub const fn rent_exempt_minimum(data_len: u64) -> u64 {
+    (ACCOUNT_STORAGE_OVERHEAD + data_len) * LAMPORTS_PER_BYTE_YEAR * EXEMPTION_THRESHOLD_YEARS
+}
+
+/// Accounts created or closed by a transaction plan.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Accounts {
+    pub count: u64,
+    /// Total data length of the accounts
+    pub data_len: u64,
+}
+
+impl Accounts {
+    /// Total rent-exempt balance of the accounts.
+    pub const fn rent(&self) -> u64 {
+        (self.count * ACCOUNT_STORAGE_OVERHEAD + self.data_len)
+            * LAMPORTS_PER_BYTE_YEAR
+            * EXEMPTION_THRESHOLD_YEARS
+    }
+}
+
+/// Resources consumed by one or more transactions.
+///
+/// The presets are estimates for the Raydium CLMM instructions; measure the
+/// deployed program and override them where precision matters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransactionPlan {
+    pub signatures: u64,
+    /// Compute unit limit requested, which the priority fee is charged on
+    pub compute_units: u64,
+    pub opened: Accounts,
+    pub closed: Accounts,
+}
+
+impl TransactionPlan {
+    /// Opening a position: the position NFT mint and token account and the
+    /// personal position state.
+    pub const OPEN_POSITION: TransactionPlan = TransactionPlan {
+        signatures: 2,
+        compute_units: 250_000,
+        opened: Accounts {
+            count: 3,
+            data_len: 82 + 165 + 281,
+        },
+        closed: Accounts {
+            count: 0,
+            data_len: 0,
+        },
+    };
+
+    /// Removing all liquidity and closing a position's accounts.
+    pub const CLOSE_POSITION: TransactionPlan = TransactionPlan {
+        signatures: 1,
+        compute_units: 150_000,
+        opened: Accounts {
+            count: 0,
+            data_len: 0,
+        },
+        closed: Accounts {
+            count: 3,
+            data_len: 82 + 165 + 281,
+        },
+    };
+
+    /// Increasing or decreasing the liquidity of an existing position.
+    pub const ADJUST_LIQUIDITY: TransactionPlan = TransactionPlan {
+        signatures: 1,
+        compute_units: 120_000,
+        opened: Accounts {
+            count: 0,
+            data_len: 0,
+        },
+        closed: Accounts {
+            count: 0,
+            data_len: 0,
+        },
+    };
+
+    /// Collecting fees, which is a zero-liquidity decrease.
+    pub const COLLECT_FEES: TransactionPlan = TransactionPlan::ADJUST_LIQUIDITY;
+
+    /// Transactions needed to execute `command` on a position holding
+    /// `liquidity`.
+    ///
+    /// `AddLiquidity` opens a position when `liquidity` is zero and
+    /// `RemoveLiquidity` closes it when it withdraws all of `liquidity`;
+    /// otherwise both adjust the existing position. A `Rebalance` closes one
+    /// position and opens another, and `Exit` closes one.
+    pub fn for_command(command: &CLMMCommand, liquidity: Liquidity) -> TransactionPlan {
+        match command {
+            CLMMCommand::AddLiquidity { .. } if liquidity == Liquidity::ZERO => {
+                TransactionPlan::OPEN_POSITION
+            }
+            CLMMCommand::RemoveLiquidity { amount } if *amount >= liquidity => {
+                TransactionPlan::CLOSE_POSITION
+            }
+            CLMMCommand::AddLiquidity { .. } | CLMMCommand::RemoveLiquidity { .. } => {
+                TransactionPlan::ADJUST_LIQUIDITY
+            }
+            CLMMCommand::Rebalance { .. } => {
+                TransactionPlan::CLOSE_POSITION + TransactionPlan::OPEN_POSITION
+            }
+            CLMMCommand::CollectFees => TransactionPlan::COLLECT_FEES,
+            CLMMCommand::Exit => TransactionPlan::CLOSE_POSITION,
+            CLMMCommand::Hold | CLMMCommand::Wait(_) => TransactionPlan::default(),
+        }
+    }
+}
+
+impl Add for TransactionPlan {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        TransactionPlan {
+            signatures: self.signatures + rhs.signatures,
+            compute_units: self.compute_units + rhs.compute_units,
+            opened: Accounts {
+                count: self.opened.count + rhs.opened.count,
+                data_len: self.opened.data_len + rhs.opened.data_len,
+            },
+            closed: Accounts {
+                count: self.closed.count + rhs.closed.count,
+                data_len: self.closed.data_len + rhs.closed.data_len,
+            },
+        }
+    }
+}
+
+/// Network prices applied to a [`TransactionPlan`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CostModel {
+    pub lamports_per_signature: u64,
+    /// Priority fee per compute unit, in micro-lamports
+    pub compute_unit_price: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            lamports_per_signature: LAMPORTS_PER_SIGNATURE,
+            compute_unit_price: 0,
+        }
+    }
+}
+
+impl CostModel {
+    pub fn base_fee(&self, plan: &TransactionPlan) -> u64 {
+        plan.signatures * self.lamports_per_signature
+    }
+
+    /// Priority fee in lamports, rounded up like the runtime.
+    pub fn priority_fee(&self, plan: &TransactionPlan) -> u64 {
+        let micro_lamports = plan.compute_units as u128 * self.compute_unit_price as u128;
+        micro_lamports.div_ceil(MICRO_LAMPORTS_PER_LAMPORT as u128) as u64
+    }
+
+    /// Rent funded for new accounts minus rent refunded by closed ones.
+    pub fn net_rent(&self, plan: &TransactionPlan) -> i64 {
+        plan.opened.rent() as i64 - plan.closed.rent() as i64
+    }
+
+    /// Total lamports spent, counting rent refunds.
+    pub fn total(&self, plan: &TransactionPlan) -> i64 {
+        (self.base_fee(plan) + self.priority_fee(plan)) as i64 + self.net_rent(plan)
+    }
+
+    /// Total cost converted with `value_per_lamport`, such as stable coin per lamport.
+    pub fn value<T: ScalarExt>(
+        &self,
+        plan: &TransactionPlan,
+        value_per_lamport: T,
+    ) -> Result<T, JoltError> {
+        let total = T::from_f64(self.total(plan) as f64).ok_or(JoltError::Conversion)?;
+        Ok(total * value_per_lamport)
+    }
+}
+
+/// Replaces a `Rebalance` with `Wait` when its expected fee gain does not cover its cost.
+///
+/// Both amounts must be in the same units. Other commands pass through.
+pub fn check_rebalance<T: Scalar>(
+    command: CLMMCommand,
+    expected_fee_gain: T,
+    cost: T,
+) -> CLMMCommand {
+    match command {
+        CLMMCommand::Rebalance { .. } if expected_fee_gain - cost < T::zero() => {
+            CLMMCommand::Wait(WaitReason::TransactionCost)
+        }
+        command => command,
+    }
+}
+
+/// Returns `CollectFees` when `fee_value` covers the cost of collecting
+/// fees under `model`, and `Wait` otherwise.
+///
+/// `fee_value` is the value of a position's uncollected fees, such as those
+/// from [`crate::Position::uncollected_fees`], in the units
+/// `value_per_lamport` converts to.
+pub fn check_collect_fees<T: ScalarExt>(
+    fee_value: T,
+    model: &CostModel,
+    value_per_lamport: T,
+) -> Result<CLMMCommand, JoltError> {
+    let cost = model.value(&TransactionPlan::COLLECT_FEES, value_per_lamport)?;
+    if fee_value - cost < T::zero() {
+        Ok(CLMMCommand::Wait(WaitReason::TransactionCost))
+    } else {
+        Ok(CLMMCommand::CollectFees)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tick;
+    use rstest::*;
+
+    const REBALANCE: CLMMCommand = CLMMCommand::Rebalance {
+        new_tick_lower: Tick::ZERO,
+        new_tick_upper: Tick::MAX,
+    };
+
+    fn add() -> CLMMCommand {
+        CLMMCommand::AddLiquidity {
+            tick_lower: Tick::ZERO,
+            tick_upper: Tick::MAX,
+            amount: Liquidity::new(1),
+        }
+    }
+
+    fn remove(amount: u128) -> CLMMCommand {
+        CLMMCommand::RemoveLiquidity {
+            amount: Liquidity::new(amount),
+        }
+    }
+
+    #[test]
+    fn test_rent_exempt_minimum() {
+        // The well-known minimum for a 165-byte token account
+        assert_eq!(rent_exempt_minimum(165), 2_039_280);
+        assert_eq!(rent_exempt_minimum(0), 890_880);
+        let accounts = Accounts {
+            count: 2,
+            data_len: 165 + 82,
+        };
+        assert_eq!(
+            accounts.rent(),
+            rent_exempt_minimum(165) + rent_exempt_minimum(82)
+        );
+    }
+
+    #[rstest]
+    #[case::no_priority(0, 10_000)]
+    #[case::priority(1_000, 10_000 + 400)]
+    #[case::rounds_up(3, 10_000 + 2)]
+    fn test_fees(#[case] compute_unit_price: u64, #[case] expected: u64) {
+        let model = CostModel {
+            compute_unit_price,
+            ..CostModel::default()
+        };
+        let plan = TransactionPlan {
+            signatures: 2,
+            compute_units: 400_000,
+            ..TransactionPlan::default()
+        };
+        assert_eq!(model.base_fee(&plan) + model.priority_fee(&plan), expected);
+        assert_eq!(model.total(&plan), expected as i64);
+    }
+
+    #[test]
+    fn test_rebalance_rent_nets_out() {
+        let model = CostModel::default();
+        let plan = TransactionPlan::for_command(&REBALANCE, Liquidity::new(1));
+        assert_eq!(model.net_rent(&plan), 0);
+        assert_eq!(model.total(&plan), 3 * 5_000);
+        let open = model.total(&TransactionPlan::OPEN_POSITION);
+        let close = model.total(&TransactionPlan::CLOSE_POSITION);
+        assert!(open > 0 && close < 0);
+    }
+
+    #[rstest]
+    #[case::open(add(), 0, TransactionPlan::OPEN_POSITION)]
+    #[case::increase(add(), 1, TransactionPlan::ADJUST_LIQUIDITY)]
+    #[case::decrease(remove(5), 10, TransactionPlan::ADJUST_LIQUIDITY)]
+    #[case::close(remove(10), 10, TransactionPlan::CLOSE_POSITION)]
+    #[case::collect(CLMMCommand::CollectFees, 10, TransactionPlan::COLLECT_FEES)]
+    #[case::hold(CLMMCommand::Hold, 10, TransactionPlan::default())]
+    fn test_for_command(
+        #[case] command: CLMMCommand,
+        #[case] liquidity: u128,
+        #[case] expected: TransactionPlan,
+    ) {
+        assert_eq!(
+            TransactionPlan::for_command(&command, Liquidity::new(liquidity)),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_value_conversion() {
+        let model = CostModel {
+            compute_unit_price: 10_000,
+            ..CostModel::default()
+        };
+        let plan = TransactionPlan::ADJUST_LIQUIDITY;
+        // 5_000 base + 1_200 priority lamports at 1e-7 stable per lamport
+        assert!((model.value(&plan, 1e-7_f64).unwrap() - 6_200.0 * 1e-7).abs() < 1e-15);
+    }
+
+    #[rstest]
+    #[case::gain_covers_cost(REBALANCE, 2.0, 1.0, REBALANCE)]
+    #[case::break_even(REBALANCE, 1.0, 1.0, REBALANCE)]
+    #[case::cost_exceeds_gain(REBALANCE, 0.5, 1.0, CLMMCommand::Wait(WaitReason::TransactionCost))]
+    #[case::exit_passes(CLMMCommand::Exit, 0.0, 1.0, CLMMCommand::Exit)]
+    fn test_check_rebalance(
+        #[case] command: CLMMCommand,
+        #[case] gain: f64,
+        #[case] cost: f64,
+        #[case] expected: CLMMCommand,
+    ) {
+        assert_eq!(check_rebalance(command, gain, cost), expected);
+    }
+
+    #[rstest]
+    #[case::fees_cover_cost(1.0, CLMMCommand::CollectFees)]
+    #[case::break_even(5_000e-6, CLMMCommand::CollectFees)]
+    #[case::cost_exceeds_fees(4_000e-6, CLMMCommand::Wait(WaitReason::TransactionCost))]
+    fn test_check_collect_fees(#[case] fee_value: f64, #[case] expected: CLMMCommand) {
+        // One signature and no priority fee at 1e-6 stable per lamport
+        let model = CostModel::default();
+        assert_eq!(check_collect_fees(fee_value, &model, 1e-6), Ok(expected));
+    }
+}
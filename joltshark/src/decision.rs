@@ -0,0 +1,258 @@
+//! Position decisions with the rule and inputs that produced them.
+//!
+//! A [`Decision`] pairs a [`CLMMCommand`] with the [`Rule`] that fired and
+//! the [`Diagnostics`] it was judged on, so a dashboard can show why a
+//! position was exited, rebalanced or left alone.
+
+use crate::{
+    CLMMCommand, JoltError, PoolState, Position, Scalar, ScalarExt, StateVector, Tick, WaitReason,
+};
+
+/// Rule that produced a decision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// Jolt magnitude exceeded its limit
+    JoltLimit,
+    /// The current tick left the range
+    OutOfRange,
+    /// The current tick is inside the range
+    InRange,
+    /// A rebalance was replaced by a wait
+    Suppressed(WaitReason),
+}
+
+/// Inputs a decision was made on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Diagnostics<T> {
+    /// Jolt of the state, if it has one
+    pub jolt: Option<T>,
+    pub jolt_limit: T,
+    pub current_tick: Tick,
+    pub tick_lower: Tick,
+    pub tick_upper: Tick,
+}
+
+impl<T: ScalarExt> Diagnostics<T> {
+    /// Position of the current tick within the range, clamped to [0, 1].
+    pub fn range_position(&self) -> Result<T, JoltError> {
+        crate::range_position(self.current_tick, self.tick_lower, self.tick_upper)
+    }
+}
+
+/// A command with the rule that fired and its inputs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decision<T> {
+    pub command: CLMMCommand,
+    pub rule: Rule,
+    pub diagnostics: Diagnostics<T>,
+}
+
+impl<T: Scalar> Decision<T> {
+    /// Replaces the command with a wait for `reason`.
+    pub fn suppress(self, reason: WaitReason) -> Self {
+        Decision {
+            command: CLMMCommand::Wait(reason),
+            rule: Rule::Suppressed(reason),
+            ..self
+        }
+    }
+
+    /// Suppresses a rebalance whose expected fee gain does not cover its cost.
+    ///
+    /// See [`crate::cost::check_rebalance`].
+    pub fn check_cost(self, expected_fee_gain: T, cost: T) -> Self {
+        match crate::cost::check_rebalance(self.command, expected_fee_gain, cost) {
+            command if command == self.command => self,
+            _ => self.suppress(WaitReason::TransactionCost),
+        }
+    }
+}
+
+/// Decides on a position the way [`crate::evaluate_clmm_position`] does,
+/// recording the rule that fired.
+///
+/// Fails when the pool or position is invalid or the state or limit is not
+/// finite.
+pub fn decide_clmm_position<T: Scalar, const D: usize>(
+    state: &StateVector<T, D>,
+    pool: &PoolState,
+    position: &Position,
+    jolt_limit: T,
+) -> Result<Decision<T>, JoltError> {
+    pool.validate()?;
+    position.validate(pool)?;
+    if !jolt_limit.is_finite() || !state.0.iter().all(|value| value.is_finite()) {
+        return Err(JoltError::NonFinite);
+    }
+    let jolt = state.jolt();
+    let diagnostics = Diagnostics {
+        jolt,
+        jolt_limit,
+        current_tick: pool.tick,
+        tick_lower: position.tick_lower,
+        tick_upper: position.tick_upper,
+    };
+
+    // Check for extreme volatility via jolt
+    if let Some(j) = jolt
+        && jolt_limit < j.abs()
+    {
+        return Ok(Decision {
+            command: CLMMCommand::Exit,
+            rule: Rule::JoltLimit,
+            diagnostics,
+        });
+    }
+
+    // Check if price is in range
+    if !position.is_in_range(pool) {
+        // Price has moved out of range, recenter on the current tick
+        let new_center = pool.tick.get();
+        let half_range = (position.tick_upper.get() - position.tick_lower.get()) / 2;
+        let (new_tick_lower, new_tick_upper) = pool
+            .tick_spacing
+            .align_range(new_center - half_range, new_center + half_range)?;
+        return Ok(Decision {
+            command: CLMMCommand::Rebalance {
+                new_tick_lower,
+                new_tick_upper,
+            },
+            rule: Rule::OutOfRange,
+            diagnostics,
+        });
+    }
+
+    Ok(Decision {
+        command: CLMMCommand::Hold,
+        rule: Rule::InRange,
+        diagnostics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Liquidity, TickSpacing};
+    use rstest::*;
+
+    fn pool(current_tick: i32) -> PoolState {
+        let spacing = TickSpacing::new(10).unwrap();
+        PoolState::at_tick(
+            Tick::new(current_tick).unwrap(),
+            Liquidity::ZERO,
+            0,
+            spacing,
+        )
+        .unwrap()
+    }
+
+    fn position() -> Position {
+        Position::new(
+            Tick::new(-100).unwrap(),
+            Tick::new(100).unwrap(),
+            Liquidity::ZERO,
+        )
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case::jolt(0, 2.0, Rule::JoltLimit, 0.5)]
+    #[case::below(-300, 0.0, Rule::OutOfRange, 0.0)]
+    #[case::above(100, 0.0, Rule::OutOfRange, 1.0)]
+    #[case::inside(50, 0.5, Rule::InRange, 0.75)]
+    fn test_decide_rule(
+        #[case] current_tick: i32,
+        #[case] jolt: f64,
+        #[case] rule: Rule,
+        #[case] range_position: f64,
+    ) {
+        let state = StateVector([0.0, 0.0, 0.0, jolt]);
+        let pool = pool(current_tick);
+        let decision = decide_clmm_position(&state, &pool, &position(), 1.0).unwrap();
+        assert_eq!(decision.rule, rule);
+        assert_eq!(decision.diagnostics.jolt, Some(jolt));
+        assert_eq!(decision.diagnostics.range_position(), Ok(range_position));
+        assert_eq!(
+            Ok(decision.command),
+            crate::evaluate_clmm_position(&state, &pool, &position(), 1.0)
+        );
+    }
+
+    #[test]
+    fn test_decide_without_jolt() {
+        let state = StateVector([1.0, 2.0]);
+        let decision = decide_clmm_position(&state, &pool(0), &position(), 1.0).unwrap();
+        assert_eq!(decision.diagnostics.jolt, None);
+        assert_eq!(decision.rule, Rule::InRange);
+    }
+
+    #[rstest]
+    #[case::aligned(200, (100, 300))]
+    #[case::widens_outward(205, (100, 310))]
+    #[case::negative(-305, (-410, -200))]
+    fn test_rebalance_range(#[case] current_tick: i32, #[case] expected: (i32, i32)) {
+        let state = StateVector([0.0; 4]);
+        let decision = decide_clmm_position(&state, &pool(current_tick), &position(), 1.0).unwrap();
+        let (tick_lower, tick_upper) = expected;
+        assert_eq!(
+            decision.command,
+            CLMMCommand::Rebalance {
+                new_tick_lower: Tick::new(tick_lower).unwrap(),
+                new_tick_upper: Tick::new(tick_upper).unwrap(),
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::covered(2.0, Rule::OutOfRange)]
+    #[case::not_covered(0.5, Rule::Suppressed(WaitReason::TransactionCost))]
+    fn test_check_cost(#[case] gain: f64, #[case] rule: Rule) {
+        let state = StateVector([0.0; 4]);
+        let decision = decide_clmm_position(&state, &pool(200), &position(), 1.0)
+            .unwrap()
+            .check_cost(gain, 1.0);
+        assert_eq!(decision.rule, rule);
+        assert_eq!(decision.diagnostics.current_tick.get(), 200);
+    }
+
+    #[rstest]
+    #[case::non_finite_state(StateVector([0.0, f64::NAN, 0.0, 0.0]), 1.0, JoltError::NonFinite)]
+    #[case::non_finite_limit(StateVector([0.0; 4]), f64::INFINITY, JoltError::NonFinite)]
+    fn test_decide_rejects_non_finite(
+        #[case] state: StateVector<f64, 4>,
+        #[case] jolt_limit: f64,
+        #[case] expected: JoltError,
+    ) {
+        assert_eq!(
+            decide_clmm_position(&state, &pool(0), &position(), jolt_limit),
+            Err(expected)
+        );
+    }
+
+    #[test]
+    fn test_decide_rejects_invalid_position() {
+        let position = Position {
+            tick_lower: Tick::new(105).unwrap(),
+            ..position()
+        };
+        assert_eq!(
+            decide_clmm_position(&StateVector([0.0; 4]), &pool(0), &position, 1.0),
+            Err(JoltError::RangeInverted {
+                tick_lower: 105,
+                tick_upper: 100
+            })
+        );
+    }
+
+    #[test]
+    fn test_decide_rejects_invalid_pool() {
+        let pool = PoolState {
+            fee_rate: crate::swap::FEE_RATE_DENOMINATOR,
+            ..pool(0)
+        };
+        assert_eq!(
+            decide_clmm_position(&StateVector([0.0; 4]), &pool, &position(), 1.0),
+            Err(JoltError::InvalidFeeRate(1_000_000))
+        );
+    }
+}
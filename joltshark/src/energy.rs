@@ -0,0 +1,243 @@
+//! Kinetic, potential and total energy of a price trajectory.
+//!
+//! Implements the energy quantities of the physical model. Kinetic energy is
+//! `½mv²` for an effective mass `m`; potential energy comes from a
+//! [`Potential`] describing equilibrium or support/resistance levels. In an
+//! idealized market total energy is conserved, so a jump in total energy
+//! between consecutive states marks an external shock.
+
+use crate::{Scalar, StateVector};
+
+/// Potential energy as a function of position.
+pub trait Potential<T: Scalar> {
+    fn potential(&self, position: T) -> T;
+}
+
+impl<T: Scalar, F: Fn(T) -> T> Potential<T> for F {
+    fn potential(&self, position: T) -> T {
+        self(position)
+    }
+}
+
+/// Harmonic well `½k(x - center)²` around an equilibrium.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HarmonicWell<T: Scalar> {
+    pub center: T,
+    pub stiffness: T,
+}
+
+impl<T: Scalar> HarmonicWell<T> {
+    /// Well centered between `lower` and `upper`, such as the R_fee bounds.
+    pub fn centered(lower: T, upper: T, stiffness: T) -> Self {
+        HarmonicWell {
+            center: (lower + upper) / (T::one() + T::one()),
+            stiffness,
+        }
+    }
+}
+
+impl<T: Scalar> Potential<T> for HarmonicWell<T> {
+    fn potential(&self, position: T) -> T {
+        let offset = position - self.center;
+        self.stiffness * offset * offset / (T::one() + T::one())
+    }
+}
+
+/// Localized well `-depth / (1 + ((x - level) / width)²)` at a price level.
+///
+/// The potential is `-depth` at the level and rises to zero far from it, so
+/// a trajectory with negative total energy cannot escape the well.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelWell<T: Scalar> {
+    pub level: T,
+    pub depth: T,
+    pub width: T,
+}
+
+impl<T: Scalar> Potential<T> for LevelWell<T> {
+    fn potential(&self, position: T) -> T {
+        let scaled = (position - self.level) / self.width;
+        -self.depth / (T::one() + scaled * scaled)
+    }
+}
+
+/// Sum of support and resistance wells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WellSum<'a, T: Scalar> {
+    pub wells: &'a [LevelWell<T>],
+}
+
+impl<T: Scalar> Potential<T> for WellSum<'_, T> {
+    fn potential(&self, position: T) -> T {
+        self.wells
+            .iter()
+            .fold(T::zero(), |sum, well| sum + well.potential(position))
+    }
+}
+
+/// Kinetic energy `½mv²`, or `None` if the state has no velocity.
+pub fn kinetic_energy<T: Scalar, const D: usize>(state: &StateVector<T, D>, mass: T) -> Option<T> {
+    let velocity = state.velocity()?;
+    Some(mass * velocity * velocity / (T::one() + T::one()))
+}
+
+/// Potential energy at the state position.
+pub fn potential_energy<T: Scalar, const D: usize>(
+    state: &StateVector<T, D>,
+    potential: &impl Potential<T>,
+) -> Option<T> {
+    Some(potential.potential(state.position()?))
+}
+
+/// Sum of kinetic and potential energy.
+pub fn total_energy<T: Scalar, const D: usize>(
+    state: &StateVector<T, D>,
+    mass: T,
+    potential: &impl Potential<T>,
+) -> Option<T> {
+    Some(kinetic_energy(state, mass)? + potential_energy(state, potential)?)
+}
+
+/// Total energy change flagged by [`EnergyShockDetector`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyShock<T: Scalar> {
+    pub previous: T,
+    pub current: T,
+}
+
+impl<T: Scalar> EnergyShock<T> {
+    /// Energy gained (positive) or lost (negative) between the states.
+    pub fn delta(&self) -> T {
+        self.current - self.previous
+    }
+
+    /// True when energy was injected, as by news or a large order.
+    pub fn is_injection(&self) -> bool {
+        self.previous < self.current
+    }
+}
+
+/// Flags consecutive states whose total energy is not conserved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyShockDetector<T: Scalar> {
+    /// Effective mass used for kinetic energy
+    pub mass: T,
+    /// Largest absolute energy change treated as conserved
+    pub tolerance: T,
+}
+
+impl<T: Scalar> EnergyShockDetector<T> {
+    /// Returns the shock if total energy changed by more than the tolerance.
+    pub fn check<const D: usize>(
+        &self,
+        previous: &StateVector<T, D>,
+        current: &StateVector<T, D>,
+        potential: &impl Potential<T>,
+    ) -> Option<EnergyShock<T>> {
+        let shock = EnergyShock {
+            previous: total_energy(previous, self.mass, potential)?,
+            current: total_energy(current, self.mass, potential)?,
+        };
+        (self.tolerance < shock.delta().abs()).then_some(shock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[test]
+    fn test_kinetic_energy() {
+        let state = StateVector([10.0, -3.0]);
+        assert_eq!(kinetic_energy(&state, 2.0), Some(9.0));
+        assert_eq!(kinetic_energy(&StateVector([10.0]), 2.0), None);
+    }
+
+    #[rstest]
+    #[case::at_center(5.0, 0.0)]
+    #[case::above_center(7.0, 8.0)]
+    #[case::below_center(3.0, 8.0)]
+    fn test_harmonic_well(#[case] position: f64, #[case] expected: f64) {
+        let well = HarmonicWell::centered(4.0, 6.0, 4.0);
+        assert_eq!(well.center, 5.0);
+        assert_eq!(well.potential(position), expected);
+    }
+
+    #[rstest]
+    #[case::at_level(1.0, -2.0)]
+    #[case::one_width_away(1.5, -1.0)]
+    #[case::far_away(1001.0, -2.0 / (1.0 + 4e6))]
+    fn test_level_well(#[case] position: f64, #[case] expected: f64) {
+        let well = LevelWell {
+            level: 1.0,
+            depth: 2.0,
+            width: 0.5,
+        };
+        assert!((well.potential(position) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_well_sum_and_closure_potential() {
+        let wells = [
+            LevelWell {
+                level: 1.0,
+                depth: 1.0,
+                width: 1.0,
+            },
+            LevelWell {
+                level: 3.0,
+                depth: 2.0,
+                width: 1.0,
+            },
+        ];
+        let sum = WellSum { wells: &wells };
+        assert_eq!(sum.potential(2.0), -0.5 - 1.0);
+        let state = StateVector([2.0, 1.0]);
+        let linear = |x: f64| 3.0 * x;
+        assert_eq!(potential_energy(&state, &linear), Some(6.0));
+        assert_eq!(total_energy(&state, 2.0, &sum), Some(1.0 - 1.5));
+    }
+
+    #[test]
+    fn test_harmonic_oscillator_conserves_energy() {
+        let (mass, omega, amplitude) = (2.0_f64, 0.5, 3.0);
+        let well = HarmonicWell {
+            center: 10.0,
+            stiffness: mass * omega * omega,
+        };
+        let detector = EnergyShockDetector {
+            mass,
+            tolerance: 1e-9,
+        };
+        let state = |t: f64| {
+            StateVector([
+                10.0 + amplitude * (omega * t).cos(),
+                -amplitude * omega * (omega * t).sin(),
+            ])
+        };
+        for step in 0..50 {
+            let t = step as f64 * 0.3;
+            assert_eq!(detector.check(&state(t), &state(t + 0.3), &well), None);
+        }
+    }
+
+    #[test]
+    fn test_detector_flags_injection_and_dissipation() {
+        let well = HarmonicWell {
+            center: 0.0,
+            stiffness: 1.0,
+        };
+        let detector = EnergyShockDetector {
+            mass: 1.0,
+            tolerance: 0.1,
+        };
+        let calm = StateVector([1.0, 0.0]);
+        let kicked = StateVector([1.0, 2.0]);
+        let shock = detector.check(&calm, &kicked, &well).unwrap();
+        assert_eq!(shock.delta(), 2.0);
+        assert!(shock.is_injection());
+        let damped = detector.check(&kicked, &calm, &well).unwrap();
+        assert!(!damped.is_injection());
+    }
+}
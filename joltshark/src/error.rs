@@ -0,0 +1,66 @@
+//! Errors returned across joltshark.
+//!
+//! A price feed glitch must surface as an error instead of silently becoming
+//! tick 0 or a panic, so every fallible tick and price conversion, every
+//! liquidity, swap and range computation and every evaluation entry point
+//! returns [`JoltError`]. Callers combining several modules need no wrapper
+//! types.
+
+use crate::three_range::RangeKind;
+
+/// Invalid input or result anywhere in joltshark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoltError {
+    /// An input or result is NaN or infinite.
+    NonFinite,
+    /// A price is zero or negative.
+    NonPositivePrice,
+    /// Tick is outside `[MIN_TICK, MAX_TICK]`; saturated at the `i32` bounds.
+    TickOutOfBounds(i32),
+    /// The base price is zero, negative or not finite.
+    InvalidBasePrice,
+    /// Tick spacing is not positive.
+    InvalidSpacing(i32),
+    /// The lower tick is not below the upper tick.
+    RangeInverted { tick_lower: i32, tick_upper: i32 },
+    /// A range lies entirely beyond the outermost multiples of the tick
+    /// spacing, so aligning it leaves no ticks.
+    RangeBeyondBounds { tick_lower: i32, tick_upper: i32 },
+    /// A position bound is not a multiple of the tick spacing.
+    TickMisaligned { tick: i32, spacing: i32 },
+    /// The first range of a layout does not start below the second.
+    RangesUnordered(RangeKind, RangeKind),
+    /// The first range of a layout extends past the start of the second.
+    RangesOverlap(RangeKind, RangeKind),
+    /// The first range of a layout ends before the start of the second.
+    RangesGap(RangeKind, RangeKind),
+    /// Square root price is outside the range the conversion accepts.
+    SqrtPriceOutOfBounds(u128),
+    /// Both square root price bounds of a range are equal.
+    EmptyRange,
+    /// The current tick is not the tick at the pool's square root price.
+    TickPriceMismatch { tick: i32, sqrt_price: u128 },
+    /// The fee rate is not below [`crate::swap::FEE_RATE_DENOMINATOR`].
+    InvalidFeeRate(u32),
+    /// A time interval, window or cool-down is negative.
+    InvalidInterval,
+    /// A detector threshold is negative.
+    InvalidThreshold,
+    /// A filter noise or variance is negative, or the measurement noise is
+    /// zero.
+    InvalidNoise,
+    /// A share is above [`crate::establishment::BPS_DENOMINATOR`].
+    InvalidShare(u16),
+    /// A swap price limit is out of bounds or on the wrong side of the price.
+    InvalidPriceLimit(u128),
+    /// More liquidity removed than was provided.
+    LiquidityUnderflow,
+    /// The tick map has no room for another initialized tick.
+    TickMapFull,
+    /// An integer result does not fit in its type.
+    Overflow,
+    /// A divisor such as the liquidity is zero.
+    DivisionByZero,
+    /// A value is not representable in the scalar type.
+    Conversion,
+}
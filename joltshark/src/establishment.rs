@@ -0,0 +1,389 @@
+//! Asymmetric position establishment for the three-range strategy.
+//!
+//! The initial funding of each range depends on where the current price sits
+//! relative to R_fee (scenarios A, B and C of the position establishment
+//! specification). Amounts are exact integer token amounts computed with
+//! [`crate::liquidity_math`], so the deposits sent on-chain match what the
+//! program will take.
+//!
+//! A range above the current price can only hold token0 and a range below it
+//! can only hold token1; [`TokenOrder`] says which of those is the meme coin.
+//! Ranges are placed by pool tick, so with the meme coin as token0 R_restock
+//! holds stable coin and R_exit holds meme coin, and with the stable coin as
+//! token0 the roles of the two tokens are mirrored.
+
+use core::mem;
+
+use crate::liquidity_math::{amounts_from_liquidity, liquidity_from_amounts};
+use crate::rebalance::RangeCommand;
+use crate::three_range::{PerRange, RangeKind, ThreeRange};
+use crate::units::{Meme, Stable, TokenOrder};
+use crate::{CLMMCommand, JoltError, Liquidity, SqrtPriceX64, TokenAmount};
+
+/// Denominator for shares expressed in basis points.
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Position of the current price relative to R_fee.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scenario {
+    /// Scenario A: price at or below the R_fee lower bound; R_fee and R_exit
+    /// hold token0 only and R_restock stays empty.
+    BelowFee,
+    /// Scenario B: price at or above the R_fee upper bound; R_restock and
+    /// R_fee hold token1 only and R_exit stays empty.
+    AboveFee,
+    /// Scenario C: price inside R_fee; R_fee is funded with both tokens.
+    WithinFee,
+}
+
+/// Liquidity and token amounts deposited into one range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RangeDeposit {
+    pub liquidity: Liquidity,
+    /// Meme coin taken by the deposit
+    pub meme: TokenAmount<Meme>,
+    /// Stable coin taken by the deposit
+    pub stable: TokenAmount<Stable>,
+}
+
+/// Capital no range can take at the current price.
+///
+/// It has to be swapped for the other token before it can be deployed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequiredSwap {
+    StableToMeme(TokenAmount<Stable>),
+    MemeToStable(TokenAmount<Meme>),
+}
+
+/// Per-range deposits for a new three-range position set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EstablishmentPlan {
+    pub scenario: Scenario,
+    pub deposits: PerRange<RangeDeposit>,
+    /// Capital that needs a swap before it can be deployed
+    pub required_swap: Option<RequiredSwap>,
+    /// Meme coin rounding dust left in the wallet
+    pub unallocated_meme: TokenAmount<Meme>,
+    /// Stable coin rounding dust left in the wallet
+    pub unallocated_stable: TokenAmount<Stable>,
+    layout: ThreeRange,
+}
+
+impl EstablishmentPlan {
+    /// `AddLiquidity` commands for every funded range, lowest range first.
+    ///
+    /// The command amount is the liquidity to mint.
+    pub fn commands(&self) -> impl Iterator<Item = RangeCommand> + '_ {
+        RangeKind::ALL.into_iter().filter_map(|kind| {
+            let deposit = self.deposits.get(kind);
+            let range = self.layout.range(kind);
+            (deposit.liquidity != Liquidity::ZERO).then_some(RangeCommand {
+                range: kind,
+                command: CLMMCommand::AddLiquidity {
+                    tick_lower: range.tick_lower,
+                    tick_upper: range.tick_upper,
+                    amount: deposit.liquidity,
+                },
+            })
+        })
+    }
+
+    /// Meme and stable coin that stay in the wallet: the rounding dust plus
+    /// any capital awaiting the required swap.
+    pub fn undeployed(&self) -> (TokenAmount<Meme>, TokenAmount<Stable>) {
+        match self.required_swap {
+            Some(RequiredSwap::StableToMeme(stable)) => (
+                self.unallocated_meme,
+                TokenAmount::new(self.unallocated_stable.get() + stable.get()),
+            ),
+            Some(RequiredSwap::MemeToStable(meme)) => (
+                TokenAmount::new(self.unallocated_meme.get() + meme.get()),
+                self.unallocated_stable,
+            ),
+            None => (self.unallocated_meme, self.unallocated_stable),
+        }
+    }
+}
+
+/// Splits the available capital across the three ranges.
+///
+/// `fee_share_bps` is the share of each token offered to R_fee, which takes
+/// what its side of the price allows. R_restock then takes the remaining
+/// token1 and R_exit the remaining token0, except that scenario A leaves
+/// R_restock empty and scenario B leaves R_exit empty, even when the price
+/// sits inside them. The token only the empty range could hold is reported
+/// as a [`RequiredSwap`]; only rounding dust is left unallocated.
+pub fn plan_establishment(
+    total_stable: TokenAmount<Stable>,
+    total_meme: TokenAmount<Meme>,
+    layout: &ThreeRange,
+    sqrt_price: SqrtPriceX64,
+    token_order: TokenOrder,
+    fee_share_bps: u16,
+) -> Result<EstablishmentPlan, JoltError> {
+    if fee_share_bps > BPS_DENOMINATOR {
+        return Err(JoltError::InvalidShare(fee_share_bps));
+    }
+    let share =
+        |amount: u64| (amount as u128 * fee_share_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+    let bounds = |kind: RangeKind| {
+        let range = layout.range(kind);
+        (
+            SqrtPriceX64::from_tick(range.tick_lower),
+            SqrtPriceX64::from_tick(range.tick_upper),
+        )
+    };
+    let mut deposits = PerRange::<RangeDeposit>::default();
+    // Deposits what `(amount_0, amount_1)` supports and takes it from `remaining`
+    let mut deposit =
+        |kind: RangeKind, amount_0: u64, amount_1: u64, remaining: &mut (u64, u64)| {
+            let (lower, upper) = bounds(kind);
+            let liquidity = liquidity_from_amounts(sqrt_price, lower, upper, amount_0, amount_1)?;
+            let (amount_0, amount_1) =
+                amounts_from_liquidity(sqrt_price, lower, upper, liquidity, true)?;
+            remaining.0 -= amount_0;
+            remaining.1 -= amount_1;
+            let (meme, stable) = token_order.from_pool(amount_0, amount_1);
+            *deposits.get_mut(kind) = RangeDeposit {
+                liquidity,
+                meme,
+                stable,
+            };
+            Ok::<_, JoltError>(())
+        };
+
+    let (fee_lower, fee_upper) = bounds(RangeKind::Fee);
+    let scenario = if sqrt_price <= fee_lower {
+        Scenario::BelowFee
+    } else if fee_upper <= sqrt_price {
+        Scenario::AboveFee
+    } else {
+        Scenario::WithinFee
+    };
+    let mut remaining = token_order.to_pool(total_meme, total_stable);
+    deposit(
+        RangeKind::Fee,
+        share(remaining.0),
+        share(remaining.1),
+        &mut remaining,
+    )?;
+    if scenario != Scenario::AboveFee {
+        deposit(RangeKind::Exit, remaining.0, 0, &mut remaining)?;
+    }
+    if scenario != Scenario::BelowFee {
+        deposit(RangeKind::Restock, 0, remaining.1, &mut remaining)?;
+    }
+
+    // Outside R_fee only one token can be deployed
+    let stranded = match scenario {
+        Scenario::BelowFee => (0, mem::take(&mut remaining.1)),
+        Scenario::AboveFee => (mem::take(&mut remaining.0), 0),
+        Scenario::WithinFee => (0, 0),
+    };
+    let (stranded_meme, stranded_stable) = token_order.from_pool(stranded.0, stranded.1);
+    let required_swap = if stranded_meme > TokenAmount::ZERO {
+        Some(RequiredSwap::MemeToStable(stranded_meme))
+    } else if stranded_stable > TokenAmount::ZERO {
+        Some(RequiredSwap::StableToMeme(stranded_stable))
+    } else {
+        None
+    };
+    let (unallocated_meme, unallocated_stable) = token_order.from_pool(remaining.0, remaining.1);
+    Ok(EstablishmentPlan {
+        scenario,
+        deposits,
+        required_swap,
+        unallocated_meme,
+        unallocated_stable,
+        layout: *layout,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tick;
+    use proptest::prelude::*;
+    use rstest::*;
+
+    fn layout() -> ThreeRange {
+        ThreeRange::from_bounds([-3000, -1000, 1000, 3000], 10).unwrap()
+    }
+
+    fn sqrt_price(tick: i32) -> SqrtPriceX64 {
+        SqrtPriceX64::from_tick(Tick::new(tick).unwrap())
+    }
+
+    fn stable(amount: u64) -> TokenAmount<Stable> {
+        TokenAmount::new(amount)
+    }
+
+    fn meme(amount: u64) -> TokenAmount<Meme> {
+        TokenAmount::new(amount)
+    }
+
+    fn plan(
+        total_stable: u64,
+        total_meme: u64,
+        tick: i32,
+        fee_share_bps: u16,
+    ) -> EstablishmentPlan {
+        plan_establishment(
+            stable(total_stable),
+            meme(total_meme),
+            &layout(),
+            sqrt_price(tick),
+            TokenOrder::MemeFirst,
+            fee_share_bps,
+        )
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case::below_restock(-5000, Scenario::BelowFee)]
+    #[case::inside_restock(-2000, Scenario::BelowFee)]
+    #[case::at_fee_lower(-1000, Scenario::BelowFee)]
+    #[case::inside_fee(0, Scenario::WithinFee)]
+    #[case::at_fee_upper(1000, Scenario::AboveFee)]
+    #[case::above_exit(5000, Scenario::AboveFee)]
+    fn test_scenario(#[case] tick: i32, #[case] expected: Scenario) {
+        assert_eq!(plan(1_000_000, 1_000_000, tick, 5000).scenario, expected);
+    }
+
+    #[test]
+    fn test_scenario_a_leaves_restock_empty() {
+        // The price is inside R_restock, which stays empty all the same
+        let plan = plan(500_000, 1_000_000, -2000, 6000);
+        assert_eq!(plan.deposits.restock, RangeDeposit::default());
+        assert_eq!(plan.deposits.fee.stable, stable(0));
+        assert_eq!(plan.deposits.exit.stable, stable(0));
+        assert!(plan.deposits.fee.meme <= meme(600_000));
+        assert_eq!(
+            plan.required_swap,
+            Some(RequiredSwap::StableToMeme(stable(500_000)))
+        );
+        assert_eq!(plan.unallocated_stable, stable(0));
+        assert!(plan.unallocated_meme <= meme(2));
+        let ranges: [RangeKind; 2] = [RangeKind::Fee, RangeKind::Exit];
+        assert!(plan.commands().map(|c| c.range).eq(ranges));
+    }
+
+    #[test]
+    fn test_below_layout_requires_swap() {
+        let plan = plan(500_000, 1_000_000, -5000, 6000);
+        assert_eq!(plan.deposits.restock, RangeDeposit::default());
+        assert_eq!(
+            plan.required_swap,
+            Some(RequiredSwap::StableToMeme(stable(500_000)))
+        );
+        assert!(plan.unallocated_meme <= meme(2));
+        let ranges: [RangeKind; 2] = [RangeKind::Fee, RangeKind::Exit];
+        assert!(plan.commands().map(|c| c.range).eq(ranges));
+    }
+
+    #[test]
+    fn test_scenario_b_leaves_exit_empty() {
+        // The price is inside R_exit, which stays empty all the same
+        let plan = plan(1_000_000, 500_000, 2000, 6000);
+        assert_eq!(plan.deposits.exit, RangeDeposit::default());
+        assert_eq!(plan.deposits.fee.meme, meme(0));
+        assert_eq!(plan.deposits.restock.meme, meme(0));
+        assert!(plan.deposits.fee.stable <= stable(600_000));
+        assert_eq!(
+            plan.required_swap,
+            Some(RequiredSwap::MemeToStable(meme(500_000)))
+        );
+        assert_eq!(plan.unallocated_meme, meme(0));
+        assert!(plan.unallocated_stable <= stable(2));
+        let ranges: [RangeKind; 2] = [RangeKind::Restock, RangeKind::Fee];
+        assert!(plan.commands().map(|c| c.range).eq(ranges));
+    }
+
+    #[test]
+    fn test_above_layout_requires_swap() {
+        let plan = plan(1_000_000, 500_000, 5000, 6000);
+        assert_eq!(plan.deposits.exit, RangeDeposit::default());
+        assert_eq!(
+            plan.required_swap,
+            Some(RequiredSwap::MemeToStable(meme(500_000)))
+        );
+        assert!(plan.unallocated_stable <= stable(2));
+        let ranges: [RangeKind; 2] = [RangeKind::Restock, RangeKind::Fee];
+        assert!(plan.commands().map(|c| c.range).eq(ranges));
+    }
+
+    #[test]
+    fn test_scenario_c_mixes_fee_range() {
+        let plan = plan(1_000_000, 1_000_000, 0, 5000);
+        let fee = plan.deposits.fee;
+        assert!(fee.meme > meme(0) && fee.stable > stable(0));
+        assert_eq!(plan.deposits.restock.meme, meme(0));
+        assert_eq!(plan.deposits.exit.stable, stable(0));
+        assert!(plan.unallocated_meme <= meme(2) && plan.unallocated_stable <= stable(2));
+        let (lower, upper) = (sqrt_price(-1000), sqrt_price(1000));
+        assert_eq!(
+            amounts_from_liquidity(sqrt_price(0), lower, upper, fee.liquidity, true),
+            Ok((fee.meme.get(), fee.stable.get()))
+        );
+        assert_eq!(plan.commands().count(), 3);
+    }
+
+    #[test]
+    fn test_stable_first_mirrors_tokens() {
+        let plan = plan_establishment(
+            stable(1_000_000),
+            meme(1_000_000),
+            &layout(),
+            sqrt_price(0),
+            TokenOrder::StableFirst,
+            5000,
+        )
+        .unwrap();
+        // The range below the price holds token1, here the meme coin
+        assert_eq!(plan.deposits.restock.stable, stable(0));
+        assert!(plan.deposits.restock.meme > meme(0));
+        assert_eq!(plan.deposits.exit.meme, meme(0));
+        assert!(plan.deposits.exit.stable > stable(0));
+    }
+
+    #[test]
+    fn test_invalid_share() {
+        assert_eq!(
+            plan_establishment(
+                stable(1),
+                meme(1),
+                &layout(),
+                sqrt_price(0),
+                TokenOrder::MemeFirst,
+                10_001
+            ),
+            Err(JoltError::InvalidShare(10_001))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_establishment_is_exact(
+            total_stable in 0..u32::MAX as u64,
+            total_meme in 0..u32::MAX as u64,
+            tick in -4000..4000_i32,
+            stable_first: bool,
+            fee_share_bps in 0..=BPS_DENOMINATOR,
+        ) {
+            let token_order = if stable_first { TokenOrder::StableFirst } else { TokenOrder::MemeFirst };
+            let plan = plan_establishment(
+                stable(total_stable), meme(total_meme), &layout(), sqrt_price(tick), token_order, fee_share_bps,
+            ).unwrap();
+            let (mut deposited_meme, mut deposited_stable) = plan.undeployed();
+            for deposit in [plan.deposits.restock, plan.deposits.fee, plan.deposits.exit] {
+                deposited_meme = deposited_meme.checked_add(deposit.meme).unwrap();
+                deposited_stable = deposited_stable.checked_add(deposit.stable).unwrap();
+            }
+            prop_assert_eq!(deposited_meme, meme(total_meme));
+            prop_assert_eq!(deposited_stable, stable(total_stable));
+            // Rounding leaves at most one unit of each token per funded range
+            prop_assert!(plan.unallocated_meme <= meme(3));
+            prop_assert!(plan.unallocated_stable <= stable(3));
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Streaming derivative estimation for irregularly timed price samples.
+//!
+//! Price ticks arrive at uneven intervals, so derivatives are estimated from
+//! the sample times rather than an assumed fixed step. Estimators keep their
+//! history in a fixed-size ring buffer and never allocate.
+
+use crate::{Scalar, StateVector};
+
+/// Fixed-capacity ring buffer of `(time, value)` samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleBuffer<T: Scalar, const N: usize> {
+    times: [T; N],
+    values: [T; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T: Scalar, const N: usize> SampleBuffer<T, N> {
+    pub fn new() -> Self {
+        SampleBuffer {
+            times: [T::zero(); N],
+            values: [T::zero(); N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Appends a sample, overwriting the oldest one when full.
+    ///
+    /// Samples must arrive in strictly increasing time order; out of order
+    /// or duplicate timestamps and non-finite times or values are rejected
+    /// and `false` is returned.
+    pub fn push(&mut self, time: T, value: T) -> bool {
+        if N == 0 || !time.is_finite() || !value.is_finite() {
+            return false;
+        }
+        if let Some((latest, _)) = self.get(0)
+            && time <= latest
+        {
+            return false;
+        }
+        self.times[self.head] = time;
+        self.values[self.head] = value;
+        self.head = (self.head + 1) % N;
+        self.len = (self.len + 1).min(N);
+        true
+    }
+
+    /// Returns the `age`-th most recent sample, where 0 is the newest.
+    pub fn get(&self, age: usize) -> Option<(T, T)> {
+        if age >= self.len {
+            return None;
+        }
+        let index = (self.head + N - 1 - age) % N;
+        Some((self.times[index], self.values[index]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Removes every sample.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+impl<T: Scalar, const N: usize> Default for SampleBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming estimator producing a `StateVector` from raw samples.
+pub trait Estimator<T: Scalar, const D: usize> {
+    /// Feeds one sample; returns `false` if it was rejected.
+    fn push(&mut self, time: T, value: T) -> bool;
+
+    /// Position and derivatives at the most recent sample time.
+    fn state(&self) -> Option<StateVector<T, D>>;
+}
+
+/// Estimates derivatives by finite differences over the last `D` samples.
+///
+/// Fits the unique polynomial of degree `D - 1` through the most recent `D`
+/// samples using Newton divided differences, which handle non-uniform sample
+/// spacing directly, and differentiates it at the newest sample time. The
+/// buffer capacity `N` must be at least `D`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FiniteDifference<T: Scalar, const N: usize, const D: usize> {
+    samples: SampleBuffer<T, N>,
+}
+
+impl<T: Scalar, const N: usize, const D: usize> FiniteDifference<T, N, D> {
+    pub fn new() -> Self {
+        FiniteDifference {
+            samples: SampleBuffer::new(),
+        }
+    }
+
+    pub fn samples(&self) -> &SampleBuffer<T, N> {
+        &self.samples
+    }
+}
+
+impl<T: Scalar, const N: usize, const D: usize> Default for FiniteDifference<T, N, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Scalar, const N: usize, const D: usize> Estimator<T, D> for FiniteDifference<T, N, D> {
+    fn push(&mut self, time: T, value: T) -> bool {
+        self.samples.push(time, value)
+    }
+
+    fn state(&self) -> Option<StateVector<T, D>> {
+        if D == 0 || self.samples.len() < D {
+            return None;
+        }
+        let mut times = [T::zero(); D];
+        let mut table = [T::zero(); D];
+        for age in 0..D {
+            (times[age], table[age]) = self.samples.get(age)?;
+        }
+
+        // In-place divided differences: table[k] becomes f[t_0, ..., t_k]
+        for order in 1..D {
+            for i in (order..D).rev() {
+                table[i] = (table[i] - table[i - 1]) / (times[i] - times[i - order]);
+            }
+        }
+
+        // Expand the Newton form into powers of (t - t_0)
+        let mut coefficients = [T::zero(); D];
+        let mut basis = [T::zero(); D];
+        basis[0] = T::one();
+        for order in 0..D {
+            for (c, b) in coefficients.iter_mut().zip(basis.iter()) {
+                *c += table[order] * *b;
+            }
+            let offset = times[0] - times[order];
+            for m in (0..D).rev() {
+                let shifted = if m > 0 { basis[m - 1] } else { T::zero() };
+                basis[m] = shifted + offset * basis[m];
+            }
+        }
+
+        // The m-th derivative is m! times the m-th power coefficient
+        let mut factorial = T::one();
+        let mut counter = T::zero();
+        for (m, c) in coefficients.iter_mut().enumerate() {
+            if m > 0 {
+                counter += T::one();
+                factorial *= counter;
+            }
+            *c *= factorial;
+        }
+        Some(StateVector(coefficients))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const TIMES: [f64; 8] = [0.0, 0.7, 1.1, 2.5, 2.6, 4.0, 5.3, 5.9];
+
+    fn cubic(t: f64) -> f64 {
+        2.0 - 3.0 * t + 0.5 * t * t + 0.25 * t * t * t
+    }
+
+    fn cubic_state(t: f64) -> [f64; 4] {
+        [cubic(t), -3.0 + t + 0.75 * t * t, 1.0 + 1.5 * t, 1.5]
+    }
+
+    #[test]
+    fn test_sample_buffer_wraps() {
+        let mut buffer: SampleBuffer<f64, 3> = SampleBuffer::new();
+        assert!(buffer.is_empty());
+        for (i, t) in TIMES.iter().take(5).enumerate() {
+            assert!(buffer.push(*t, i as f64));
+        }
+        assert!(buffer.is_full());
+        assert_eq!(buffer.get(0), Some((2.6, 4.0)));
+        assert_eq!(buffer.get(2), Some((1.1, 2.0)));
+        assert_eq!(buffer.get(3), None);
+        buffer.clear();
+        assert_eq!(buffer.len(), 0);
+    }
+
+    #[rstest]
+    #[case::duplicate(1.0)]
+    #[case::earlier(0.5)]
+    fn test_sample_buffer_rejects_non_increasing_time(#[case] time: f64) {
+        let mut buffer: SampleBuffer<f64, 4> = SampleBuffer::new();
+        assert!(buffer.push(1.0, 10.0));
+        assert!(!buffer.push(time, 11.0));
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[rstest]
+    #[case::nan_time(f64::NAN, 11.0)]
+    #[case::infinite_time(f64::INFINITY, 11.0)]
+    #[case::nan_value(2.0, f64::NAN)]
+    #[case::infinite_value(2.0, f64::NEG_INFINITY)]
+    fn test_sample_buffer_rejects_non_finite(#[case] time: f64, #[case] value: f64) {
+        let mut buffer: SampleBuffer<f64, 4> = SampleBuffer::new();
+        assert!(buffer.push(1.0, 10.0));
+        assert!(!buffer.push(time, value));
+        assert_eq!(buffer.len(), 1);
+        // Later samples are still ordered against the last finite one
+        assert!(!buffer.push(0.5, 12.0));
+        assert!(buffer.push(3.0, 12.0));
+    }
+
+    #[test]
+    fn test_finite_difference_needs_d_samples() {
+        let mut estimator: FiniteDifference<f64, 8, 4> = FiniteDifference::new();
+        for t in TIMES.iter().take(3) {
+            estimator.push(*t, cubic(*t));
+        }
+        assert!(estimator.state().is_none());
+        estimator.push(TIMES[3], cubic(TIMES[3]));
+        assert!(estimator.state().is_some());
+    }
+
+    #[rstest]
+    #[case::first_window(4)]
+    #[case::after_wrap(8)]
+    fn test_finite_difference_exact_for_cubic(#[case] count: usize) {
+        let mut estimator: FiniteDifference<f64, 5, 4> = FiniteDifference::new();
+        for t in TIMES.iter().take(count) {
+            estimator.push(*t, cubic(*t));
+        }
+        let state = estimator.state().unwrap();
+        let expected = cubic_state(TIMES[count - 1]);
+        for (n, value) in expected.iter().enumerate() {
+            let actual = state.derivative(n).unwrap();
+            assert!((actual - value).abs() < 1e-9, "derivative {n}: {actual}");
+        }
+    }
+
+    #[test]
+    fn test_finite_difference_linear_velocity() {
+        let mut estimator: FiniteDifference<f64, 4, 2> = FiniteDifference::new();
+        for t in [10.0, 10.25, 11.0] {
+            estimator.push(t, 3.0 * t + 1.0);
+        }
+        let state = estimator.state().unwrap();
+        assert_eq!(state.position(), Some(34.0));
+        assert_eq!(state.velocity(), Some(3.0));
+        assert_eq!(state.acceleration(), None);
+    }
+}
@@ -0,0 +1,337 @@
+//! Stateful position evaluation with rebalance hysteresis and rate limits.
+//!
+//! [`crate::evaluate_clmm_position`] rebalances the moment the tick leaves
+//! the range, so a price oscillating around a boundary churns the position
+//! on every crossing. [`PositionEvaluator`] wraps it with three guards:
+//!
+//! - a dead-band of ticks beyond each boundary that must be crossed before
+//!   the position counts as out of range,
+//! - a minimum interval between rebalances,
+//! - at most `N` rebalances within any window.
+//!
+//! A suppressed rebalance is returned as [`CLMMCommand::Wait`] with the
+//! [`WaitReason`] of the first guard that fired. `Exit` is never suppressed.
+
+use crate::decision::{Decision, decide_clmm_position};
+use crate::{CLMMCommand, JoltError, PoolState, Position, Scalar, StateVector, WaitReason};
+
+/// Thresholds for [`PositionEvaluator`]. Times share the units of `update`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvaluatorConfig<T: Scalar> {
+    /// Jolt magnitude above which the position exits
+    pub jolt_limit: T,
+    /// Ticks beyond a boundary within which the position is kept
+    pub dead_band: u32,
+    /// Minimum time between rebalances
+    pub min_interval: T,
+    /// Length of the window the rebalance count is limited over
+    pub window: T,
+}
+
+/// Evaluator that remembers the last `N` rebalances.
+///
+/// `N` is the maximum number of rebalances allowed in any window; with
+/// `N == 0` every rebalance is suppressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionEvaluator<T: Scalar, const N: usize> {
+    config: EvaluatorConfig<T>,
+    /// Times of recent rebalances, oldest overwritten first
+    history: [T; N],
+    len: usize,
+    next: usize,
+    last: Option<T>,
+}
+
+impl<T: Scalar, const N: usize> PositionEvaluator<T, N> {
+    /// Fails with [`JoltError::NonFinite`] for a non-finite threshold and
+    /// [`JoltError::InvalidInterval`] for a negative interval or window,
+    /// either of which would disable the rate limits.
+    pub fn new(config: EvaluatorConfig<T>) -> Result<Self, JoltError> {
+        let thresholds = [config.jolt_limit, config.min_interval, config.window];
+        if !thresholds.iter().all(|value| value.is_finite()) {
+            return Err(JoltError::NonFinite);
+        }
+        if config.min_interval < T::zero() || config.window < T::zero() {
+            return Err(JoltError::InvalidInterval);
+        }
+        Ok(PositionEvaluator {
+            config,
+            history: [T::zero(); N],
+            len: 0,
+            next: 0,
+            last: None,
+        })
+    }
+
+    pub fn config(&self) -> &EvaluatorConfig<T> {
+        &self.config
+    }
+
+    /// Time of the last rebalance issued.
+    pub fn last_rebalance(&self) -> Option<T> {
+        self.last
+    }
+
+    /// Number of rebalances issued within the window ending at `time`.
+    pub fn rebalances_in_window(&self, time: T) -> usize {
+        self.history[..self.len]
+            .iter()
+            .filter(|&&issued| time - issued < self.config.window)
+            .count()
+    }
+
+    /// Evaluates `state` at `time`, recording any rebalance it issues.
+    ///
+    /// Fails as [`decide_clmm_position`] does, recording nothing.
+    pub fn update<const D: usize>(
+        &mut self,
+        time: T,
+        state: &StateVector<T, D>,
+        pool: &PoolState,
+        position: &Position,
+    ) -> Result<CLMMCommand, JoltError> {
+        Ok(self.decide(time, state, pool, position)?.command)
+    }
+
+    /// Like [`Self::update`], returning the rule that fired and its inputs.
+    ///
+    /// A non-finite `time` fails with [`JoltError::NonFinite`].
+    pub fn decide<const D: usize>(
+        &mut self,
+        time: T,
+        state: &StateVector<T, D>,
+        pool: &PoolState,
+        position: &Position,
+    ) -> Result<Decision<T>, JoltError> {
+        if !time.is_finite() {
+            return Err(JoltError::NonFinite);
+        }
+        let decision = decide_clmm_position(state, pool, position, self.config.jolt_limit)?;
+        if !matches!(decision.command, CLMMCommand::Rebalance { .. }) {
+            return Ok(decision);
+        }
+        if let Some(reason) = self.suppression(time, pool, position) {
+            return Ok(decision.suppress(reason));
+        }
+        self.record(time);
+        Ok(decision)
+    }
+
+    /// Forgets all recorded rebalances.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.next = 0;
+        self.last = None;
+    }
+
+    fn suppression(&self, time: T, pool: &PoolState, position: &Position) -> Option<WaitReason> {
+        let dead_band = self.config.dead_band;
+        let current_tick = pool.tick.get();
+        if position.tick_lower.get().saturating_sub_unsigned(dead_band) <= current_tick
+            && current_tick < position.tick_upper.get().saturating_add_unsigned(dead_band)
+        {
+            return Some(WaitReason::DeadBand);
+        }
+        if self
+            .last
+            .is_some_and(|last| time - last < self.config.min_interval)
+        {
+            return Some(WaitReason::MinInterval);
+        }
+        if self.rebalances_in_window(time) >= N {
+            return Some(WaitReason::WindowLimit);
+        }
+        None
+    }
+
+    fn record(&mut self, time: T) {
+        self.last = Some(time);
+        if N == 0 {
+            return;
+        }
+        self.history[self.next] = time;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decision::Rule;
+    use crate::{Liquidity, Tick, TickSpacing};
+    use rstest::*;
+
+    const CALM: StateVector<f64, 4> = StateVector([0.0; 4]);
+
+    fn evaluator<const N: usize>(dead_band: u32, min_interval: f64) -> PositionEvaluator<f64, N> {
+        PositionEvaluator::new(EvaluatorConfig {
+            jolt_limit: 1.0,
+            dead_band,
+            min_interval,
+            window: 10.0,
+        })
+        .unwrap()
+    }
+
+    fn pool(current_tick: i32) -> PoolState {
+        let spacing = TickSpacing::new(10).unwrap();
+        PoolState::at_tick(
+            Tick::new(current_tick).unwrap(),
+            Liquidity::ZERO,
+            0,
+            spacing,
+        )
+        .unwrap()
+    }
+
+    fn position() -> Position {
+        Position::new(
+            Tick::new(-100).unwrap(),
+            Tick::new(100).unwrap(),
+            Liquidity::ZERO,
+        )
+        .unwrap()
+    }
+
+    fn rebalance(new_tick_lower: i32, new_tick_upper: i32) -> CLMMCommand {
+        CLMMCommand::Rebalance {
+            new_tick_lower: Tick::new(new_tick_lower).unwrap(),
+            new_tick_upper: Tick::new(new_tick_upper).unwrap(),
+        }
+    }
+
+    #[rstest]
+    #[case::in_range(0, CLMMCommand::Hold)]
+    #[case::inside_upper_band(120, CLMMCommand::Wait(WaitReason::DeadBand))]
+    #[case::inside_lower_band(-150, CLMMCommand::Wait(WaitReason::DeadBand))]
+    #[case::beyond_band(150, rebalance(50, 250))]
+    fn test_dead_band(#[case] current_tick: i32, #[case] expected: CLMMCommand) {
+        let mut evaluator = evaluator::<4>(50, 0.0);
+        assert_eq!(
+            evaluator
+                .update(0.0, &CALM, &pool(current_tick), &position())
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_min_interval() {
+        let mut evaluator = evaluator::<4>(0, 5.0);
+        let out = pool(200);
+        assert!(matches!(
+            evaluator.update(0.0, &CALM, &out, &position()).unwrap(),
+            CLMMCommand::Rebalance { .. }
+        ));
+        assert_eq!(
+            evaluator.update(4.0, &CALM, &out, &position()).unwrap(),
+            CLMMCommand::Wait(WaitReason::MinInterval)
+        );
+        assert!(matches!(
+            evaluator.update(5.0, &CALM, &out, &position()).unwrap(),
+            CLMMCommand::Rebalance { .. }
+        ));
+        assert_eq!(evaluator.last_rebalance(), Some(5.0));
+    }
+
+    #[test]
+    fn test_window_limit() {
+        let mut evaluator = evaluator::<2>(0, 0.0);
+        let out = pool(200);
+        let commands = [0.0, 1.0, 2.0, 9.0, 10.0, 11.0, 12.0].map(|time| {
+            matches!(
+                evaluator.update(time, &CALM, &out, &position()).unwrap(),
+                CLMMCommand::Rebalance { .. }
+            )
+        });
+        assert_eq!(commands, [true, true, false, false, true, true, false]);
+        assert_eq!(
+            evaluator.update(12.0, &CALM, &out, &position()).unwrap(),
+            CLMMCommand::Wait(WaitReason::WindowLimit)
+        );
+        let decision = evaluator.decide(12.0, &CALM, &out, &position()).unwrap();
+        assert_eq!(decision.rule, Rule::Suppressed(WaitReason::WindowLimit));
+        evaluator.reset();
+        assert_eq!(evaluator.rebalances_in_window(12.0), 0);
+    }
+
+    #[test]
+    fn test_exit_is_never_suppressed() {
+        let mut evaluator = evaluator::<0>(1000, 100.0);
+        let volatile = StateVector([0.0, 0.0, 0.0, 5.0]);
+        assert_eq!(
+            evaluator
+                .update(0.0, &volatile, &pool(120), &position())
+                .unwrap(),
+            CLMMCommand::Exit
+        );
+        assert_eq!(
+            evaluator
+                .update(0.0, &CALM, &pool(2000), &position())
+                .unwrap(),
+            CLMMCommand::Wait(WaitReason::WindowLimit)
+        );
+    }
+
+    #[test]
+    fn test_error_records_nothing() {
+        let mut evaluator = evaluator::<4>(0, 0.0);
+        let invalid = Position {
+            tick_upper: Tick::new(105).unwrap(),
+            ..position()
+        };
+        assert_eq!(
+            evaluator.update(0.0, &CALM, &pool(200), &invalid),
+            Err(JoltError::TickMisaligned {
+                tick: 105,
+                spacing: 10
+            })
+        );
+        assert_eq!(evaluator.last_rebalance(), None);
+    }
+
+    #[test]
+    fn test_non_finite_time_records_nothing() {
+        let mut evaluator = evaluator::<4>(0, 5.0);
+        let out = pool(200);
+        assert_eq!(
+            evaluator.update(f64::NAN, &CALM, &out, &position()),
+            Err(JoltError::NonFinite)
+        );
+        assert_eq!(evaluator.last_rebalance(), None);
+        assert!(matches!(
+            evaluator.update(0.0, &CALM, &out, &position()).unwrap(),
+            CLMMCommand::Rebalance { .. }
+        ));
+        assert_eq!(
+            evaluator.update(f64::INFINITY, &CALM, &out, &position()),
+            Err(JoltError::NonFinite)
+        );
+        assert_eq!(
+            evaluator.update(1.0, &CALM, &out, &position()).unwrap(),
+            CLMMCommand::Wait(WaitReason::MinInterval)
+        );
+    }
+
+    #[rstest]
+    #[case::nan_window(1.0, 0.0, f64::NAN, JoltError::NonFinite)]
+    #[case::nan_min_interval(1.0, f64::NAN, 10.0, JoltError::NonFinite)]
+    #[case::infinite_jolt_limit(f64::INFINITY, 0.0, 10.0, JoltError::NonFinite)]
+    #[case::negative_window(1.0, 0.0, -1.0, JoltError::InvalidInterval)]
+    #[case::negative_min_interval(1.0, -1.0, 10.0, JoltError::InvalidInterval)]
+    fn test_invalid_config(
+        #[case] jolt_limit: f64,
+        #[case] min_interval: f64,
+        #[case] window: f64,
+        #[case] expected: JoltError,
+    ) {
+        let config = EvaluatorConfig {
+            jolt_limit,
+            dead_band: 0,
+            min_interval,
+            window,
+        };
+        assert_eq!(PositionEvaluator::<f64, 4>::new(config), Err(expected));
+    }
+}
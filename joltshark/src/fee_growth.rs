@@ -0,0 +1,217 @@
+//! Fee growth accounting and uncollected fee estimation.
+//!
+//! Fee growth is the fee earned per unit of liquidity as a Q64.64 value, kept
+//! for each token. Like the Raydium program, values wrap on overflow: only
+//! differences between two readings are meaningful, and wrapping subtraction
+//! keeps those differences exact.
+//!
+//! The pool tracks a global accumulator and, for each initialized tick, the
+//! growth on the side of the tick away from the current price. The growth
+//! inside a position's range follows from those, and the fees a position has
+//! earned since its last update are its liquidity times the change in that
+//! inside growth.
+
+use crate::JoltError;
+use crate::full_math::mul_div_floor;
+use crate::tick_math::Q64;
+
+/// Q64.64 fee growth per unit of liquidity for token0 and token1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeGrowth {
+    pub token_0_x64: u128,
+    pub token_1_x64: u128,
+}
+
+impl FeeGrowth {
+    pub fn wrapping_add(self, other: FeeGrowth) -> Self {
+        FeeGrowth {
+            token_0_x64: self.token_0_x64.wrapping_add(other.token_0_x64),
+            token_1_x64: self.token_1_x64.wrapping_add(other.token_1_x64),
+        }
+    }
+
+    pub fn wrapping_sub(self, other: FeeGrowth) -> Self {
+        FeeGrowth {
+            token_0_x64: self.token_0_x64.wrapping_sub(other.token_0_x64),
+            token_1_x64: self.token_1_x64.wrapping_sub(other.token_1_x64),
+        }
+    }
+}
+
+/// Fee growth from charging `fee_amount` to `liquidity`, rounded down.
+///
+/// Returns zero when no liquidity is active; those fees accrue to nobody.
+pub fn fee_growth_delta(fee_amount: u64, liquidity: u128) -> u128 {
+    if liquidity == 0 {
+        return 0;
+    }
+    // fee_amount * Q64 < 2^128, so the quotient always fits
+    ((fee_amount as u128) << 64) / liquidity
+}
+
+/// Fee growth inside `[tick_lower, tick_upper)`.
+///
+/// `lower_outside` and `upper_outside` are the ticks' fee growth outside
+/// values; uninitialized ticks have zero.
+pub fn fee_growth_inside(
+    tick_lower: i32,
+    lower_outside: FeeGrowth,
+    tick_upper: i32,
+    upper_outside: FeeGrowth,
+    tick_current: i32,
+    fee_growth_global: FeeGrowth,
+) -> FeeGrowth {
+    let below = if tick_lower <= tick_current {
+        lower_outside
+    } else {
+        fee_growth_global.wrapping_sub(lower_outside)
+    };
+    let above = if tick_current < tick_upper {
+        upper_outside
+    } else {
+        fee_growth_global.wrapping_sub(upper_outside)
+    };
+    fee_growth_global.wrapping_sub(below).wrapping_sub(above)
+}
+
+/// Fees earned by `liquidity` while the inside growth moved from `last` to `now`.
+pub fn fees_earned(
+    liquidity: u128,
+    fee_growth_inside_last: FeeGrowth,
+    fee_growth_inside_now: FeeGrowth,
+) -> Result<(u64, u64), JoltError> {
+    let delta = fee_growth_inside_now.wrapping_sub(fee_growth_inside_last);
+    let earned = |growth: u128| {
+        let amount = mul_div_floor(growth, liquidity, Q64)?;
+        u64::try_from(amount).map_err(|_| JoltError::Overflow)
+    };
+    Ok((earned(delta.token_0_x64)?, earned(delta.token_1_x64)?))
+}
+
+/// Fee state of one position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PositionFees {
+    pub liquidity: u128,
+    /// Inside fee growth when fees were last accrued
+    pub fee_growth_inside_last: FeeGrowth,
+    /// Fees accrued but not yet collected
+    pub fees_owed_0: u64,
+    pub fees_owed_1: u64,
+}
+
+impl PositionFees {
+    /// New position snapshotting the current inside growth.
+    pub fn new(liquidity: u128, fee_growth_inside: FeeGrowth) -> Self {
+        PositionFees {
+            liquidity,
+            fee_growth_inside_last: fee_growth_inside,
+            fees_owed_0: 0,
+            fees_owed_1: 0,
+        }
+    }
+
+    /// Uncollected fees `(token0, token1)` given the current inside growth.
+    pub fn uncollected(&self, fee_growth_inside: FeeGrowth) -> Result<(u64, u64), JoltError> {
+        let (earned_0, earned_1) = fees_earned(
+            self.liquidity,
+            self.fee_growth_inside_last,
+            fee_growth_inside,
+        )?;
+        Ok((
+            self.fees_owed_0
+                .checked_add(earned_0)
+                .ok_or(JoltError::Overflow)?,
+            self.fees_owed_1
+                .checked_add(earned_1)
+                .ok_or(JoltError::Overflow)?,
+        ))
+    }
+
+    /// Accrues earned fees into the owed amounts and moves the snapshot.
+    ///
+    /// Must be called before the position liquidity changes.
+    pub fn accrue(&mut self, fee_growth_inside: FeeGrowth) -> Result<(), JoltError> {
+        (self.fees_owed_0, self.fees_owed_1) = self.uncollected(fee_growth_inside)?;
+        self.fee_growth_inside_last = fee_growth_inside;
+        Ok(())
+    }
+
+    /// Accrues, then returns and clears the owed fees.
+    pub fn collect(&mut self, fee_growth_inside: FeeGrowth) -> Result<(u64, u64), JoltError> {
+        self.accrue(fee_growth_inside)?;
+        let owed = (self.fees_owed_0, self.fees_owed_1);
+        (self.fees_owed_0, self.fees_owed_1) = (0, 0);
+        Ok(owed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    fn growth(token_0_x64: u128, token_1_x64: u128) -> FeeGrowth {
+        FeeGrowth {
+            token_0_x64,
+            token_1_x64,
+        }
+    }
+
+    #[test]
+    fn test_fee_growth_delta() {
+        assert_eq!(fee_growth_delta(10, 0), 0);
+        assert_eq!(fee_growth_delta(3, 2), 3 * Q64 / 2);
+        assert_eq!(fee_growth_delta(u64::MAX, 1), (u64::MAX as u128) << 64);
+    }
+
+    // Growth of 3 below tick -10, 12 inside and 5 above tick 10. Each tick
+    // records the growth on its side away from the current tick.
+    #[rstest]
+    #[case::below_range(-20, 17, 5)]
+    #[case::in_range(0, 3, 5)]
+    #[case::above_range(10, 3, 15)]
+    fn test_fee_growth_inside(
+        #[case] tick_current: i32,
+        #[case] lower_outside: u128,
+        #[case] upper_outside: u128,
+    ) {
+        let inside = fee_growth_inside(
+            -10,
+            growth(lower_outside, 0),
+            10,
+            growth(upper_outside, 0),
+            tick_current,
+            growth(20, 0),
+        );
+        assert_eq!(inside, growth(12, 0));
+    }
+
+    #[test]
+    fn test_fee_growth_inside_wraps() {
+        let global = growth(5, u128::MAX);
+        let lower = growth(u128::MAX - 2, 10);
+        let inside = fee_growth_inside(-10, lower, 10, FeeGrowth::default(), 0, global);
+        assert_eq!(inside, growth(8, u128::MAX - 10));
+    }
+
+    #[test]
+    fn test_position_fees_accrue_across_wrap() {
+        let last = growth(u128::MAX - Q64 + 1, 0);
+        let now = last.wrapping_add(growth(3 * Q64, Q64 / 2));
+        let mut position = PositionFees::new(100, last);
+        assert_eq!(position.uncollected(now), Ok((300, 50)));
+        position.accrue(now).unwrap();
+        assert_eq!((position.fees_owed_0, position.fees_owed_1), (300, 50));
+        assert_eq!(position.uncollected(now), Ok((300, 50)));
+        assert_eq!(position.collect(now), Ok((300, 50)));
+        assert_eq!(position.uncollected(now), Ok((0, 0)));
+    }
+
+    #[test]
+    fn test_fees_earned_overflow() {
+        assert_eq!(
+            fees_earned(u128::MAX, FeeGrowth::default(), growth(Q64 * 2, 0)),
+            Err(JoltError::Overflow)
+        );
+    }
+}
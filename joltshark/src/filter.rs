@@ -0,0 +1,556 @@
+//! Smoothing filters for noisy derivative estimates.
+//!
+//! Raw finite differences amplify noise with every derivative order, so the
+//! higher derivatives of a meme-coin price are mostly noise. The filters here
+//! trade responsiveness for stability and report a variance for every
+//! derivative, letting downstream logic judge how far to trust a jolt spike.
+
+use crate::estimator::{Estimator, SampleBuffer};
+use crate::{JoltError, Scalar, StateVector};
+
+/// A state estimate with the variance of each derivative.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate<T: Scalar, const D: usize> {
+    pub state: StateVector<T, D>,
+    pub variance: [T; D],
+}
+
+/// Estimator that also reports the uncertainty of its output.
+pub trait VarianceEstimator<T: Scalar, const D: usize>: Estimator<T, D> {
+    /// Position, derivatives and their variances at the latest sample time.
+    fn estimate(&self) -> Option<Estimate<T, D>>;
+}
+
+/// Savitzky–Golay smoother for non-uniformly sampled data.
+///
+/// Fits a least-squares polynomial of the configured order to the samples in
+/// the window (the buffer capacity `N`) and differentiates it at the newest
+/// sample. Variances come from the residual variance of the fit propagated
+/// through the covariance of the polynomial coefficients, so at least
+/// `order + 2` samples are needed for an estimate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SavitzkyGolay<T: Scalar, const N: usize, const D: usize> {
+    samples: SampleBuffer<T, N>,
+    order: usize,
+}
+
+impl<T: Scalar, const N: usize, const D: usize> SavitzkyGolay<T, N, D> {
+    /// Returns `None` unless `order < D` and the window can over-determine
+    /// the fit (`order + 2 <= N`).
+    pub fn new(order: usize) -> Option<Self> {
+        (order < D && order + 2 <= N).then(|| SavitzkyGolay {
+            samples: SampleBuffer::new(),
+            order,
+        })
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn samples(&self) -> &SampleBuffer<T, N> {
+        &self.samples
+    }
+}
+
+impl<T: Scalar, const N: usize, const D: usize> Estimator<T, D> for SavitzkyGolay<T, N, D> {
+    fn push(&mut self, time: T, value: T) -> bool {
+        self.samples.push(time, value)
+    }
+
+    fn state(&self) -> Option<StateVector<T, D>> {
+        self.estimate().map(|estimate| estimate.state)
+    }
+}
+
+impl<T: Scalar, const N: usize, const D: usize> VarianceEstimator<T, D> for SavitzkyGolay<T, N, D> {
+    fn estimate(&self) -> Option<Estimate<T, D>> {
+        let terms = self.order + 1;
+        let count = self.samples.len();
+        if count <= terms {
+            return None;
+        }
+
+        // Scale time offsets to [-1, 0] to keep the normal equations well conditioned
+        let (newest, _) = self.samples.get(0)?;
+        let (oldest, _) = self.samples.get(count - 1)?;
+        let span = newest - oldest;
+
+        let mut normal = [[T::zero(); D]; D];
+        let mut moments = [T::zero(); D];
+        for age in 0..count {
+            let (time, value) = self.samples.get(age)?;
+            let powers = powers::<T, D>((time - newest) / span, terms);
+            for i in 0..terms {
+                moments[i] += powers[i] * value;
+                for j in 0..terms {
+                    normal[i][j] += powers[i] * powers[j];
+                }
+            }
+        }
+        let inverse = invert(normal, terms)?;
+        let mut coefficients = [T::zero(); D];
+        for i in 0..terms {
+            for j in 0..terms {
+                coefficients[i] += inverse[i][j] * moments[j];
+            }
+        }
+
+        let mut residual = T::zero();
+        let mut degrees = T::zero();
+        for age in 0..count {
+            let (time, value) = self.samples.get(age)?;
+            let powers = powers::<T, D>((time - newest) / span, terms);
+            let mut fitted = T::zero();
+            for i in 0..terms {
+                fitted += coefficients[i] * powers[i];
+            }
+            residual += (value - fitted) * (value - fitted);
+            if age >= terms {
+                degrees += T::one();
+            }
+        }
+        let sigma_squared = residual / degrees;
+
+        // Undo the time scaling: d^m/dt^m = m! * c_m / span^m
+        let mut state = [T::zero(); D];
+        let mut variance = [T::zero(); D];
+        let mut factor = T::one();
+        let mut counter = T::zero();
+        for m in 0..terms {
+            if m > 0 {
+                counter += T::one();
+                factor = factor * counter / span;
+            }
+            state[m] = coefficients[m] * factor;
+            variance[m] = sigma_squared * inverse[m][m] * factor * factor;
+        }
+        Some(Estimate {
+            state: StateVector(state),
+            variance,
+        })
+    }
+}
+
+/// Kalman filter over position and its first `D - 1` derivatives.
+///
+/// Uses a constant highest-derivative motion model driven by white noise of
+/// spectral density `process_noise`; with `D = 4` this is the constant-jerk
+/// model over position, velocity, acceleration and jolt. Measurements are
+/// prices with variance `measurement_noise`. The transition is rebuilt from
+/// each sample interval, so irregular sampling is handled exactly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Kalman<T: Scalar, const D: usize> {
+    /// Spectral density of the noise driving the highest derivative
+    pub process_noise: T,
+    /// Variance of each price measurement
+    pub measurement_noise: T,
+    /// Prior variance of the derivatives before the first update
+    pub initial_variance: T,
+    state: [T; D],
+    covariance: [[T; D]; D],
+    last_time: Option<T>,
+}
+
+impl<T: Scalar, const D: usize> Kalman<T, D> {
+    /// Fails with [`JoltError::NonFinite`] for a non-finite parameter and
+    /// [`JoltError::InvalidNoise`] for a negative one or a zero
+    /// `measurement_noise`, which would divide zero by zero on the first
+    /// update.
+    pub fn new(
+        process_noise: T,
+        measurement_noise: T,
+        initial_variance: T,
+    ) -> Result<Self, JoltError> {
+        let parameters = [process_noise, measurement_noise, initial_variance];
+        if !parameters.iter().all(|value| value.is_finite()) {
+            return Err(JoltError::NonFinite);
+        }
+        if parameters.iter().any(|value| *value < T::zero()) || measurement_noise == T::zero() {
+            return Err(JoltError::InvalidNoise);
+        }
+        Ok(Kalman {
+            process_noise,
+            measurement_noise,
+            initial_variance,
+            state: [T::zero(); D],
+            covariance: [[T::zero(); D]; D],
+            last_time: None,
+        })
+    }
+
+    /// Discards the filter state; the next sample reinitializes it.
+    pub fn reset(&mut self) {
+        self.last_time = None;
+    }
+
+    fn predict(&mut self, dt: T) {
+        // Transition F[i][j] = dt^(j - i) / (j - i)!
+        let mut transition = [[T::zero(); D]; D];
+        let steps = powers::<T, D>(dt, D);
+        for (i, row) in transition.iter_mut().enumerate() {
+            for (j, entry) in row.iter_mut().enumerate().skip(i) {
+                *entry = steps[j - i] / factorial(j - i);
+            }
+        }
+
+        let mut predicted = [T::zero(); D];
+        for (p, row) in predicted.iter_mut().zip(transition.iter()) {
+            *p = dot(row, &self.state);
+        }
+        self.state = predicted;
+
+        // P = F P F^T + Q
+        let mut fp = [[T::zero(); D]; D];
+        for (fp_row, row) in fp.iter_mut().zip(transition.iter()) {
+            for (f, covariance_row) in row.iter().zip(self.covariance.iter()) {
+                for (entry, p) in fp_row.iter_mut().zip(covariance_row.iter()) {
+                    *entry += *f * *p;
+                }
+            }
+        }
+        let last = D - 1;
+        for (i, (row, fp_row)) in self.covariance.iter_mut().zip(fp.iter()).enumerate() {
+            for (j, (entry, transition_row)) in row.iter_mut().zip(transition.iter()).enumerate() {
+                // Integrated white noise on the highest derivative
+                let exponent = 2 * last + 1 - i - j;
+                let denominator =
+                    factorial::<T>(last - i) * factorial::<T>(last - j) * from_count::<T>(exponent);
+                let noise = self.process_noise * power(dt, exponent) / denominator;
+                *entry = dot(fp_row, transition_row) + noise;
+            }
+        }
+    }
+
+    fn update(&mut self, value: T) {
+        // H = [1, 0, ...], so the innovation covariance is P[0][0] + R
+        let innovation = value - self.state[0];
+        let innovation_variance = self.covariance[0][0] + self.measurement_noise;
+        let mut gain = [T::zero(); D];
+        for (i, g) in gain.iter_mut().enumerate() {
+            *g = self.covariance[i][0] / innovation_variance;
+        }
+        for (s, g) in self.state.iter_mut().zip(gain.iter()) {
+            *s += *g * innovation;
+        }
+        let first_row = self.covariance[0];
+        for (i, row) in self.covariance.iter_mut().enumerate() {
+            for (entry, p) in row.iter_mut().zip(first_row.iter()) {
+                *entry -= gain[i] * *p;
+            }
+        }
+    }
+}
+
+impl<T: Scalar, const D: usize> Estimator<T, D> for Kalman<T, D> {
+    /// Rejects non-increasing and non-finite times and non-finite values,
+    /// which would otherwise poison the state for every later sample.
+    fn push(&mut self, time: T, value: T) -> bool {
+        if D == 0 || !time.is_finite() || !value.is_finite() {
+            return false;
+        }
+        match self.last_time {
+            Some(last) if time <= last => return false,
+            Some(last) => {
+                self.predict(time - last);
+                self.update(value);
+            }
+            None => {
+                self.state = [T::zero(); D];
+                self.state[0] = value;
+                self.covariance = [[T::zero(); D]; D];
+                for (i, row) in self.covariance.iter_mut().enumerate() {
+                    row[i] = self.initial_variance;
+                }
+                self.covariance[0][0] = self.measurement_noise;
+            }
+        }
+        self.last_time = Some(time);
+        true
+    }
+
+    fn state(&self) -> Option<StateVector<T, D>> {
+        self.last_time.map(|_| StateVector(self.state))
+    }
+}
+
+impl<T: Scalar, const D: usize> VarianceEstimator<T, D> for Kalman<T, D> {
+    fn estimate(&self) -> Option<Estimate<T, D>> {
+        let state = self.state()?;
+        let mut variance = [T::zero(); D];
+        for (i, v) in variance.iter_mut().enumerate() {
+            *v = self.covariance[i][i];
+        }
+        Some(Estimate { state, variance })
+    }
+}
+
+/// Smoothing filter selected when building a `StateVector` stream.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoother<T: Scalar, const N: usize, const D: usize> {
+    SavitzkyGolay(SavitzkyGolay<T, N, D>),
+    Kalman(Kalman<T, D>),
+}
+
+impl<T: Scalar, const N: usize, const D: usize> Estimator<T, D> for Smoother<T, N, D> {
+    fn push(&mut self, time: T, value: T) -> bool {
+        match self {
+            Smoother::SavitzkyGolay(filter) => filter.push(time, value),
+            Smoother::Kalman(filter) => filter.push(time, value),
+        }
+    }
+
+    fn state(&self) -> Option<StateVector<T, D>> {
+        match self {
+            Smoother::SavitzkyGolay(filter) => filter.state(),
+            Smoother::Kalman(filter) => filter.state(),
+        }
+    }
+}
+
+impl<T: Scalar, const N: usize, const D: usize> VarianceEstimator<T, D> for Smoother<T, N, D> {
+    fn estimate(&self) -> Option<Estimate<T, D>> {
+        match self {
+            Smoother::SavitzkyGolay(filter) => filter.estimate(),
+            Smoother::Kalman(filter) => filter.estimate(),
+        }
+    }
+}
+
+/// `[1, x, x^2, ...]` for the first `terms` entries.
+fn powers<T: Scalar, const D: usize>(x: T, terms: usize) -> [T; D] {
+    let mut result = [T::zero(); D];
+    let mut value = T::one();
+    for r in result.iter_mut().take(terms) {
+        *r = value;
+        value *= x;
+    }
+    result
+}
+
+fn power<T: Scalar>(x: T, exponent: usize) -> T {
+    let mut result = T::one();
+    for _ in 0..exponent {
+        result *= x;
+    }
+    result
+}
+
+fn from_count<T: Scalar>(n: usize) -> T {
+    let mut result = T::zero();
+    for _ in 0..n {
+        result += T::one();
+    }
+    result
+}
+
+fn dot<T: Scalar, const D: usize>(a: &[T; D], b: &[T; D]) -> T {
+    a.iter()
+        .zip(b.iter())
+        .fold(T::zero(), |sum, (x, y)| sum + *x * *y)
+}
+
+fn factorial<T: Scalar>(n: usize) -> T {
+    let mut result = T::one();
+    for k in 2..=n {
+        result *= from_count::<T>(k);
+    }
+    result
+}
+
+/// Inverts the leading `size` x `size` block by Gauss-Jordan elimination.
+fn invert<T: Scalar, const D: usize>(mut matrix: [[T; D]; D], size: usize) -> Option<[[T; D]; D]> {
+    let mut inverse = [[T::zero(); D]; D];
+    for (i, row) in inverse.iter_mut().enumerate().take(size) {
+        row[i] = T::one();
+    }
+    for column in 0..size {
+        let pivot_row = (column..size).max_by(|a, b| {
+            matrix[*a][column]
+                .abs()
+                .partial_cmp(&matrix[*b][column].abs())
+                .unwrap_or(core::cmp::Ordering::Equal)
+        })?;
+        if matrix[pivot_row][column] == T::zero() {
+            return None;
+        }
+        matrix.swap(column, pivot_row);
+        inverse.swap(column, pivot_row);
+
+        let pivot = matrix[column][column];
+        for j in 0..size {
+            matrix[column][j] /= pivot;
+            inverse[column][j] /= pivot;
+        }
+        for row in 0..size {
+            if row != column {
+                let factor = matrix[row][column];
+                for j in 0..size {
+                    let (m, v) = (matrix[column][j], inverse[column][j]);
+                    matrix[row][j] -= factor * m;
+                    inverse[row][j] -= factor * v;
+                }
+            }
+        }
+    }
+    Some(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const TIMES: [f64; 10] = [0.0, 0.4, 1.1, 1.5, 2.6, 3.0, 3.3, 4.8, 5.1, 6.0];
+
+    fn cubic(t: f64) -> f64 {
+        2.0 - 3.0 * t + 0.5 * t * t + 0.25 * t * t * t
+    }
+
+    /// Deterministic zero-mean noise in [-1, 1].
+    fn noise(i: usize) -> f64 {
+        [0.8, -0.3, -0.9, 0.5, 0.1, -0.6, 0.9, -0.2, 0.4, -0.7][i % 10]
+    }
+
+    #[rstest]
+    #[case::order_too_high(4)]
+    #[case::window_too_small(3)]
+    fn test_savitzky_golay_rejects_order(#[case] order: usize) {
+        assert!(SavitzkyGolay::<f64, 4, 4>::new(order).is_none());
+    }
+
+    #[test]
+    fn test_savitzky_golay_exact_for_cubic() {
+        let mut filter: SavitzkyGolay<f64, 8, 4> = SavitzkyGolay::new(3).unwrap();
+        for t in TIMES {
+            filter.push(t, cubic(t));
+        }
+        let estimate = filter.estimate().unwrap();
+        let t = TIMES[9];
+        let expected = [cubic(t), -3.0 + t + 0.75 * t * t, 1.0 + 1.5 * t, 1.5];
+        for (n, value) in expected.iter().enumerate() {
+            let actual = estimate.state.derivative(n).unwrap();
+            assert!((actual - value).abs() < 1e-6, "derivative {n}: {actual}");
+            assert!(estimate.variance[n].abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_savitzky_golay_lower_order_zeroes_higher_derivatives() {
+        let mut filter: SavitzkyGolay<f64, 6, 4> = SavitzkyGolay::new(1).unwrap();
+        for t in TIMES {
+            filter.push(t, 5.0 + 2.0 * t + 0.01 * noise(t as usize));
+        }
+        let estimate = filter.estimate().unwrap();
+        assert!((estimate.state.velocity().unwrap() - 2.0).abs() < 0.05);
+        assert_eq!(estimate.state.acceleration(), Some(0.0));
+        assert_eq!(estimate.variance[3], 0.0);
+    }
+
+    #[test]
+    fn test_savitzky_golay_reports_noise_variance() {
+        let mut filter: SavitzkyGolay<f64, 10, 3> = SavitzkyGolay::new(2).unwrap();
+        assert!(filter.estimate().is_none());
+        for (i, t) in TIMES.iter().enumerate() {
+            filter.push(*t, 1.0 + 0.5 * t + 0.1 * noise(i));
+        }
+        let estimate = filter.estimate().unwrap();
+        assert!((estimate.state.velocity().unwrap() - 0.5).abs() < 0.2);
+        assert!(estimate.variance.iter().all(|v| *v > 0.0));
+    }
+
+    #[test]
+    fn test_kalman_tracks_linear_trend() {
+        let mut filter: Kalman<f64, 4> = Kalman::new(1e-6, 1e-4, 1e3).unwrap();
+        assert!(filter.estimate().is_none());
+        let mut t = 0.0;
+        for i in 0..200 {
+            t += 0.5 + 0.1 * noise(i).abs();
+            assert!(filter.push(t, 10.0 + 0.2 * t));
+        }
+        let estimate = filter.estimate().unwrap();
+        assert!((estimate.state.position().unwrap() - (10.0 + 0.2 * t)).abs() < 1e-2);
+        assert!((estimate.state.velocity().unwrap() - 0.2).abs() < 1e-2);
+        assert!(estimate.state.jolt().unwrap().abs() < 1e-2);
+        assert!(estimate.variance.iter().all(|v| *v > 0.0));
+    }
+
+    #[test]
+    fn test_kalman_variance_shrinks_with_data() {
+        let mut filter: Kalman<f64, 4> = Kalman::new(1e-3, 1e-2, 1e2).unwrap();
+        filter.push(0.0, 1.0);
+        filter.push(1.0, 1.1);
+        let early = filter.estimate().unwrap().variance;
+        for i in 2..50 {
+            filter.push(i as f64, 1.0 + 0.1 * i as f64 + 0.05 * noise(i));
+        }
+        let late = filter.estimate().unwrap().variance;
+        for (n, (late, early)) in late.iter().zip(early.iter()).enumerate() {
+            assert!(late < early, "variance {n} did not shrink");
+        }
+    }
+
+    #[test]
+    fn test_kalman_rejects_non_increasing_time_and_resets() {
+        let mut filter: Kalman<f64, 2> = Kalman::new(1.0, 1.0, 1.0).unwrap();
+        assert!(filter.push(1.0, 5.0));
+        assert!(!filter.push(1.0, 6.0));
+        filter.reset();
+        assert!(filter.state().is_none());
+        assert!(filter.push(0.5, 7.0));
+        assert_eq!(filter.state().unwrap().position(), Some(7.0));
+    }
+
+    #[test]
+    fn test_kalman_rejects_non_finite() {
+        let mut filter: Kalman<f64, 2> = Kalman::new(1.0, 1.0, 1.0).unwrap();
+        assert!(!filter.push(f64::NAN, 5.0));
+        assert!(filter.state().is_none());
+        assert!(filter.push(1.0, 5.0));
+        assert!(!filter.push(f64::NAN, 6.0));
+        assert!(!filter.push(2.0, f64::INFINITY));
+        assert!(!filter.push(0.5, 6.0));
+        assert!(filter.push(2.0, 6.0));
+        assert!(
+            filter
+                .state()
+                .unwrap()
+                .0
+                .iter()
+                .all(|value| value.is_finite())
+        );
+    }
+
+    #[rstest]
+    #[case::zero_measurement_noise(1.0, 0.0, 1.0, JoltError::InvalidNoise)]
+    #[case::negative_process_noise(-1.0, 1.0, 1.0, JoltError::InvalidNoise)]
+    #[case::negative_variance(1.0, 1.0, -1.0, JoltError::InvalidNoise)]
+    #[case::nan_process_noise(f64::NAN, 1.0, 1.0, JoltError::NonFinite)]
+    #[case::infinite_variance(1.0, 1.0, f64::INFINITY, JoltError::NonFinite)]
+    fn test_kalman_rejects_invalid_noise(
+        #[case] process_noise: f64,
+        #[case] measurement_noise: f64,
+        #[case] initial_variance: f64,
+        #[case] expected: JoltError,
+    ) {
+        assert_eq!(
+            Kalman::<f64, 4>::new(process_noise, measurement_noise, initial_variance),
+            Err(expected)
+        );
+    }
+
+    #[test]
+    fn test_smoother_dispatch() {
+        let mut smoothers: [Smoother<f64, 6, 3>; 2] = [
+            Smoother::SavitzkyGolay(SavitzkyGolay::new(2).unwrap()),
+            Smoother::Kalman(Kalman::new(1e-6, 1e-6, 1e3).unwrap()),
+        ];
+        for smoother in smoothers.iter_mut() {
+            for t in TIMES {
+                smoother.push(t, 3.0 * t);
+            }
+            let estimate = smoother.estimate().unwrap();
+            assert!((estimate.state.velocity().unwrap() - 3.0).abs() < 1e-2);
+        }
+    }
+}
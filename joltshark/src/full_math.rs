@@ -0,0 +1,479 @@
+//! Full-precision multiply-divide on u128 with explicit rounding.
+//!
+//! `a * b / denominator` is computed from the exact 256-bit product, so no
+//! precision is lost before the single division and the only way to fail,
+//! apart from a zero denominator, is a quotient that does not fit in u128.
+//! Amounts and liquidity sent on-chain must round exactly as the program
+//! does, so every function names its rounding direction.
+
+use crate::JoltError;
+
+/// `a * b / denominator`, rounded down.
+pub fn mul_div_floor(a: u128, b: u128, denominator: u128) -> Result<u128, JoltError> {
+    U256::from_u128(a).mul_div_floor(b, denominator)?.to_u128()
+}
+
+/// `a * b / denominator`, rounded up.
+pub fn mul_div_ceil(a: u128, b: u128, denominator: u128) -> Result<u128, JoltError> {
+    U256::from_u128(a).mul_div_ceil(b, denominator)?.to_u128()
+}
+
+/// `numerator / denominator`, rounded up.
+pub fn div_rounding_up(numerator: u128, denominator: u128) -> Result<u128, JoltError> {
+    U256::from_u128(numerator)
+        .div_rounding_up(U256::from_u128(denominator))?
+        .to_u128()
+}
+
+/// Minimal unsigned 256-bit integer for full-precision intermediates.
+///
+/// Field order makes the derived ordering numeric.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct U256 {
+    hi: u128,
+    lo: u128,
+}
+
+impl U256 {
+    pub(crate) const ZERO: U256 = U256 { hi: 0, lo: 0 };
+
+    pub(crate) fn from_u128(value: u128) -> Self {
+        U256 { hi: 0, lo: value }
+    }
+
+    /// Full 256-bit product of two u128 values.
+    pub(crate) fn mul(a: u128, b: u128) -> Self {
+        const MASK: u128 = u64::MAX as u128;
+        let (a_hi, a_lo) = (a >> 64, a & MASK);
+        let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+        let lo_lo = a_lo * b_lo;
+        let hi_lo = a_hi * b_lo;
+        let lo_hi = a_lo * b_hi;
+        let hi_hi = a_hi * b_hi;
+
+        let middle = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+        let lo = (lo_lo & MASK) | (middle << 64);
+        let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (middle >> 64);
+        U256 { hi, lo }
+    }
+
+    /// Product with a u128, failing on overflow.
+    pub(crate) fn checked_mul(self, factor: u128) -> Result<Self, JoltError> {
+        let low = U256::mul(self.lo, factor);
+        let high = U256::mul(self.hi, factor);
+        if high.hi != 0 {
+            return Err(JoltError::Overflow);
+        }
+        let hi = low.hi.checked_add(high.lo).ok_or(JoltError::Overflow)?;
+        Ok(U256 { hi, lo: low.lo })
+    }
+
+    /// `self * factor / denominator`, rounded down.
+    pub(crate) fn mul_div_floor(self, factor: u128, denominator: u128) -> Result<Self, JoltError> {
+        Ok(self.mul_div(factor, denominator)?.0)
+    }
+
+    /// `self * factor / denominator`, rounded up.
+    pub(crate) fn mul_div_ceil(self, factor: u128, denominator: u128) -> Result<Self, JoltError> {
+        match self.mul_div(factor, denominator)? {
+            (quotient, true) => quotient.add_one(),
+            (quotient, false) => Ok(quotient),
+        }
+    }
+
+    /// Quotient rounded down and whether the division left a remainder.
+    ///
+    /// The product may exceed 256 bits; only the quotient has to fit.
+    fn mul_div(self, factor: u128, denominator: u128) -> Result<(Self, bool), JoltError> {
+        if denominator == 0 {
+            return Err(JoltError::DivisionByZero);
+        }
+        // self = q * d + r, so self * f / d = q * f + r * f / d exactly
+        let (quotient, remainder) = self.div_rem(denominator);
+        let (fraction, remainder) = U256::mul(remainder, factor).div_rem(denominator);
+        let quotient = quotient.checked_mul(factor)?.add(fraction)?;
+        Ok((quotient, remainder != 0))
+    }
+
+    /// `self / divisor`, rounded up.
+    pub(crate) fn div_rounding_up(self, divisor: U256) -> Result<Self, JoltError> {
+        if divisor == U256::ZERO {
+            return Err(JoltError::DivisionByZero);
+        }
+        match self.div_rem_wide(divisor) {
+            (quotient, U256::ZERO) => Ok(quotient),
+            (quotient, _) => quotient.add_one(),
+        }
+    }
+
+    /// Shifts left by `shift < 128` bits, failing if any bit is lost.
+    pub(crate) fn shl(self, shift: u32) -> Result<Self, JoltError> {
+        if shift == 0 {
+            return Ok(self);
+        }
+        if self.hi >> (128 - shift) != 0 {
+            return Err(JoltError::Overflow);
+        }
+        Ok(U256 {
+            hi: (self.hi << shift) | (self.lo >> (128 - shift)),
+            lo: self.lo << shift,
+        })
+    }
+
+    /// Adds one, failing on overflow.
+    pub(crate) fn add_one(self) -> Result<Self, JoltError> {
+        let (lo, carry) = self.lo.overflowing_add(1);
+        let hi = self
+            .hi
+            .checked_add(carry as u128)
+            .ok_or(JoltError::Overflow)?;
+        Ok(U256 { hi, lo })
+    }
+
+    /// Sum of two values, failing on overflow.
+    pub(crate) fn add(self, other: U256) -> Result<Self, JoltError> {
+        let (lo, carry) = self.lo.overflowing_add(other.lo);
+        let hi = self
+            .hi
+            .checked_add(other.hi)
+            .and_then(|hi| hi.checked_add(carry as u128))
+            .ok_or(JoltError::Overflow)?;
+        Ok(U256 { hi, lo })
+    }
+
+    /// Difference `self - other`, wrapping below zero.
+    pub(crate) fn wrapping_sub(self, other: U256) -> Self {
+        let (lo, borrow) = self.lo.overflowing_sub(other.lo);
+        let hi = self.hi.wrapping_sub(other.hi).wrapping_sub(borrow as u128);
+        U256 { hi, lo }
+    }
+
+    /// `self * factor / denominator` for a wide denominator, rounded up.
+    ///
+    /// The product may exceed 256 bits; only the quotient has to fit.
+    pub(crate) fn mul_div_ceil_wide(
+        self,
+        factor: u128,
+        denominator: U256,
+    ) -> Result<Self, JoltError> {
+        if denominator == U256::ZERO {
+            return Err(JoltError::DivisionByZero);
+        }
+        let (high, low) = self.mul_wide(factor);
+        if high >= denominator {
+            return Err(JoltError::Overflow);
+        }
+        match low.div_rem_from(high, denominator) {
+            (quotient, U256::ZERO) => Ok(quotient),
+            (quotient, _) => quotient.add_one(),
+        }
+    }
+
+    /// Full product with a u128 as high and low 256-bit halves.
+    fn mul_wide(self, factor: u128) -> (Self, Self) {
+        let low = U256::mul(self.lo, factor);
+        let high = U256::mul(self.hi, factor);
+        let (middle, carry) = low.hi.overflowing_add(high.lo);
+        // The product is below 2^384, so the top word cannot overflow
+        (
+            U256::from_u128(high.hi + carry as u128),
+            U256 {
+                hi: middle,
+                lo: low.lo,
+            },
+        )
+    }
+
+    /// Quotient and remainder of division by a non-zero U256.
+    pub(crate) fn div_rem_wide(self, divisor: U256) -> (Self, Self) {
+        self.div_rem_from(U256::ZERO, divisor)
+    }
+
+    /// Divides `high * 2^256 + self` by a non-zero U256 above `high`, so the
+    /// quotient fits in 256 bits.
+    fn div_rem_from(self, high: U256, divisor: U256) -> (Self, Self) {
+        let mut quotient = U256::ZERO;
+        let mut remainder = high;
+        for i in (0..256).rev() {
+            let bit = if i >= 128 {
+                (self.hi >> (i - 128)) & 1
+            } else {
+                (self.lo >> i) & 1
+            };
+            // remainder < divisor, so a carry out of bit 255 means >= divisor
+            let carry = remainder.hi >> 127;
+            remainder = U256 {
+                hi: (remainder.hi << 1) | (remainder.lo >> 127),
+                lo: (remainder.lo << 1) | bit,
+            };
+            if carry != 0 || remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                if i >= 128 {
+                    quotient.hi |= 1 << (i - 128);
+                } else {
+                    quotient.lo |= 1 << i;
+                }
+            }
+        }
+        (quotient, remainder)
+    }
+
+    /// Quotient and remainder of division by a non-zero u128.
+    pub(crate) fn div_rem(self, divisor: u128) -> (Self, u128) {
+        let mut quotient = U256::ZERO;
+        let mut remainder: u128 = 0;
+        for i in (0..256).rev() {
+            let bit = if i >= 128 {
+                (self.hi >> (i - 128)) & 1
+            } else {
+                (self.lo >> i) & 1
+            };
+            // remainder < divisor, so a carry out of bit 127 means >= divisor
+            let carry = remainder >> 127;
+            remainder = (remainder << 1) | bit;
+            if carry != 0 || remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                if i >= 128 {
+                    quotient.hi |= 1 << (i - 128);
+                } else {
+                    quotient.lo |= 1 << i;
+                }
+            }
+        }
+        (quotient, remainder)
+    }
+
+    /// Narrows to u128, failing if the high half is set.
+    pub(crate) fn to_u128(self) -> Result<u128, JoltError> {
+        if self.hi == 0 {
+            Ok(self.lo)
+        } else {
+            Err(JoltError::Overflow)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tick_math::Q64;
+    use proptest::prelude::*;
+    use rstest::*;
+
+    const MAX: u128 = u128::MAX;
+
+    /// `(2^129 - 1) / 7`, so `7 * SEVENTH == 2 * MAX + 1`.
+    const SEVENTH: u128 = (MAX / 7) * 2 + 1;
+
+    #[test]
+    fn test_u256_mul() {
+        assert_eq!(U256::mul(MAX, MAX), U256 { hi: MAX - 1, lo: 1 });
+        assert_eq!(U256::mul(Q64, Q64), U256 { hi: 1, lo: 0 });
+    }
+
+    #[test]
+    fn test_u256_div_rem() {
+        let product = U256::mul(MAX, MAX);
+        assert_eq!(product.div_rem(MAX), (U256 { hi: 0, lo: MAX }, 0));
+        let (quotient, remainder) = U256::mul(1000, 7).div_rem(3);
+        assert_eq!((quotient.to_u128(), remainder), (Ok(2333), 1));
+    }
+
+    #[test]
+    fn test_u256_div_rem_wide() {
+        let product = U256::mul(MAX, MAX);
+        let divisor = U256::mul(MAX, 3);
+        let (quotient, remainder) = product.div_rem_wide(divisor);
+        assert_eq!(quotient.to_u128(), Ok(MAX / 3));
+        assert_eq!(remainder, U256::ZERO);
+        let (quotient, remainder) = U256::from_u128(7).div_rem_wide(product);
+        assert_eq!((quotient, remainder), (U256::ZERO, U256::from_u128(7)));
+        let sum = product.add(U256::from_u128(MAX)).unwrap();
+        assert_eq!(sum.div_rem_wide(U256::from_u128(MAX)).1, U256::ZERO);
+        let full = sum.add(U256::from_u128(MAX)).unwrap();
+        assert_eq!(full.add(U256::from_u128(1)), Err(JoltError::Overflow));
+    }
+
+    #[test]
+    fn test_u256_mul_div_ceil_wide() {
+        // MAX^3 / MAX^2 needs a 384-bit product
+        let square = U256::mul(MAX, MAX);
+        assert_eq!(
+            square.mul_div_ceil_wide(MAX, square),
+            Ok(U256::from_u128(MAX))
+        );
+        let divisor = square.add(U256::from_u128(1)).unwrap();
+        assert_eq!(
+            square.mul_div_ceil_wide(MAX, divisor),
+            Ok(U256::from_u128(MAX))
+        );
+        assert_eq!(
+            U256::from_u128(10).mul_div_ceil_wide(1, U256::from_u128(3)),
+            Ok(U256::from_u128(4))
+        );
+        assert_eq!(
+            square.mul_div_ceil_wide(MAX, U256::from_u128(1)),
+            Err(JoltError::Overflow)
+        );
+        assert_eq!(
+            square.mul_div_ceil_wide(MAX, U256::ZERO),
+            Err(JoltError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_u256_checked_mul() {
+        let wide = U256::from_u128(MAX).shl(64).unwrap();
+        assert_eq!(
+            U256::from_u128(MAX).checked_mul(MAX),
+            Ok(U256::mul(MAX, MAX))
+        );
+        assert_eq!(
+            wide.checked_mul(1 << 63),
+            Ok(U256 {
+                hi: MAX >> 1,
+                lo: 1 << 127
+            })
+        );
+        assert_eq!(wide.checked_mul(1 << 64), Ok(U256 { hi: MAX, lo: 0 }));
+        assert_eq!(wide.checked_mul(1 << 65), Err(JoltError::Overflow));
+        assert_eq!(
+            U256 { hi: 1, lo: MAX }.checked_mul(MAX),
+            Err(JoltError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_u256_mul_div_wide() {
+        // Products beyond 256 bits with quotients that fit
+        let wide = U256::from_u128(MAX).shl(64).unwrap();
+        assert_eq!(wide.mul_div_floor(MAX, MAX), Ok(wide));
+        let shrunk = wide.wrapping_sub(U256::from_u128(Q64));
+        assert_eq!(wide.mul_div_floor(MAX - 1, MAX), Ok(shrunk));
+        assert_eq!(wide.mul_div_ceil(MAX - 1, MAX), Ok(shrunk));
+        let seventh = wide.mul_div_floor(1, 7).unwrap();
+        assert_eq!(wide.mul_div_ceil(1, 7), seventh.add_one());
+        assert_eq!(wide.mul_div_floor(MAX, 1), Err(JoltError::Overflow));
+        assert_eq!(wide.mul_div_ceil(1, 0), Err(JoltError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_u256_div_rounding_up() {
+        let max = U256 { hi: MAX, lo: MAX };
+        let half = U256 {
+            hi: 1 << 127,
+            lo: 0,
+        };
+        assert_eq!(max.div_rounding_up(U256::from_u128(2)), Ok(half));
+        assert_eq!(max.div_rounding_up(max), Ok(U256::from_u128(1)));
+        assert_eq!(U256::ZERO.div_rounding_up(max), Ok(U256::ZERO));
+        assert_eq!(
+            max.div_rounding_up(U256::ZERO),
+            Err(JoltError::DivisionByZero)
+        );
+    }
+
+    #[rstest]
+    #[case::zero_a(0, MAX, 1, Ok(0), Ok(0))]
+    #[case::zero_b(MAX, 0, 7, Ok(0), Ok(0))]
+    #[case::unit_denominator(MAX, 1, 1, Ok(MAX), Ok(MAX))]
+    #[case::exact(6, 7, 3, Ok(14), Ok(14))]
+    #[case::remainder(1000, 7, 3, Ok(2333), Ok(2334))]
+    #[case::below_one(1, 1, 2, Ok(0), Ok(1))]
+    #[case::below_one_of_max(1, 1, MAX, Ok(0), Ok(1))]
+    #[case::max_squared_over_max(MAX, MAX, MAX, Ok(MAX), Ok(MAX))]
+    #[case::max_squared_over_max_less_one(MAX, MAX, MAX - 1, Err(JoltError::Overflow), Err(JoltError::Overflow))]
+    #[case::max_times_two(MAX, 2, 2, Ok(MAX), Ok(MAX))]
+    #[case::product_above_u128(Q64, Q64, Q64, Ok(Q64), Ok(Q64))]
+    #[case::wide_product_narrow_quotient(MAX, MAX - 1, MAX, Ok(MAX - 1), Ok(MAX - 1))]
+    #[case::quotient_just_fits(MAX, 3, 3, Ok(MAX), Ok(MAX))]
+    #[case::quotient_just_overflows(MAX, 3, 2, Err(JoltError::Overflow), Err(JoltError::Overflow))]
+    #[case::ceil_overflows(7, SEVENTH, 2, Ok(MAX), Err(JoltError::Overflow))]
+    #[case::zero_denominator(
+        1,
+        1,
+        0,
+        Err(JoltError::DivisionByZero),
+        Err(JoltError::DivisionByZero)
+    )]
+    #[case::zero_over_zero(
+        0,
+        0,
+        0,
+        Err(JoltError::DivisionByZero),
+        Err(JoltError::DivisionByZero)
+    )]
+    fn test_mul_div(
+        #[case] a: u128,
+        #[case] b: u128,
+        #[case] denominator: u128,
+        #[case] floor: Result<u128, JoltError>,
+        #[case] ceil: Result<u128, JoltError>,
+    ) {
+        assert_eq!(mul_div_floor(a, b, denominator), floor);
+        assert_eq!(mul_div_ceil(a, b, denominator), ceil);
+        // Multiplication commutes
+        assert_eq!(mul_div_floor(b, a, denominator), floor);
+        assert_eq!(mul_div_ceil(b, a, denominator), ceil);
+    }
+
+    #[rstest]
+    #[case::zero(0, 5, Ok(0))]
+    #[case::exact(10, 5, Ok(2))]
+    #[case::remainder(11, 5, Ok(3))]
+    #[case::below_one(1, MAX, Ok(1))]
+    #[case::max_over_one(MAX, 1, Ok(MAX))]
+    #[case::max_over_two(MAX, 2, Ok(MAX / 2 + 1))]
+    #[case::max_over_max(MAX, MAX, Ok(1))]
+    #[case::zero_denominator(1, 0, Err(JoltError::DivisionByZero))]
+    fn test_div_rounding_up(
+        #[case] numerator: u128,
+        #[case] denominator: u128,
+        #[case] expected: Result<u128, JoltError>,
+    ) {
+        assert_eq!(div_rounding_up(numerator, denominator), expected);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_matches_native_when_product_fits(
+            a in any::<u64>(),
+            b in any::<u64>(),
+            denominator in 1..=u128::MAX,
+        ) {
+            let product = a as u128 * b as u128;
+            prop_assert_eq!(mul_div_floor(a as u128, b as u128, denominator), Ok(product / denominator));
+            prop_assert_eq!(
+                mul_div_ceil(a as u128, b as u128, denominator),
+                Ok(product.div_ceil(denominator))
+            );
+        }
+
+        #[test]
+        fn prop_quotient_brackets_product(
+            a in any::<u128>(),
+            b in any::<u128>(),
+            denominator in 1..=u128::MAX,
+        ) {
+            let product = U256::mul(a, b);
+            let Ok(floor) = mul_div_floor(a, b, denominator) else {
+                // Only a quotient above u128 fails
+                prop_assert!(product.div_rem(denominator).0.to_u128().is_err());
+                return Ok(());
+            };
+            // floor * denominator <= a * b < (floor + 1) * denominator
+            let low = U256::mul(floor, denominator);
+            prop_assert!(low <= product);
+            prop_assert!(product.wrapping_sub(low) < U256::from_u128(denominator));
+            let exact = low == product;
+            match mul_div_ceil(a, b, denominator) {
+                Ok(ceil) => prop_assert_eq!(ceil, if exact { floor } else { floor + 1 }),
+                Err(error) => {
+                    prop_assert_eq!(error, JoltError::Overflow);
+                    prop_assert!(floor == MAX && !exact);
+                }
+            }
+        }
+    }
+}
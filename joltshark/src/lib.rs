@@ -1,12 +1,40 @@
 #![no_std]
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
-use num_traits::{Euclid, One, Signed, Zero, float::Float};
+use num_traits::{Euclid, FloatConst, One, Signed, Zero, float::Float};
+
+pub use error::JoltError;
+pub use pool::{PoolState, Position};
+pub use units::{
+    Liquidity, SqrtPriceX64, Tick, TickRounding, TickSpacing, TokenAmount, TokenOrder,
+};
+
+pub mod analytics;
+pub mod cost;
+pub mod decision;
+pub mod energy;
+pub mod error;
+pub mod establishment;
+pub mod estimator;
+pub mod evaluator;
+pub mod fee_growth;
+pub mod filter;
+pub mod full_math;
+pub mod liquidity_math;
+pub mod orbit;
+pub mod pool;
+pub mod rebalance;
+pub mod swap;
+pub mod three_range;
+pub mod tick_math;
+pub mod units;
+pub mod withdrawal;
 
 /// Trait for types that support trigonometric functions.
 /// `Float` already implements this for f32/f64.
 pub trait FloatMath: Copy {
     fn cos(self) -> Self;
     fn frac_pi_2() -> Self;
+    fn is_finite(self) -> bool;
     fn max(self, other: Self) -> Self;
     fn min(self, other: Self) -> Self;
     fn pi() -> Self;
@@ -16,12 +44,15 @@ pub trait FloatMath: Copy {
 }
 
 // Blanket implementation for all Floats
-impl<T: Float + num_traits::FromPrimitive> FloatMath for T {
+impl<T: Float + FloatConst> FloatMath for T {
     fn cos(self) -> Self {
         Float::cos(self)
     }
     fn frac_pi_2() -> Self {
-        T::from_f64(core::f64::consts::FRAC_PI_2).unwrap()
+        T::FRAC_PI_2()
+    }
+    fn is_finite(self) -> bool {
+        Float::is_finite(self)
     }
     fn max(self, other: Self) -> Self {
         Float::max(self, other)
@@ -30,7 +61,7 @@ impl<T: Float + num_traits::FromPrimitive> FloatMath for T {
         Float::min(self, other)
     }
     fn pi() -> Self {
-        T::from_f64(core::f64::consts::PI).unwrap()
+        T::PI()
     }
     fn sin(self) -> Self {
         Float::sin(self)
@@ -39,7 +70,7 @@ impl<T: Float + num_traits::FromPrimitive> FloatMath for T {
         Float::sin_cos(self)
     }
     fn tau() -> Self {
-        T::from_f64(core::f64::consts::TAU).unwrap()
+        T::TAU()
     }
 }
 
@@ -85,6 +116,7 @@ impl<T> Scalar for T where
 
 /// State vector for tracking position and derivatives.
 /// Used for price momentum and jolt-based signal detection.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct StateVector<T: Scalar, const D: usize>(pub [T; D]);
 
 impl<T: Scalar, const D: usize> StateVector<T, D> {
@@ -139,6 +171,10 @@ impl<T: Scalar, const D: usize> Sub for StateVector<T, D> {
 /// within specific price ranges defined by ticks. This replaces the traditional
 /// grid trading approach with DeFi-native tick-based positioning.
 ///
+/// Superseded by [`PoolState`] and [`Position`], which keep pool-wide state
+/// apart from each position's range; [`Self::pool`] and [`Self::position`]
+/// split a config into the two.
+///
 /// ## Tick Math
 ///
 /// Price at tick i: `price = 1.0001^i`
@@ -147,140 +183,262 @@ impl<T: Scalar, const D: usize> Sub for StateVector<T, D> {
 /// ## Example
 ///
 /// ```
+/// # #![allow(deprecated)]
 /// use joltshark::CLMMConfig;
-/// let config: CLMMConfig<f64> = CLMMConfig {
-///     tick_lower: -1000,  // Lower bound tick
-///     tick_upper: 1000,   // Upper bound tick
-///     tick_spacing: 10,   // Minimum tick increment
-///     current_tick: 0,    // Current price tick
-///     base_price: 1.0,    // Base price at tick 0
-/// };
+/// let config: CLMMConfig<f64> = CLMMConfig::new(
+///     -1000, // Lower bound tick
+///     1000,  // Upper bound tick
+///     10,    // Minimum tick increment
+///     0,     // Current price tick
+///     1.0,   // Base price at tick 0
+/// )
+/// .unwrap();
+/// assert!(config.is_in_range());
 /// ```
+#[deprecated(note = "use `PoolState` and `Position`")]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct CLMMConfig<T: Scalar> {
     /// Lower tick boundary of the liquidity range
-    pub tick_lower: i32,
+    pub tick_lower: Tick,
     /// Upper tick boundary of the liquidity range
-    pub tick_upper: i32,
+    pub tick_upper: Tick,
     /// Tick spacing (determines valid tick values)
-    pub tick_spacing: i32,
+    pub tick_spacing: TickSpacing,
     /// Current price tick
-    pub current_tick: i32,
+    pub current_tick: Tick,
     /// Base price for tick 0 (optional reference)
     pub base_price: T,
 }
 
+#[allow(deprecated)]
 impl<T: Scalar> CLMMConfig<T> {
+    /// Builds a config from raw ticks, checking it with [`Self::validate`].
+    pub fn new(
+        tick_lower: i32,
+        tick_upper: i32,
+        tick_spacing: i32,
+        current_tick: i32,
+        base_price: T,
+    ) -> Result<Self, JoltError> {
+        let config = CLMMConfig {
+            tick_lower: Tick::new(tick_lower)?,
+            tick_upper: Tick::new(tick_upper)?,
+            tick_spacing: TickSpacing::new(tick_spacing)?,
+            current_tick: Tick::new(current_tick)?,
+            base_price,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Pool at the current tick, with no liquidity and no fee.
+    pub fn pool(&self) -> PoolState {
+        PoolState {
+            sqrt_price: self.current_tick.sqrt_price(),
+            tick: self.current_tick,
+            liquidity: Liquidity::ZERO,
+            fee_rate: 0,
+            tick_spacing: self.tick_spacing,
+        }
+    }
+
+    /// Position over the config's range, with no liquidity.
+    pub fn position(&self) -> Position {
+        Position {
+            tick_lower: self.tick_lower,
+            tick_upper: self.tick_upper,
+            liquidity: Liquidity::ZERO,
+            fee_growth_inside_last: fee_growth::FeeGrowth::default(),
+        }
+    }
+
     /// Returns true if the current tick is within the liquidity range.
     pub fn is_in_range(&self) -> bool {
-        self.current_tick >= self.tick_lower && self.current_tick < self.tick_upper
+        self.position().is_in_range(&self.pool())
     }
 
-    /// Rounds a tick to the nearest valid tick based on tick_spacing.
-    pub fn round_tick(&self, tick: i32) -> i32 {
-        let spacing = self.tick_spacing;
-        ((tick as f64 / spacing as f64).round() as i32) * spacing
+    /// Checks the range order and alignment, and the base price.
+    pub fn validate(&self) -> Result<(), JoltError> {
+        self.position().validate(&self.pool())?;
+        check_base_price(self.base_price)
+    }
+
+    /// Rounds a tick to the nearest multiple of tick_spacing, ties upward,
+    /// clamped to the aligned tick bounds.
+    pub fn round_tick(&self, tick: i32) -> Tick {
+        self.pool().tick_spacing.align(tick, TickRounding::Nearest)
     }
 }
 
+#[allow(deprecated)]
 impl<T: ScalarExt> CLMMConfig<T> {
-    /// Returns the tick at a given price.
-    ///
-    /// Uses the formula: tick = log(price / base_price) / log(1.0001)
-    pub fn tick_at_price(&self, price: T) -> i32 {
-        // log(price / base_price) / log(1.0001)
-        let ratio = price / self.base_price;
-        let log_ratio = ratio.ln();
-        let log_base = T::from_f64(Float::ln(1.0001_f64)).unwrap();
-        let tick = log_ratio / log_base;
-        tick.to_i32().unwrap_or(0)
-    }
-
-    /// Returns the price at a given tick.
-    ///
-    /// Uses the formula: price = base_price * 1.0001^tick
-    pub fn price_at_tick(&self, tick: i32) -> T {
-        let base = T::from_f64(1.0001).unwrap();
-        let tick_scalar = T::from_i32(tick).unwrap();
-        self.base_price * base.powf(tick_scalar)
+    /// Returns the tick at a given price; see [`tick_at_price`].
+    pub fn tick_at_price(&self, price: T) -> Result<Tick, JoltError> {
+        tick_at_price(self.base_price, price)
+    }
+
+    /// Returns the price at a given tick; see [`price_at_tick`].
+    pub fn price_at_tick(&self, tick: Tick) -> Result<T, JoltError> {
+        price_at_tick(self.base_price, tick)
     }
 
     /// Returns the position within the range as a normalized value [0, 1].
-    pub fn range_position(&self) -> T {
-        if !self.is_in_range() {
-            if self.current_tick < self.tick_lower {
-                return T::zero();
-            } else {
-                return T::one();
-            }
-        }
-        let current = T::from_i32(self.current_tick).unwrap();
-        let lower = T::from_i32(self.tick_lower).unwrap();
-        let upper = T::from_i32(self.tick_upper).unwrap();
-        (current - lower) / (upper - lower)
+    pub fn range_position(&self) -> Result<T, JoltError> {
+        self.position().range_position(&self.pool())
+    }
+}
+
+fn check_base_price<T: Scalar>(base_price: T) -> Result<(), JoltError> {
+    if !base_price.is_finite() || base_price <= T::zero() {
+        return Err(JoltError::InvalidBasePrice);
+    }
+    Ok(())
+}
+
+/// Rejects prices that are not finite or not positive.
+pub fn check_price<T: Scalar>(price: T) -> Result<T, JoltError> {
+    if !price.is_finite() {
+        return Err(JoltError::NonFinite);
+    }
+    if price <= T::zero() {
+        return Err(JoltError::NonPositivePrice);
     }
+    Ok(price)
+}
+
+/// Returns the tick at `price`, truncated toward zero, where tick 0 is at
+/// `base_price`.
+///
+/// Uses the formula: tick = log(price / base_price) / log(1.0001)
+pub fn tick_at_price<T: ScalarExt>(base_price: T, price: T) -> Result<Tick, JoltError> {
+    check_base_price(base_price)?;
+    check_price(price)?;
+    // log(price / base_price) / log(1.0001)
+    let ratio = price / base_price;
+    let log_base = T::from_f64(Float::ln(1.0001_f64)).ok_or(JoltError::Conversion)?;
+    let tick = ratio.ln() / log_base;
+    if !tick.is_finite() {
+        return Err(JoltError::NonFinite);
+    }
+    let saturated = if tick < T::zero() { i32::MIN } else { i32::MAX };
+    Tick::new(tick.to_i32().ok_or(JoltError::TickOutOfBounds(saturated))?)
+}
+
+/// Returns the price at `tick`, where tick 0 is at `base_price`.
+///
+/// Uses the formula: price = base_price * 1.0001^tick
+pub fn price_at_tick<T: ScalarExt>(base_price: T, tick: Tick) -> Result<T, JoltError> {
+    check_base_price(base_price)?;
+    let base = T::from_f64(1.0001).ok_or(JoltError::Conversion)?;
+    let tick_scalar = T::from_i32(tick.get()).ok_or(JoltError::Conversion)?;
+    let price = base_price * base.powf(tick_scalar);
+    if !price.is_finite() {
+        return Err(JoltError::NonFinite);
+    }
+    Ok(price)
+}
+
+/// Position of `tick` within `[tick_lower, tick_upper)`, clamped to [0, 1].
+pub(crate) fn range_position<T: ScalarExt>(
+    tick: Tick,
+    tick_lower: Tick,
+    tick_upper: Tick,
+) -> Result<T, JoltError> {
+    if tick_lower >= tick_upper {
+        return Err(JoltError::RangeInverted {
+            tick_lower: tick_lower.get(),
+            tick_upper: tick_upper.get(),
+        });
+    }
+    if tick < tick_lower {
+        return Ok(T::zero());
+    }
+    if tick_upper <= tick {
+        return Ok(T::one());
+    }
+    // Ticks are within bounds, so neither difference overflows
+    let offset = T::from_i32(tick.get() - tick_lower.get()).ok_or(JoltError::Conversion)?;
+    let width = T::from_i32(tick_upper.get() - tick_lower.get()).ok_or(JoltError::Conversion)?;
+    Ok(offset / width)
 }
 
 /// Commands for CLMM position management.
 ///
 /// These commands represent actions that can be taken on a concentrated
 /// liquidity position based on market conditions and signal analysis.
-pub enum CLMMCommand<T: Scalar> {
+/// `AddLiquidity` and `RemoveLiquidity` amounts are on-chain [`Liquidity`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CLMMCommand {
     /// Add liquidity at the specified tick range
     AddLiquidity {
-        tick_lower: i32,
-        tick_upper: i32,
-        amount: T,
+        tick_lower: Tick,
+        tick_upper: Tick,
+        amount: Liquidity,
     },
     /// Remove liquidity from the current position
-    RemoveLiquidity { amount: T },
+    RemoveLiquidity { amount: Liquidity },
     /// Rebalance position to a new tick range
     Rebalance {
-        new_tick_lower: i32,
-        new_tick_upper: i32,
+        new_tick_lower: Tick,
+        new_tick_upper: Tick,
     },
     /// Collect accumulated fees
     CollectFees,
     /// Hold current position (no action)
     Hold,
     /// Wait for better conditions
-    Wait,
+    Wait(WaitReason),
     /// Exit position entirely (emergency)
     Exit,
 }
 
+/// Why a [`CLMMCommand::Wait`] was issued instead of a rebalance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaitReason {
+    /// Expected fee gain does not cover the transaction cost
+    TransactionCost,
+    /// Price is outside the range but within the dead-band
+    DeadBand,
+    /// Too little time has passed since the last rebalance
+    MinInterval,
+    /// The rebalance budget for the current window is spent
+    WindowLimit,
+}
+
 /// Evaluates the current state and returns a CLMM command.
 ///
 /// Uses jolt (4th derivative of price) as a volatility indicator.
 /// High jolt values suggest rapid momentum changes that may require
 /// position adjustment or exit.
+///
+/// Fails when the pool or position is invalid or the state or limit is not
+/// finite.
 pub fn evaluate_clmm_position<T: Scalar, const D: usize>(
     state: &StateVector<T, D>,
-    config: &CLMMConfig<T>,
+    pool: &PoolState,
+    position: &Position,
     jolt_limit: T,
-) -> CLMMCommand<T> {
-    // Check for extreme volatility via jolt
-    if let Some(j) = state.jolt() {
-        if jolt_limit < j.abs() {
-            return CLMMCommand::Exit;
-        }
-    }
-
-    // Check if price is in range
-    if !config.is_in_range() {
-        // Price has moved out of range, consider rebalancing
-        let new_center = config.current_tick;
-        let half_range = (config.tick_upper - config.tick_lower) / 2;
-        let new_lower = config.round_tick(new_center - half_range);
-        let new_upper = config.round_tick(new_center + half_range);
-
-        return CLMMCommand::Rebalance {
-            new_tick_lower: new_lower,
-            new_tick_upper: new_upper,
-        };
-    }
+) -> Result<CLMMCommand, JoltError> {
+    Ok(decision::decide_clmm_position(state, pool, position, jolt_limit)?.command)
+}
 
-    // Position is in range, hold
-    CLMMCommand::Hold
+/// Evaluates like [`evaluate_clmm_position`], waiting instead of rebalancing
+/// when `expected_fee_gain` does not cover the rebalance `cost`.
+///
+/// Both amounts are in the same units; see [`cost::CostModel::value`].
+pub fn evaluate_clmm_position_with_cost<T: Scalar, const D: usize>(
+    state: &StateVector<T, D>,
+    pool: &PoolState,
+    position: &Position,
+    jolt_limit: T,
+    expected_fee_gain: T,
+    cost: T,
+) -> Result<CLMMCommand, JoltError> {
+    Ok(cost::check_rebalance(
+        evaluate_clmm_position(state, pool, position, jolt_limit)?,
+        expected_fee_gain,
+        cost,
+    ))
 }
 
 // =============================================================================
@@ -463,59 +621,131 @@ impl<T: Scalar + num_traits::FromPrimitive + num_traits::ToPrimitive + Float> Sc
 // =============================================================================
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
     use rstest::*;
 
     #[test]
     fn test_clmm_config_in_range() {
-        let config = CLMMConfig {
-            tick_lower: -1000,
-            tick_upper: 1000,
-            tick_spacing: 10,
-            current_tick: 0,
-            base_price: 1.0_f64,
-        };
+        let config = CLMMConfig::new(-1000, 1000, 10, 0, 1.0_f64).unwrap();
         assert!(config.is_in_range());
     }
 
     #[test]
     fn test_clmm_config_out_of_range() {
-        let config = CLMMConfig {
-            tick_lower: -1000,
-            tick_upper: 1000,
-            tick_spacing: 10,
-            current_tick: 1500,
-            base_price: 1.0_f64,
-        };
+        let config = CLMMConfig::new(-1000, 1000, 10, 1500, 1.0_f64).unwrap();
         assert!(!config.is_in_range());
     }
 
+    #[test]
+    fn test_clmm_config_adapter() {
+        let config = CLMMConfig::new(-1000, 1000, 10, 1500, 1.0_f64).unwrap();
+        let pool = config.pool();
+        assert_eq!(pool.tick, config.current_tick);
+        assert_eq!(pool.sqrt_price.to_tick(), Ok(config.current_tick));
+        assert_eq!(pool.tick_spacing, config.tick_spacing);
+        let position = config.position();
+        assert_eq!(
+            (position.tick_lower, position.tick_upper),
+            (config.tick_lower, config.tick_upper)
+        );
+        assert_eq!(position.is_in_range(&pool), config.is_in_range());
+    }
+
     #[test]
     fn test_clmm_range_position() {
-        let config = CLMMConfig {
-            tick_lower: 0,
-            tick_upper: 100,
-            tick_spacing: 10,
-            current_tick: 50,
-            base_price: 1.0_f64,
-        };
-        let position = config.range_position();
+        let config = CLMMConfig::new(0, 100, 10, 50, 1.0_f64).unwrap();
+        let position = config.range_position().unwrap();
         assert!((position - 0.5).abs() < 0.001);
     }
 
     #[test]
     fn test_clmm_round_tick() {
-        let config = CLMMConfig {
-            tick_lower: 0,
-            tick_upper: 100,
-            tick_spacing: 10,
-            current_tick: 0,
-            base_price: 1.0_f64,
-        };
-        assert_eq!(config.round_tick(15), 20);
-        assert_eq!(config.round_tick(14), 10);
-        assert_eq!(config.round_tick(25), 30);
+        let config = CLMMConfig::new(0, 100, 10, 0, 1.0_f64).unwrap();
+        assert_eq!(config.round_tick(15).get(), 20);
+        assert_eq!(config.round_tick(14).get(), 10);
+        assert_eq!(config.round_tick(25).get(), 30);
+        assert_eq!(config.round_tick(-15).get(), -10);
+        assert_eq!(config.round_tick(-16).get(), -20);
+        assert_eq!(config.round_tick(i32::MAX).get(), 443_630);
+    }
+
+    #[rstest]
+    #[case::nan(1.0, f64::NAN, JoltError::NonFinite)]
+    #[case::infinite(1.0, f64::INFINITY, JoltError::NonFinite)]
+    #[case::zero(1.0, 0.0, JoltError::NonPositivePrice)]
+    #[case::negative(1.0, -1.0, JoltError::NonPositivePrice)]
+    #[case::too_high(1.0, 1e300, JoltError::TickOutOfBounds(6_908_100))]
+    #[case::zero_base(0.0, 1.0, JoltError::InvalidBasePrice)]
+    fn test_tick_at_price_errors(
+        #[case] base_price: f64,
+        #[case] price: f64,
+        #[case] expected: JoltError,
+    ) {
+        assert_eq!(tick_at_price(base_price, price), Err(expected));
+    }
+
+    #[rstest]
+    #[case::valid([-100, 100, 10, 0], 1.0, None)]
+    #[case::zero_spacing([-100, 100, 0, 0], 1.0, Some(JoltError::InvalidSpacing(0)))]
+    #[case::inverted(
+        [100, -100, 10, 0],
+        1.0,
+        Some(JoltError::RangeInverted { tick_lower: 100, tick_upper: -100 })
+    )]
+    #[case::misaligned(
+        [-105, 100, 10, 0],
+        1.0,
+        Some(JoltError::TickMisaligned { tick: -105, spacing: 10 })
+    )]
+    #[case::out_of_bounds(
+        [-100, tick_math::MAX_TICK + 1, 10, 0],
+        1.0,
+        Some(JoltError::TickOutOfBounds(tick_math::MAX_TICK + 1))
+    )]
+    #[case::nan_base([-100, 100, 10, 0], f64::NAN, Some(JoltError::InvalidBasePrice))]
+    fn test_config_new(
+        #[case] ticks: [i32; 4],
+        #[case] base_price: f64,
+        #[case] expected: Option<JoltError>,
+    ) {
+        let [tick_lower, tick_upper, tick_spacing, current_tick] = ticks;
+        let config = CLMMConfig::new(
+            tick_lower,
+            tick_upper,
+            tick_spacing,
+            current_tick,
+            base_price,
+        );
+        assert_eq!(config.err(), expected);
+    }
+
+    #[test]
+    fn test_price_at_tick_bounds() {
+        assert!(price_at_tick(1.0, Tick::MIN).unwrap() > 0.0);
+        assert!(price_at_tick(1.0_f64, Tick::MAX).unwrap().is_finite());
+        assert_eq!(price_at_tick(1e300, Tick::MAX), Err(JoltError::NonFinite));
+        assert_eq!(
+            price_at_tick(0.0, Tick::ZERO),
+            Err(JoltError::InvalidBasePrice)
+        );
+    }
+
+    #[rstest]
+    #[case::gain_covers_cost(2.0, 1.0, true)]
+    #[case::cost_exceeds_gain(1.0, 2.0, false)]
+    fn test_evaluate_with_cost(#[case] gain: f64, #[case] cost: f64, #[case] rebalances: bool) {
+        let config = CLMMConfig::new(-1000, 1000, 10, 1500, 1.0_f64).unwrap();
+        let (pool, position) = (config.pool(), config.position());
+        let state = StateVector([0.0_f64; 4]);
+        let command =
+            evaluate_clmm_position_with_cost(&state, &pool, &position, 1.0, gain, cost).unwrap();
+        assert_eq!(matches!(command, CLMMCommand::Rebalance { .. }), rebalances);
+        assert_eq!(
+            command == CLMMCommand::Wait(WaitReason::TransactionCost),
+            !rebalances
+        );
     }
 
     #[rstest]
@@ -539,8 +769,8 @@ mod tests {
         let phase = range_map(phase, 0.0, 24.0, 0.0, tau);
         let event_start = range_map(event_start, 0.0, 24.0, 0.0, tau);
         let event_end = range_map(event_end, 0.0, 24.0, 0.0, tau);
-        let blend_outer = core::f64::EPSILON;
-        let blend_inner = core::f64::EPSILON;
+        let blend_outer = f64::EPSILON;
+        let blend_inner = f64::EPSILON;
         let (x, y) = event_pulse(phase, event_start, event_end, blend_outer, blend_inner);
         assert!(
             (expected_x - x).abs() < acceptable_error,
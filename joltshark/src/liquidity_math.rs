@@ -0,0 +1,302 @@
+//! Conversions between liquidity and token amounts for a tick range.
+//!
+//! Square root prices are [`SqrtPriceX64`] values as produced by
+//! [`crate::tick_math`] and liquidity is [`Liquidity`] like the Raydium
+//! position state. Token amounts are `u64` token0 and token1 balances like
+//! SPL token accounts. Rounding follows the program: liquidity minted for a
+//! deposit rounds down, token amounts owed by the position round down, and
+//! token amounts required from the user round up.
+
+use crate::full_math::{U256, mul_div_ceil, mul_div_floor};
+use crate::tick_math::{Q64, RESOLUTION};
+use crate::{JoltError, Liquidity, SqrtPriceX64};
+
+/// Returns the bounds in ascending order, rejecting an empty range.
+fn ordered(
+    sqrt_price_a: SqrtPriceX64,
+    sqrt_price_b: SqrtPriceX64,
+) -> Result<(SqrtPriceX64, SqrtPriceX64), JoltError> {
+    match sqrt_price_a.cmp(&sqrt_price_b) {
+        core::cmp::Ordering::Less => Ok((sqrt_price_a, sqrt_price_b)),
+        core::cmp::Ordering::Greater => Ok((sqrt_price_b, sqrt_price_a)),
+        core::cmp::Ordering::Equal => Err(JoltError::EmptyRange),
+    }
+}
+
+/// Liquidity provided by `amount_0` of token0 between two square root prices.
+///
+/// `L = amount_0 * (sqrt_a * sqrt_b) / (sqrt_b - sqrt_a)`, rounded down.
+pub fn liquidity_from_amount_0(
+    sqrt_price_a: SqrtPriceX64,
+    sqrt_price_b: SqrtPriceX64,
+    amount_0: u64,
+) -> Result<Liquidity, JoltError> {
+    let (a, b) = ordered(sqrt_price_a, sqrt_price_b)?;
+    let (a, b) = (a.get(), b.get());
+    let intermediate = mul_div_floor(a, b, Q64)?;
+    Ok(Liquidity::new(mul_div_floor(
+        amount_0 as u128,
+        intermediate,
+        b - a,
+    )?))
+}
+
+/// Liquidity provided by `amount_1` of token1 between two square root prices.
+///
+/// `L = amount_1 / (sqrt_b - sqrt_a)`, rounded down.
+pub fn liquidity_from_amount_1(
+    sqrt_price_a: SqrtPriceX64,
+    sqrt_price_b: SqrtPriceX64,
+    amount_1: u64,
+) -> Result<Liquidity, JoltError> {
+    let (a, b) = ordered(sqrt_price_a, sqrt_price_b)?;
+    Ok(Liquidity::new(mul_div_floor(
+        amount_1 as u128,
+        Q64,
+        b.get() - a.get(),
+    )?))
+}
+
+/// Maximum liquidity the given token amounts can provide at the current price.
+///
+/// Below the range only token0 counts, above it only token1, and inside it
+/// the scarcer of the two sides limits the liquidity.
+pub fn liquidity_from_amounts(
+    sqrt_price: SqrtPriceX64,
+    sqrt_price_a: SqrtPriceX64,
+    sqrt_price_b: SqrtPriceX64,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<Liquidity, JoltError> {
+    let (a, b) = ordered(sqrt_price_a, sqrt_price_b)?;
+    if sqrt_price <= a {
+        liquidity_from_amount_0(a, b, amount_0)
+    } else if sqrt_price < b {
+        let liquidity_0 = liquidity_from_amount_0(sqrt_price, b, amount_0)?;
+        let liquidity_1 = liquidity_from_amount_1(a, sqrt_price, amount_1)?;
+        Ok(liquidity_0.min(liquidity_1))
+    } else {
+        liquidity_from_amount_1(a, b, amount_1)
+    }
+}
+
+/// Amount of token0 backing `liquidity` between two square root prices.
+///
+/// `amount_0 = L * (sqrt_b - sqrt_a) / (sqrt_a * sqrt_b)`.
+pub fn amount_0_from_liquidity(
+    sqrt_price_a: SqrtPriceX64,
+    sqrt_price_b: SqrtPriceX64,
+    liquidity: Liquidity,
+    round_up: bool,
+) -> Result<u64, JoltError> {
+    let (a, b) = ordered(sqrt_price_a, sqrt_price_b)?;
+    let amount = delta_amount_0(a.get(), b.get(), liquidity.get(), round_up)?;
+    u64::try_from(amount).map_err(|_| JoltError::Overflow)
+}
+
+/// Amount of token1 backing `liquidity` between two square root prices.
+///
+/// `amount_1 = L * (sqrt_b - sqrt_a)`.
+pub fn amount_1_from_liquidity(
+    sqrt_price_a: SqrtPriceX64,
+    sqrt_price_b: SqrtPriceX64,
+    liquidity: Liquidity,
+    round_up: bool,
+) -> Result<u64, JoltError> {
+    let (a, b) = ordered(sqrt_price_a, sqrt_price_b)?;
+    let amount = delta_amount_1(a.get(), b.get(), liquidity.get(), round_up)?;
+    u64::try_from(amount).map_err(|_| JoltError::Overflow)
+}
+
+/// Token0 delta between ordered bounds `a < b` as a u128.
+pub(crate) fn delta_amount_0(
+    a: u128,
+    b: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<u128, JoltError> {
+    // (L << 64) * (b - a) / b / a, rounding each division as the program does
+    let numerator = U256::from_u128(liquidity).shl(RESOLUTION)?;
+    let amount = if round_up {
+        numerator
+            .mul_div_ceil(b - a, b)?
+            .div_rounding_up(U256::from_u128(a))?
+    } else {
+        numerator.mul_div_floor(b - a, b)?.div_rem(a).0
+    };
+    amount.to_u128()
+}
+
+/// Token1 delta between ordered bounds `a < b` as a u128.
+pub(crate) fn delta_amount_1(
+    a: u128,
+    b: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<u128, JoltError> {
+    let amount = if round_up {
+        mul_div_ceil(liquidity, b - a, Q64)?
+    } else {
+        mul_div_floor(liquidity, b - a, Q64)?
+    };
+    Ok(amount)
+}
+
+/// Token amounts `(amount_0, amount_1)` backing `liquidity` at the current price.
+///
+/// Pass `round_up = true` when computing what a deposit requires and
+/// `round_up = false` when computing what a withdrawal returns.
+pub fn amounts_from_liquidity(
+    sqrt_price: SqrtPriceX64,
+    sqrt_price_a: SqrtPriceX64,
+    sqrt_price_b: SqrtPriceX64,
+    liquidity: Liquidity,
+    round_up: bool,
+) -> Result<(u64, u64), JoltError> {
+    let (a, b) = ordered(sqrt_price_a, sqrt_price_b)?;
+    if sqrt_price <= a {
+        Ok((amount_0_from_liquidity(a, b, liquidity, round_up)?, 0))
+    } else if sqrt_price < b {
+        Ok((
+            amount_0_from_liquidity(sqrt_price, b, liquidity, round_up)?,
+            amount_1_from_liquidity(a, sqrt_price, liquidity, round_up)?,
+        ))
+    } else {
+        Ok((0, amount_1_from_liquidity(a, b, liquidity, round_up)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tick;
+    use crate::tick_math::MIN_SQRT_PRICE_X64;
+    use rstest::*;
+
+    fn sqrt_price(tick: i32) -> SqrtPriceX64 {
+        SqrtPriceX64::from_tick(Tick::new(tick).unwrap())
+    }
+
+    #[rstest]
+    #[case::in_range(0, 200_510_416, 999_999, 999_999)]
+    #[case::below_range(-200, 100_004_583, 999_999, 0)]
+    #[case::above_range(200, 100_004_583, 0, 999_999)]
+    fn test_liquidity_from_amounts(
+        #[case] tick: i32,
+        #[case] expected_liquidity: u128,
+        #[case] expected_amount_0: u64,
+        #[case] expected_amount_1: u64,
+    ) {
+        let (current, lower, upper) = (sqrt_price(tick), sqrt_price(-100), sqrt_price(100));
+        let liquidity =
+            liquidity_from_amounts(current, lower, upper, 1_000_000, 1_000_000).unwrap();
+        assert_eq!(liquidity, Liquidity::new(expected_liquidity));
+        let amounts = amounts_from_liquidity(current, lower, upper, liquidity, false).unwrap();
+        assert_eq!(amounts, (expected_amount_0, expected_amount_1));
+    }
+
+    #[test]
+    fn test_bounds_order_does_not_matter() {
+        let (a, b) = (sqrt_price(-500), sqrt_price(700));
+        assert_eq!(
+            liquidity_from_amount_0(a, b, 12345),
+            liquidity_from_amount_0(b, a, 12345)
+        );
+        assert_eq!(
+            amount_1_from_liquidity(a, b, Liquidity::new(1 << 70), true),
+            amount_1_from_liquidity(b, a, Liquidity::new(1 << 70), true)
+        );
+    }
+
+    #[test]
+    fn test_empty_range() {
+        let a = sqrt_price(10);
+        assert_eq!(liquidity_from_amount_1(a, a, 1), Err(JoltError::EmptyRange));
+        assert_eq!(
+            amounts_from_liquidity(a, a, a, Liquidity::new(1), false),
+            Err(JoltError::EmptyRange)
+        );
+    }
+
+    #[test]
+    fn test_amount_overflow() {
+        const MAX_LIQUIDITY: Liquidity = Liquidity::new(u128::MAX);
+        assert_eq!(
+            amount_0_from_liquidity(SqrtPriceX64::MIN, SqrtPriceX64::MAX, MAX_LIQUIDITY, false),
+            Err(JoltError::Overflow)
+        );
+        assert_eq!(
+            amount_1_from_liquidity(SqrtPriceX64::MIN, SqrtPriceX64::MAX, MAX_LIQUIDITY, false),
+            Err(JoltError::Overflow)
+        );
+    }
+
+    #[rstest]
+    #[case::floor(false)]
+    #[case::ceil(true)]
+    fn test_delta_amount_0_wide_product(#[case] round_up: bool) {
+        // L * (b - a) = 3 * 2^191, past the 2^192 a 256-bit shift allows
+        let (a, b, liquidity) = (Q64, 4 * Q64, 1 << 127);
+        assert_eq!(delta_amount_0(a, b, liquidity, round_up), Ok(3 << 125));
+        // The u128 quotient still overflows past that
+        assert_eq!(
+            delta_amount_0(MIN_SQRT_PRICE_X64, b, u128::MAX, round_up),
+            Err(JoltError::Overflow)
+        );
+    }
+
+    #[rstest]
+    #[case::narrow(-10, 0, 10)]
+    #[case::wide(-50000, 1234, 60000)]
+    #[case::low_prices(-400000, -350000, -300000)]
+    #[case::high_prices(300000, 350000, 400000)]
+    fn test_rounding_direction(
+        #[case] tick_lower: i32,
+        #[case] tick: i32,
+        #[case] tick_upper: i32,
+    ) {
+        let (current, lower, upper) = (
+            sqrt_price(tick),
+            sqrt_price(tick_lower),
+            sqrt_price(tick_upper),
+        );
+        for liquidity in [1, 999, 123_456_789, 1 << 40, 987_654_321_987_654].map(Liquidity::new) {
+            let down = amounts_from_liquidity(current, lower, upper, liquidity, false);
+            let up = amounts_from_liquidity(current, lower, upper, liquidity, true);
+            let (Ok((down_0, down_1)), Ok((up_0, up_1))) = (down, up) else {
+                continue;
+            };
+            assert!(down_0 <= up_0 && up_0 - down_0 <= 1);
+            assert!(down_1 <= up_1 && up_1 - down_1 <= 1);
+        }
+    }
+
+    #[rstest]
+    #[case::in_range(-1000, 0, 1000)]
+    #[case::below_range(-1000, -2000, 1000)]
+    #[case::above_range(-1000, 2000, 1000)]
+    fn test_deposit_never_exceeds_amounts(
+        #[case] tick_lower: i32,
+        #[case] tick: i32,
+        #[case] tick_upper: i32,
+    ) {
+        let (current, lower, upper) = (
+            sqrt_price(tick),
+            sqrt_price(tick_lower),
+            sqrt_price(tick_upper),
+        );
+        for (amount_0, amount_1) in [
+            (1, 1),
+            (1_000, 50),
+            (10_000_000, 10_000_000),
+            (7, 9_999_999),
+        ] {
+            let liquidity =
+                liquidity_from_amounts(current, lower, upper, amount_0, amount_1).unwrap();
+            let (required_0, required_1) =
+                amounts_from_liquidity(current, lower, upper, liquidity, true).unwrap();
+            assert!(required_0 <= amount_0, "token0 {required_0} > {amount_0}");
+            assert!(required_1 <= amount_1, "token1 {required_1} > {amount_1}");
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Orbital classification of price trajectories near support and resistance.
+//!
+//! Each level is modeled as a gravitational well (a [`LevelWell`]) and the
+//! recent trajectory's total energy relative to that well decides the orbit:
+//! negative energy throughout is a bound orbit, energy that drops from
+//! non-negative to negative is a capture, and non-negative energy at the end
+//! is an escape. The strategy uses this to choose between holding and
+//! withdrawing liquidity.
+
+use crate::energy::{LevelWell, total_energy};
+use crate::{Scalar, StateVector};
+
+/// Orbit type of a trajectory around one level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orbit {
+    /// Price oscillates around the level.
+    Bound,
+    /// Price breaks away from the level.
+    Escape,
+    /// Price settles into the level as a new equilibrium.
+    Capture,
+}
+
+/// Classification of one level with a confidence in `[0, 1]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelOrbit<T: Scalar> {
+    pub level: T,
+    pub orbit: Orbit,
+    pub confidence: T,
+}
+
+/// Classifies trajectories against support and resistance levels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitClassifier<T: Scalar> {
+    /// Effective mass used for kinetic energy
+    pub mass: T,
+    /// Depth of the well at each level
+    pub depth: T,
+    /// Width of the well at each level, in price units
+    pub width: T,
+}
+
+impl<T: Scalar> OrbitClassifier<T> {
+    /// Classifies `states` (oldest first) against a single `level`.
+    ///
+    /// Returns `None` if `states` is empty or lacks velocity.
+    pub fn classify<const D: usize>(
+        &self,
+        states: &[StateVector<T, D>],
+        level: T,
+    ) -> Option<LevelOrbit<T>> {
+        let well = LevelWell {
+            level,
+            depth: self.depth,
+            width: self.width,
+        };
+        let first = total_energy(states.first()?, self.mass, &well)?;
+        let last_state = states.last()?;
+        let last = total_energy(last_state, self.mass, &well)?;
+
+        let mut bound_count = T::zero();
+        let mut count = T::zero();
+        for state in states {
+            if total_energy(state, self.mass, &well)? < T::zero() {
+                bound_count += T::one();
+            }
+            count += T::one();
+        }
+        let bound_fraction = bound_count / count;
+        let clamp = |value: T| value.max(T::zero()).min(T::one());
+
+        let (orbit, confidence) = if T::zero() <= last {
+            // Unbound: more confident when already moving away from the level
+            let offset = last_state.position()? - level;
+            let receding = T::zero() < offset * last_state.velocity()?;
+            let half = T::one() / (T::one() + T::one());
+            let direction = if receding { T::one() } else { half };
+            let margin = half + half * clamp(last / self.depth);
+            (
+                Orbit::Escape,
+                clamp((T::one() - bound_fraction) * direction * margin),
+            )
+        } else if T::zero() <= first {
+            (Orbit::Capture, clamp((first - last) / self.depth))
+        } else {
+            (Orbit::Bound, clamp(bound_fraction * (-last / self.depth)))
+        };
+        Some(LevelOrbit {
+            level,
+            orbit,
+            confidence,
+        })
+    }
+
+    /// Classifies `states` against every level in `levels`.
+    pub fn classify_levels<'a, const D: usize>(
+        &'a self,
+        states: &'a [StateVector<T, D>],
+        levels: &'a [T],
+    ) -> impl Iterator<Item = Option<LevelOrbit<T>>> + 'a {
+        levels
+            .iter()
+            .map(move |level| self.classify(states, *level))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classifier() -> OrbitClassifier<f64> {
+        OrbitClassifier {
+            mass: 1.0,
+            depth: 1.0,
+            width: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_empty_or_positional_states() {
+        let states: [StateVector<f64, 2>; 0] = [];
+        assert_eq!(classifier().classify(&states, 1.0), None);
+        assert_eq!(classifier().classify(&[StateVector([1.0])], 1.0), None);
+    }
+
+    #[test]
+    fn test_bound_orbit() {
+        // Slow oscillation deep inside the well
+        let states = [
+            StateVector([9.8, 0.2]),
+            StateVector([10.0, 0.3]),
+            StateVector([10.2, 0.0]),
+            StateVector([10.0, -0.3]),
+            StateVector([9.8, 0.0]),
+        ];
+        let result = classifier().classify(&states, 10.0).unwrap();
+        assert_eq!(result.orbit, Orbit::Bound);
+        assert!(result.confidence > 0.5);
+    }
+
+    #[test]
+    fn test_escape_orbit() {
+        let states = [
+            StateVector([10.5, 1.0]),
+            StateVector([11.5, 1.6]),
+            StateVector([13.0, 2.0]),
+        ];
+        let result = classifier().classify(&states, 10.0).unwrap();
+        assert_eq!(result.orbit, Orbit::Escape);
+        // The first state was still bound, so the breakout is not yet certain
+        assert!((result.confidence - 2.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_approaching_flyby_is_less_certain_than_receding_escape() {
+        let receding = [StateVector([12.0, 2.0])];
+        let approaching = [StateVector([8.0, 2.0])];
+        let receding = classifier().classify(&receding, 10.0).unwrap();
+        let approaching = classifier().classify(&approaching, 10.0).unwrap();
+        assert_eq!(approaching.orbit, Orbit::Escape);
+        assert!(approaching.confidence < receding.confidence);
+    }
+
+    #[test]
+    fn test_capture_orbit() {
+        // Energy bleeds away as price settles onto the level
+        let states = [
+            StateVector([13.0, -1.5]),
+            StateVector([11.0, -0.8]),
+            StateVector([10.2, -0.1]),
+        ];
+        let result = classifier().classify(&states, 10.0).unwrap();
+        assert_eq!(result.orbit, Orbit::Capture);
+        assert!(result.confidence > 0.8);
+    }
+
+    #[test]
+    fn test_classify_levels() {
+        let states = [StateVector([10.0, 0.1]), StateVector([10.01, 0.1])];
+        let levels = [10.0, 50.0];
+        let classifier = classifier();
+        let mut results = classifier.classify_levels(&states, &levels);
+        assert_eq!(results.next().unwrap().unwrap().orbit, Orbit::Bound);
+        assert_eq!(results.next().unwrap().unwrap().orbit, Orbit::Escape);
+        assert!(results.next().is_none());
+    }
+}
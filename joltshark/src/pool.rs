@@ -0,0 +1,269 @@
+//! Pool-wide and per-position CLMM state.
+//!
+//! A [`PoolState`] holds what every position in a pool shares: the square
+//! root price, the current tick, the active liquidity, the fee rate and the
+//! tick spacing. A [`Position`] holds what one position owns: its range, its
+//! liquidity and the inside fee growth at its last fee collection. Several
+//! positions evaluated against one pool share a single `PoolState`, so the
+//! pool they see cannot drift apart.
+
+use crate::fee_growth::{FeeGrowth, fees_earned};
+use crate::swap::FEE_RATE_DENOMINATOR;
+use crate::{JoltError, Liquidity, ScalarExt, SqrtPriceX64, Tick, TickSpacing};
+
+/// State shared by all positions in a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolState {
+    pub sqrt_price: SqrtPriceX64,
+    /// Current tick, the tick at or below `sqrt_price`
+    pub tick: Tick,
+    /// Liquidity active at the current tick
+    pub liquidity: Liquidity,
+    /// Trade fee in units of [`FEE_RATE_DENOMINATOR`]
+    pub fee_rate: u32,
+    pub tick_spacing: TickSpacing,
+}
+
+impl PoolState {
+    /// Pool at `sqrt_price`, deriving the current tick from it.
+    pub fn new(
+        sqrt_price: SqrtPriceX64,
+        liquidity: Liquidity,
+        fee_rate: u32,
+        tick_spacing: TickSpacing,
+    ) -> Result<Self, JoltError> {
+        let pool = PoolState {
+            sqrt_price,
+            tick: sqrt_price.to_tick()?,
+            liquidity,
+            fee_rate,
+            tick_spacing,
+        };
+        pool.validate()?;
+        Ok(pool)
+    }
+
+    /// Pool at the lower price boundary of `tick`.
+    pub fn at_tick(
+        tick: Tick,
+        liquidity: Liquidity,
+        fee_rate: u32,
+        tick_spacing: TickSpacing,
+    ) -> Result<Self, JoltError> {
+        let pool = PoolState {
+            sqrt_price: tick.sqrt_price(),
+            tick,
+            liquidity,
+            fee_rate,
+            tick_spacing,
+        };
+        pool.validate()?;
+        Ok(pool)
+    }
+
+    /// Checks the current tick is the tick at `sqrt_price` and the fee rate
+    /// is below [`FEE_RATE_DENOMINATOR`].
+    ///
+    /// The fields are public, so a pool updated field by field must be
+    /// validated again before use.
+    pub fn validate(&self) -> Result<(), JoltError> {
+        if self.sqrt_price.to_tick()? != self.tick {
+            return Err(JoltError::TickPriceMismatch {
+                tick: self.tick.get(),
+                sqrt_price: self.sqrt_price.get(),
+            });
+        }
+        if self.fee_rate >= FEE_RATE_DENOMINATOR {
+            return Err(JoltError::InvalidFeeRate(self.fee_rate));
+        }
+        Ok(())
+    }
+}
+
+/// One liquidity position in a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub tick_lower: Tick,
+    pub tick_upper: Tick,
+    pub liquidity: Liquidity,
+    /// Inside fee growth when fees were last collected
+    pub fee_growth_inside_last: FeeGrowth,
+}
+
+impl Position {
+    /// Position over `[tick_lower, tick_upper)` with no fee growth recorded.
+    pub fn new(
+        tick_lower: Tick,
+        tick_upper: Tick,
+        liquidity: Liquidity,
+    ) -> Result<Self, JoltError> {
+        if tick_lower >= tick_upper {
+            return Err(JoltError::RangeInverted {
+                tick_lower: tick_lower.get(),
+                tick_upper: tick_upper.get(),
+            });
+        }
+        Ok(Position {
+            tick_lower,
+            tick_upper,
+            liquidity,
+            fee_growth_inside_last: FeeGrowth::default(),
+        })
+    }
+
+    /// Checks the range order and that both bounds align to the pool's spacing.
+    pub fn validate(&self, pool: &PoolState) -> Result<(), JoltError> {
+        if self.tick_lower >= self.tick_upper {
+            return Err(JoltError::RangeInverted {
+                tick_lower: self.tick_lower.get(),
+                tick_upper: self.tick_upper.get(),
+            });
+        }
+        Tick::aligned(self.tick_lower.get(), pool.tick_spacing)?;
+        Tick::aligned(self.tick_upper.get(), pool.tick_spacing)?;
+        Ok(())
+    }
+
+    /// Returns true if the pool's current tick is within the range.
+    pub fn is_in_range(&self, pool: &PoolState) -> bool {
+        self.tick_lower <= pool.tick && pool.tick < self.tick_upper
+    }
+
+    /// Position of the pool's current tick within the range, clamped to [0, 1].
+    pub fn range_position<T: ScalarExt>(&self, pool: &PoolState) -> Result<T, JoltError> {
+        crate::range_position(pool.tick, self.tick_lower, self.tick_upper)
+    }
+
+    /// Fees `(token0, token1)` earned since the last collection, given the
+    /// current inside fee growth.
+    pub fn uncollected_fees(&self, fee_growth_inside: FeeGrowth) -> Result<(u64, u64), JoltError> {
+        fees_earned(
+            self.liquidity.get(),
+            self.fee_growth_inside_last,
+            fee_growth_inside,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tick_math::Q64;
+    use rstest::*;
+
+    fn tick(tick: i32) -> Tick {
+        Tick::new(tick).unwrap()
+    }
+
+    fn pool(current_tick: i32) -> PoolState {
+        PoolState::at_tick(
+            tick(current_tick),
+            Liquidity::new(1_000),
+            2_500,
+            TickSpacing::new(10).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pool_new_derives_tick() {
+        let spacing = TickSpacing::new(10).unwrap();
+        let sqrt_price = SqrtPriceX64::from_tick(tick(-305));
+        let pool = PoolState::new(sqrt_price, Liquidity::ZERO, 0, spacing).unwrap();
+        assert_eq!(pool.tick, tick(-305));
+        let above = SqrtPriceX64::new(sqrt_price.get() + 1).unwrap();
+        let pool = PoolState::new(above, Liquidity::ZERO, 0, spacing).unwrap();
+        assert_eq!(pool.tick, tick(-305));
+    }
+
+    #[test]
+    fn test_pool_rejects_fee_rate() {
+        let spacing = TickSpacing::new(10).unwrap();
+        assert_eq!(
+            PoolState::at_tick(Tick::ZERO, Liquidity::ZERO, FEE_RATE_DENOMINATOR, spacing),
+            Err(JoltError::InvalidFeeRate(FEE_RATE_DENOMINATOR))
+        );
+    }
+
+    #[test]
+    fn test_pool_rejects_drifted_tick() {
+        let drifted = PoolState {
+            tick: tick(-304),
+            ..pool(-305)
+        };
+        assert_eq!(
+            drifted.validate(),
+            Err(JoltError::TickPriceMismatch {
+                tick: -304,
+                sqrt_price: SqrtPriceX64::from_tick(tick(-305)).get(),
+            })
+        );
+        let moved = PoolState {
+            sqrt_price: SqrtPriceX64::from_tick(tick(-304)),
+            ..drifted
+        };
+        assert_eq!(moved.validate(), Ok(()));
+    }
+
+    #[rstest]
+    #[case::valid(-100, 100, None)]
+    #[case::inverted(100, 100, Some(JoltError::RangeInverted { tick_lower: 100, tick_upper: 100 }))]
+    #[case::misaligned(-100, 105, Some(JoltError::TickMisaligned { tick: 105, spacing: 10 }))]
+    fn test_position_validate(
+        #[case] tick_lower: i32,
+        #[case] tick_upper: i32,
+        #[case] expected: Option<JoltError>,
+    ) {
+        let position = Position {
+            tick_lower: tick(tick_lower),
+            tick_upper: tick(tick_upper),
+            liquidity: Liquidity::ZERO,
+            fee_growth_inside_last: FeeGrowth::default(),
+        };
+        assert_eq!(position.validate(&pool(0)).err(), expected);
+    }
+
+    #[rstest]
+    #[case::below(-101, false, 0.0)]
+    #[case::lower(-100, true, 0.0)]
+    #[case::middle(50, true, 0.75)]
+    #[case::upper(100, false, 1.0)]
+    fn test_position_in_range(
+        #[case] current_tick: i32,
+        #[case] in_range: bool,
+        #[case] range_position: f64,
+    ) {
+        let position = Position::new(tick(-100), tick(100), Liquidity::ZERO).unwrap();
+        assert_eq!(position.is_in_range(&pool(current_tick)), in_range);
+        assert_eq!(
+            position.range_position(&pool(current_tick)),
+            Ok(range_position)
+        );
+    }
+
+    #[test]
+    fn test_positions_share_pool() {
+        let pool = pool(0);
+        let narrow = Position::new(tick(-10), tick(10), Liquidity::ZERO).unwrap();
+        let wide = Position::new(tick(-100), tick(100), Liquidity::ZERO).unwrap();
+        let above = Position::new(tick(10), tick(20), Liquidity::ZERO).unwrap();
+        assert!(narrow.is_in_range(&pool) && wide.is_in_range(&pool));
+        assert!(!above.is_in_range(&pool));
+    }
+
+    #[test]
+    fn test_uncollected_fees() {
+        let position = Position {
+            fee_growth_inside_last: FeeGrowth {
+                token_0_x64: Q64,
+                token_1_x64: 0,
+            },
+            ..Position::new(tick(-100), tick(100), Liquidity::new(1_000)).unwrap()
+        };
+        let now = FeeGrowth {
+            token_0_x64: 3 * Q64,
+            token_1_x64: Q64 / 2,
+        };
+        assert_eq!(position.uncollected_fees(now), Ok((2_000, 500)));
+    }
+}
@@ -0,0 +1,824 @@
+//! Rebalancing planner for the three-range strategy.
+//!
+//! Given the range layout, the liquidity of each position and the fees
+//! waiting to be collected, the planner produces the ordered
+//! [`CLMMCommand`]s that move every range toward its target share of
+//! capital. Targets depend on which range is active, as described in the
+//! rebalancing specification.
+//!
+//! No tokens are swapped, so targets are planned per token: a range above
+//! the price can only hold token0, a range below it can only hold token1
+//! and the active range holds both in the ratio the price sets. While
+//! R_restock is active, for example, the token0 it holds is withdrawn and
+//! spread over R_fee and R_exit by weight. R_restock is the only range that
+//! can hold token1, so the token1 released with it stays idle in the wallet
+//! until a later plan can deploy it. With [`TokenOrder::MemeFirst`] token0
+//! is the meme coin; with [`TokenOrder::StableFirst`] the roles are
+//! mirrored.
+//!
+//! Fee collection comes first, then every withdrawal, then every deposit, so
+//! deposits are always funded by tokens that are already in the wallet. A
+//! plan whose expected fee gain does not cover its cost is replaced by a
+//! `Wait` with [`RebalancePlan::check_cost`].
+
+use crate::cost::{CostModel, TransactionPlan, check_collect_fees};
+use crate::liquidity_math::{amounts_from_liquidity, liquidity_from_amounts};
+use crate::three_range::{PerRange, RangeKind, ThreeRange};
+use crate::units::{Meme, Stable, TokenOrder};
+use crate::{
+    CLMMCommand, JoltError, Liquidity, PoolState, Scalar, ScalarExt, SqrtPriceX64, TokenAmount,
+    WaitReason,
+};
+
+/// Token balances of a position, its uncollected fees or the wallet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Holdings {
+    pub meme: TokenAmount<Meme>,
+    pub stable: TokenAmount<Stable>,
+}
+
+impl Holdings {
+    pub fn new(meme: TokenAmount<Meme>, stable: TokenAmount<Stable>) -> Self {
+        Holdings { meme, stable }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meme == TokenAmount::ZERO && self.stable == TokenAmount::ZERO
+    }
+
+    /// Value in stable coin units at `price` (stable per meme).
+    pub fn value<T: ScalarExt>(&self, price: T) -> Result<T, JoltError> {
+        let meme = T::from_f64(self.meme.get() as f64).ok_or(JoltError::Conversion)?;
+        let stable = T::from_f64(self.stable.get() as f64).ok_or(JoltError::Conversion)?;
+        Ok(stable + meme * price)
+    }
+}
+
+/// Target capital weights for each range, chosen by the active range.
+///
+/// Weights are relative. Each token is split between the ranges that can
+/// hold it in proportion to their weights, so a range with weight zero is
+/// emptied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocationRatios {
+    restock_active: PerRange<u32>,
+    fee_active: PerRange<u32>,
+    exit_active: PerRange<u32>,
+}
+
+impl AllocationRatios {
+    /// Returns `None` if a set of weights sums to zero.
+    pub fn new(
+        restock_active: PerRange<u32>,
+        fee_active: PerRange<u32>,
+        exit_active: PerRange<u32>,
+    ) -> Option<Self> {
+        let valid =
+            |weights: &PerRange<u32>| RangeKind::ALL.iter().any(|kind| *weights.get(*kind) > 0);
+        if valid(&restock_active) && valid(&fee_active) && valid(&exit_active) {
+            Some(AllocationRatios {
+                restock_active,
+                fee_active,
+                exit_active,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Weights to use while `active` contains the price.
+    pub fn weights(&self, active: RangeKind) -> PerRange<u32> {
+        match active {
+            RangeKind::Restock => self.restock_active,
+            RangeKind::Fee => self.fee_active,
+            RangeKind::Exit => self.exit_active,
+        }
+    }
+}
+
+/// A command together with the range whose position it applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeCommand {
+    pub range: RangeKind,
+    pub command: CLMMCommand,
+}
+
+/// Upper bound on plan length: collect, withdraw and deposit per range.
+pub const MAX_PLAN_STEPS: usize = 3 * RangeKind::ALL.len();
+
+/// Ordered commands produced by [`RebalancePlanner::plan`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RebalancePlan {
+    steps: [Option<RangeCommand>; MAX_PLAN_STEPS],
+    len: usize,
+    /// Range containing the price, if any
+    active: Option<RangeKind>,
+    /// Liquidity of each range before the plan
+    liquidity: PerRange<Liquidity>,
+    /// Tokens in the wallet before the plan
+    wallet: Holdings,
+    idle: Holdings,
+}
+
+impl RebalancePlan {
+    fn empty(active: Option<RangeKind>, liquidity: PerRange<Liquidity>, wallet: Holdings) -> Self {
+        RebalancePlan {
+            steps: [None; MAX_PLAN_STEPS],
+            len: 0,
+            active,
+            liquidity,
+            wallet,
+            idle: wallet,
+        }
+    }
+
+    fn push(&mut self, range: RangeKind, command: CLMMCommand) {
+        self.steps[self.len] = Some(RangeCommand { range, command });
+        self.len += 1;
+    }
+
+    /// Commands in execution order.
+    pub fn iter(&self) -> impl Iterator<Item = &RangeCommand> {
+        self.steps[..self.len].iter().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Released tokens left in the wallet after all deposits.
+    pub fn idle(&self) -> Holdings {
+        self.idle
+    }
+
+    /// Transactions needed to execute every command.
+    ///
+    /// A deposit into a range without liquidity opens its position and a
+    /// withdrawal of all of a range's liquidity closes it.
+    pub fn transactions(&self) -> TransactionPlan {
+        let mut liquidity = self.liquidity;
+        let mut total = TransactionPlan::default();
+        for step in self {
+            let current = liquidity.get_mut(step.range);
+            total = total + TransactionPlan::for_command(&step.command, *current);
+            *current = match step.command {
+                CLMMCommand::AddLiquidity { amount, .. } => {
+                    Liquidity::new(current.get().saturating_add(amount.get()))
+                }
+                CLMMCommand::RemoveLiquidity { amount } => {
+                    Liquidity::new(current.get().saturating_sub(amount.get()))
+                }
+                _ => *current,
+            };
+        }
+        total
+    }
+
+    /// Replaces the plan with a single `Wait` on the active range when
+    /// `expected_fee_gain` does not cover `cost`, both in stable coin.
+    ///
+    /// See [`crate::cost::check_rebalance`]. An empty plan is returned
+    /// unchanged.
+    pub fn check_cost<T: Scalar>(self, expected_fee_gain: T, cost: T) -> Self {
+        match self.active {
+            Some(active) if !self.is_empty() && expected_fee_gain - cost < T::zero() => {
+                let mut plan = RebalancePlan::empty(self.active, self.liquidity, self.wallet);
+                plan.push(active, CLMMCommand::Wait(WaitReason::TransactionCost));
+                plan
+            }
+            _ => self,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a RebalancePlan {
+    type Item = &'a RangeCommand;
+    type IntoIter = core::iter::Flatten<core::slice::Iter<'a, Option<RangeCommand>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.steps[..self.len].iter().flatten()
+    }
+}
+
+/// Pure planner that redistributes capital across the three ranges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RebalancePlanner<T: Scalar> {
+    /// Target weights per active range
+    pub ratios: AllocationRatios,
+    pub token_order: TokenOrder,
+    /// Withdrawals and deposits worth at most this, in stable coin, are
+    /// skipped to avoid churn
+    pub min_transfer: T,
+    /// Prices fee collection, which only happens when the fees cover it
+    pub cost_model: CostModel,
+    /// Stable coin per lamport
+    pub value_per_lamport: T,
+}
+
+impl<T: ScalarExt> RebalancePlanner<T> {
+    /// Plans the commands that move capital toward the target allocation.
+    ///
+    /// `liquidity` is the liquidity of each range's position, `fees` its
+    /// uncollected fees and `wallet` the tokens already available, such as
+    /// the idle tokens of an earlier plan. `price` is the pool price in
+    /// stable per meme, used to value transfers against `min_transfer` and
+    /// fees against the cost of collecting them. Fees that do not cover that
+    /// cost are left uncollected. Returns an empty plan when the price is
+    /// outside all three ranges.
+    pub fn plan(
+        &self,
+        layout: &ThreeRange,
+        pool: &PoolState,
+        liquidity: &PerRange<Liquidity>,
+        fees: &PerRange<Holdings>,
+        wallet: Holdings,
+        price: T,
+    ) -> Result<RebalancePlan, JoltError> {
+        let Some(active) = layout.active_range(pool.tick) else {
+            return Ok(RebalancePlan::empty(None, *liquidity, wallet));
+        };
+        let mut plan = RebalancePlan::empty(Some(active), *liquidity, wallet);
+        let sqrt_price = pool.sqrt_price;
+        let bounds = |kind: RangeKind| {
+            let range = layout.range(kind);
+            (
+                SqrtPriceX64::from_tick(range.tick_lower),
+                SqrtPriceX64::from_tick(range.tick_upper),
+            )
+        };
+        let amounts = |kind: RangeKind, liquidity: Liquidity, round_up: bool| {
+            let (lower, upper) = bounds(kind);
+            amounts_from_liquidity(sqrt_price, lower, upper, liquidity, round_up)
+        };
+        let value = |amount_0: u64, amount_1: u64| {
+            let (meme, stable) = self.token_order.from_pool(amount_0, amount_1);
+            Holdings::new(meme, stable).value(price)
+        };
+        // A range holds token0 unless it is below the price and token1
+        // unless it is above it
+        let holds_0 = |kind: RangeKind| sqrt_price < bounds(kind).1;
+        let holds_1 = |kind: RangeKind| bounds(kind).0 < sqrt_price;
+        let add = |total: (u64, u64), amount_0: u64, amount_1: u64| {
+            Ok::<_, JoltError>((
+                total.0.checked_add(amount_0).ok_or(JoltError::Overflow)?,
+                total.1.checked_add(amount_1).ok_or(JoltError::Overflow)?,
+            ))
+        };
+
+        let mut wallet = self.token_order.to_pool(wallet.meme, wallet.stable);
+        for kind in RangeKind::ALL {
+            let fees = fees.get(kind);
+            if fees.is_empty() {
+                continue;
+            }
+            let command =
+                check_collect_fees(fees.value(price)?, &self.cost_model, self.value_per_lamport)?;
+            if command == CLMMCommand::CollectFees {
+                plan.push(kind, command);
+                let (amount_0, amount_1) = self.token_order.to_pool(fees.meme, fees.stable);
+                wallet = add(wallet, amount_0, amount_1)?;
+            }
+        }
+        let mut total = wallet;
+        for kind in RangeKind::ALL {
+            let (amount_0, amount_1) = amounts(kind, *liquidity.get(kind), false)?;
+            total = add(total, amount_0, amount_1)?;
+        }
+
+        // Each token is split by weight between the ranges that can hold it.
+        // The range containing the price needs both tokens, so it is sized
+        // first and whatever it does not pair goes to the one-sided ranges.
+        let weights = self.ratios.weights(active);
+        let mut side_weights = (0_u64, 0_u64);
+        for kind in RangeKind::ALL {
+            let weight = *weights.get(kind) as u64;
+            side_weights.0 += if holds_0(kind) { weight } else { 0 };
+            side_weights.1 += if holds_1(kind) { weight } else { 0 };
+        }
+        let split = |amount: u64, weight: u32, side_weight: u64| {
+            if side_weight == 0 {
+                0
+            } else {
+                (amount as u128 * weight as u128 / side_weight as u128) as u64
+            }
+        };
+        let mut targets = PerRange::<Liquidity>::default();
+        let mut rest = total;
+        for kind in RangeKind::ALL
+            .into_iter()
+            .filter(|kind| holds_0(*kind) && holds_1(*kind))
+        {
+            let weight = *weights.get(kind);
+            let (lower, upper) = bounds(kind);
+            let offer_0 = split(total.0, weight, side_weights.0);
+            let offer_1 = split(total.1, weight, side_weights.1);
+            let target = liquidity_from_amounts(sqrt_price, lower, upper, offer_0, offer_1)?;
+            let (amount_0, amount_1) = amounts(kind, target, true)?;
+            rest.0 -= amount_0;
+            rest.1 -= amount_1;
+            side_weights.0 -= weight as u64;
+            side_weights.1 -= weight as u64;
+            *targets.get_mut(kind) = target;
+        }
+        for kind in RangeKind::ALL
+            .into_iter()
+            .filter(|kind| holds_0(*kind) != holds_1(*kind))
+        {
+            let weight = *weights.get(kind);
+            let (lower, upper) = bounds(kind);
+            let offer_0 = if holds_0(kind) {
+                split(rest.0, weight, side_weights.0)
+            } else {
+                0
+            };
+            let offer_1 = if holds_1(kind) {
+                split(rest.1, weight, side_weights.1)
+            } else {
+                0
+            };
+            *targets.get_mut(kind) =
+                liquidity_from_amounts(sqrt_price, lower, upper, offer_0, offer_1)?;
+        }
+
+        for kind in RangeKind::ALL {
+            let Some(excess) = liquidity.get(kind).checked_sub(*targets.get(kind)) else {
+                continue;
+            };
+            let (amount_0, amount_1) = amounts(kind, excess, false)?;
+            if excess != Liquidity::ZERO && self.min_transfer < value(amount_0, amount_1)? {
+                plan.push(kind, CLMMCommand::RemoveLiquidity { amount: excess });
+                wallet = add(wallet, amount_0, amount_1)?;
+            }
+        }
+        // Skipped withdrawals can leave deposits short; each takes what is left
+        for kind in RangeKind::ALL {
+            let Some(shortfall) = targets.get(kind).checked_sub(*liquidity.get(kind)) else {
+                continue;
+            };
+            let (lower, upper) = bounds(kind);
+            let affordable = liquidity_from_amounts(sqrt_price, lower, upper, wallet.0, wallet.1)?;
+            let amount = shortfall.min(affordable);
+            let (amount_0, amount_1) = amounts(kind, amount, true)?;
+            if amount != Liquidity::ZERO && self.min_transfer < value(amount_0, amount_1)? {
+                let range = layout.range(kind);
+                plan.push(
+                    kind,
+                    CLMMCommand::AddLiquidity {
+                        tick_lower: range.tick_lower,
+                        tick_upper: range.tick_upper,
+                        amount,
+                    },
+                );
+                wallet.0 -= amount_0;
+                wallet.1 -= amount_1;
+            }
+        }
+        let (meme, stable) = self.token_order.from_pool(wallet.0, wallet.1);
+        plan.idle = Holdings::new(meme, stable);
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Tick, TickSpacing};
+    use proptest::prelude::*;
+
+    fn layout() -> ThreeRange {
+        ThreeRange::from_bounds([-300, -100, 100, 300], 10).unwrap()
+    }
+
+    fn planner(min_transfer: f64) -> RebalancePlanner<f64> {
+        RebalancePlanner {
+            ratios: AllocationRatios::new(
+                PerRange::new(5, 3, 2),
+                PerRange::new(1, 2, 1),
+                PerRange::new(2, 3, 5),
+            )
+            .unwrap(),
+            token_order: TokenOrder::MemeFirst,
+            min_transfer,
+            cost_model: CostModel::default(),
+            value_per_lamport: 0.0,
+        }
+    }
+
+    fn pool(tick: i32) -> PoolState {
+        let spacing = TickSpacing::new(10).unwrap();
+        PoolState::at_tick(Tick::new(tick).unwrap(), Liquidity::ZERO, 2500, spacing).unwrap()
+    }
+
+    fn holdings(meme: u64, stable: u64) -> Holdings {
+        Holdings::new(TokenAmount::new(meme), TokenAmount::new(stable))
+    }
+
+    fn liquidity(restock: u128, fee: u128, exit: u128) -> PerRange<Liquidity> {
+        PerRange::new(restock, fee, exit).map(Liquidity::new)
+    }
+
+    fn ranges(plan: &RebalancePlan) -> impl Iterator<Item = (RangeKind, CLMMCommand)> + '_ {
+        plan.iter().map(|step| {
+            let command = match step.command {
+                CLMMCommand::RemoveLiquidity { .. } => CLMMCommand::RemoveLiquidity {
+                    amount: Liquidity::ZERO,
+                },
+                CLMMCommand::AddLiquidity {
+                    tick_lower,
+                    tick_upper,
+                    ..
+                } => CLMMCommand::AddLiquidity {
+                    tick_lower,
+                    tick_upper,
+                    amount: Liquidity::ZERO,
+                },
+                command => command,
+            };
+            (step.range, command)
+        })
+    }
+
+    const REMOVE: CLMMCommand = CLMMCommand::RemoveLiquidity {
+        amount: Liquidity::ZERO,
+    };
+
+    fn add(kind: RangeKind) -> CLMMCommand {
+        let range = layout().range(kind);
+        CLMMCommand::AddLiquidity {
+            tick_lower: range.tick_lower,
+            tick_upper: range.tick_upper,
+            amount: Liquidity::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_allocation_ratios_invalid() {
+        let (zero, valid) = (PerRange::new(0, 0, 0), PerRange::new(1, 1, 1));
+        assert_eq!(AllocationRatios::new(zero, valid, valid), None);
+        assert_eq!(AllocationRatios::new(valid, zero, valid), None);
+        assert_eq!(AllocationRatios::new(valid, valid, zero), None);
+        assert!(AllocationRatios::new(PerRange::new(0, 1, 0), valid, valid).is_some());
+    }
+
+    #[test]
+    fn test_plan_empty_outside_layout() {
+        let fees = PerRange::new(holdings(0, 0), holdings(5, 5), holdings(0, 0));
+        let plan = planner(0.0)
+            .plan(
+                &layout(),
+                &pool(500),
+                &liquidity(0, 1_000_000, 0),
+                &fees,
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn test_plan_fee_active_tops_off_outer_ranges() {
+        let fees = PerRange::new(holdings(0, 0), holdings(1_000, 1_000), holdings(0, 0));
+        let plan = planner(0.0)
+            .plan(
+                &layout(),
+                &pool(0),
+                &liquidity(0, 100_000_000, 0),
+                &fees,
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        let expected = [
+            (RangeKind::Fee, CLMMCommand::CollectFees),
+            (RangeKind::Fee, REMOVE),
+            (RangeKind::Restock, add(RangeKind::Restock)),
+            (RangeKind::Exit, add(RangeKind::Exit)),
+        ];
+        assert!(ranges(&plan).eq(expected));
+        assert!(plan.idle().meme <= TokenAmount::new(2));
+        assert!(plan.idle().stable <= TokenAmount::new(2));
+    }
+
+    #[test]
+    fn test_plan_restock_active_spreads_meme() {
+        // R_restock has bought meme coin; R_fee and R_exit can only take meme coin
+        let plan = planner(0.0)
+            .plan(
+                &layout(),
+                &pool(-150),
+                &liquidity(100_000_000, 0, 0),
+                &PerRange::default(),
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        let expected = [
+            (RangeKind::Restock, REMOVE),
+            (RangeKind::Fee, add(RangeKind::Fee)),
+            (RangeKind::Exit, add(RangeKind::Exit)),
+        ];
+        assert!(ranges(&plan).eq(expected));
+        // Only R_restock can hold the stable coin released with the meme coin
+        assert!(plan.idle().meme <= TokenAmount::new(2));
+        assert!(plan.idle().stable > TokenAmount::new(0));
+    }
+
+    #[test]
+    fn test_plan_restock_active_stable_first() {
+        // With the stable coin as token0, R_restock has bought stable coin
+        // and only R_restock can hold the released meme coin
+        let planner = RebalancePlanner {
+            token_order: TokenOrder::StableFirst,
+            ..planner(0.0)
+        };
+        let plan = planner
+            .plan(
+                &layout(),
+                &pool(-150),
+                &liquidity(100_000_000, 0, 0),
+                &PerRange::default(),
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        let expected = [
+            (RangeKind::Restock, REMOVE),
+            (RangeKind::Fee, add(RangeKind::Fee)),
+            (RangeKind::Exit, add(RangeKind::Exit)),
+        ];
+        assert!(ranges(&plan).eq(expected));
+        assert!(plan.idle().stable <= TokenAmount::new(2));
+        assert!(plan.idle().meme > TokenAmount::new(0));
+    }
+
+    #[test]
+    fn test_plan_balance_overflow() {
+        let half = u64::MAX / 2 + 1;
+        let fees = PerRange::new(holdings(half, 0), holdings(half, 0), holdings(0, 0));
+        let result = planner(0.0).plan(
+            &layout(),
+            &pool(0),
+            &liquidity(0, 0, 0),
+            &fees,
+            Holdings::default(),
+            1.0,
+        );
+        assert_eq!(result, Err(JoltError::Overflow));
+    }
+
+    #[test]
+    fn test_plan_leaves_fees_below_cost() {
+        // Collecting costs 5_000 lamports, worth 1_000 stable coin here
+        let planner = RebalancePlanner {
+            value_per_lamport: 0.2,
+            ..planner(0.0)
+        };
+        let fees = PerRange::new(holdings(0, 999), holdings(500, 500), holdings(0, 0));
+        let plan = planner
+            .plan(
+                &layout(),
+                &pool(0),
+                &liquidity(0, 0, 0),
+                &fees,
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        assert_eq!(
+            plan.iter().next().map(|step| step.range),
+            Some(RangeKind::Fee)
+        );
+        assert_eq!(
+            plan.iter()
+                .filter(|step| step.command == CLMMCommand::CollectFees)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_plan_transactions_and_cost() {
+        let fees = PerRange::new(holdings(0, 0), holdings(1_000, 1_000), holdings(0, 0));
+        let plan = planner(0.0)
+            .plan(
+                &layout(),
+                &pool(0),
+                &liquidity(0, 100_000_000, 0),
+                &fees,
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        // Collect and withdraw adjust R_fee; both deposits open a position
+        let transactions = plan.transactions();
+        let expected = TransactionPlan::COLLECT_FEES
+            + TransactionPlan::ADJUST_LIQUIDITY
+            + TransactionPlan::OPEN_POSITION
+            + TransactionPlan::OPEN_POSITION;
+        assert_eq!(transactions, expected);
+        assert_eq!(transactions.opened.count, 6);
+        assert_eq!(transactions.closed, Default::default());
+        assert_eq!(plan.check_cost(1.0, 0.5), plan);
+        let wait = plan.check_cost(0.5, 1.0);
+        let expected = [RangeCommand {
+            range: RangeKind::Fee,
+            command: CLMMCommand::Wait(WaitReason::TransactionCost),
+        }];
+        assert!(wait.iter().eq(expected.iter()));
+        assert_eq!(wait.idle(), Holdings::default());
+        assert_eq!(wait.transactions(), TransactionPlan::default());
+        let empty = RebalancePlan::empty(
+            Some(RangeKind::Fee),
+            PerRange::default(),
+            Holdings::default(),
+        );
+        assert!(empty.check_cost(0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_plan_transactions_close_emptied_ranges() {
+        let planner = RebalancePlanner {
+            ratios: AllocationRatios::new(
+                PerRange::new(1, 1, 1),
+                PerRange::new(0, 1, 0),
+                PerRange::new(1, 1, 1),
+            )
+            .unwrap(),
+            ..planner(0.0)
+        };
+        let plan = planner
+            .plan(
+                &layout(),
+                &pool(0),
+                &liquidity(100_000_000, 100_000_000, 100_000_000),
+                &PerRange::default(),
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        let expected = [
+            (RangeKind::Restock, REMOVE),
+            (RangeKind::Exit, REMOVE),
+            (RangeKind::Fee, add(RangeKind::Fee)),
+        ];
+        assert!(ranges(&plan).eq(expected));
+        let expected = TransactionPlan::CLOSE_POSITION
+            + TransactionPlan::CLOSE_POSITION
+            + TransactionPlan::ADJUST_LIQUIDITY;
+        assert_eq!(plan.transactions(), expected);
+        assert!(CostModel::default().net_rent(&plan.transactions()) < 0);
+    }
+
+    #[test]
+    fn test_plan_skips_small_transfers() {
+        let plan = planner(1e9)
+            .plan(
+                &layout(),
+                &pool(0),
+                &liquidity(0, 100_000_000, 0),
+                &PerRange::default(),
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn test_plan_limits_deposits_when_withdrawal_skipped() {
+        // The withdrawal is too small to make, so only the fees are deposited
+        let fees = PerRange::new(holdings(0, 0), holdings(40_000, 40_000), holdings(0, 0));
+        let current = liquidity(2_000_000_000, 4_000_000_000, 2_000_000_000);
+        let plan = planner(50_000.0)
+            .plan(
+                &layout(),
+                &pool(0),
+                &current,
+                &fees,
+                Holdings::default(),
+                1.0,
+            )
+            .unwrap();
+        assert!(plan.iter().all(|step| step.command != REMOVE));
+        assert!(
+            plan.iter()
+                .any(|step| matches!(step.command, CLMMCommand::AddLiquidity { .. }))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_plan_large_balances_never_panic(
+            current in prop::array::uniform3(0..u64::MAX as u128),
+            fee_amounts in prop::array::uniform3((any::<u64>(), any::<u64>())),
+            wallet_amounts in (any::<u64>(), any::<u64>()),
+            tick in -400..400_i32,
+            stable_first: bool,
+        ) {
+            let liquidity = PerRange::new(current[0], current[1], current[2]).map(Liquidity::new);
+            let fees = fee_amounts.map(|(meme, stable)| holdings(meme, stable));
+            let fees = PerRange::new(fees[0], fees[1], fees[2]);
+            let token_order = if stable_first { TokenOrder::StableFirst } else { TokenOrder::MemeFirst };
+            let planner = RebalancePlanner { token_order, ..planner(0.0) };
+            let wallet = holdings(wallet_amounts.0, wallet_amounts.1);
+            match planner.plan(&layout(), &pool(tick), &liquidity, &fees, wallet, 1.0) {
+                Ok(_) | Err(JoltError::Overflow) => {}
+                Err(error) => prop_assert!(false, "unexpected {error:?}"),
+            }
+        }
+
+        #[test]
+        fn prop_plan_conserves_capital(
+            current in prop::array::uniform3(0..1_000_000_000_000_u128),
+            fee_amounts in prop::array::uniform3((0..1_000_000_u64, 0..1_000_000_u64)),
+            wallet_amounts in (0..1_000_000_u64, 0..1_000_000_u64),
+            weights in prop::array::uniform3(prop::array::uniform3(1..100_u32)),
+            tick in -400..400_i32,
+            stable_first: bool,
+            min_transfer in 0.0..1e6_f64,
+            value_per_lamport in 0.0..100.0_f64,
+        ) {
+            let layout = layout();
+            let pool = pool(tick);
+            let liquidity = PerRange::new(current[0], current[1], current[2]).map(Liquidity::new);
+            let fees = fee_amounts.map(|(meme, stable)| holdings(meme, stable));
+            let fees = PerRange::new(fees[0], fees[1], fees[2]);
+            let [r, f, e] = weights.map(|w| PerRange::new(w[0], w[1], w[2]));
+            let token_order = if stable_first { TokenOrder::StableFirst } else { TokenOrder::MemeFirst };
+            let planner = RebalancePlanner {
+                ratios: AllocationRatios::new(r, f, e).unwrap(),
+                token_order,
+                min_transfer,
+                cost_model: CostModel::default(),
+                value_per_lamport,
+            };
+            let start = holdings(wallet_amounts.0, wallet_amounts.1);
+            let plan = planner.plan(&layout, &pool, &liquidity, &fees, start, 1.0).unwrap();
+
+            let amounts = |kind: RangeKind, liquidity: Liquidity, round_up: bool| {
+                let range = layout.range(kind);
+                amounts_from_liquidity(
+                    pool.sqrt_price,
+                    range.tick_lower.sqrt_price(),
+                    range.tick_upper.sqrt_price(),
+                    liquidity,
+                    round_up,
+                )
+                .unwrap()
+            };
+            // Replay the plan; a deposit the wallet cannot fund underflows
+            let mut after = liquidity;
+            let mut wallet = token_order.to_pool(start.meme, start.stable);
+            let mut depositing = false;
+            for step in &plan {
+                match step.command {
+                    CLMMCommand::CollectFees => {
+                        prop_assert!(!depositing);
+                        let fees = fees.get(step.range);
+                        let (amount_0, amount_1) = token_order.to_pool(fees.meme, fees.stable);
+                        wallet = (wallet.0 + amount_0, wallet.1 + amount_1);
+                    }
+                    CLMMCommand::RemoveLiquidity { amount } => {
+                        prop_assert!(!depositing, "withdrawal after deposit");
+                        *after.get_mut(step.range) = after.get(step.range).checked_sub(amount).unwrap();
+                        let (amount_0, amount_1) = amounts(step.range, amount, false);
+                        wallet = (wallet.0 + amount_0, wallet.1 + amount_1);
+                    }
+                    CLMMCommand::AddLiquidity { amount, .. } => {
+                        depositing = true;
+                        *after.get_mut(step.range) = after.get(step.range).checked_add(amount).unwrap();
+                        let (amount_0, amount_1) = amounts(step.range, amount, true);
+                        wallet = (wallet.0.checked_sub(amount_0).unwrap(), wallet.1.checked_sub(amount_1).unwrap());
+                    }
+                    command => prop_assert!(false, "unexpected {command:?}"),
+                }
+            }
+            let (meme, stable) = token_order.from_pool(wallet.0, wallet.1);
+            prop_assert_eq!(plan.idle(), Holdings::new(meme, stable));
+
+            // Rounding only ever loses a few units to the pool
+            let total = |liquidity: &PerRange<Liquidity>, extra: (u64, u64)| {
+                RangeKind::ALL.iter().fold(extra, |(total_0, total_1), kind| {
+                    let (amount_0, amount_1) = amounts(*kind, *liquidity.get(*kind), false);
+                    (total_0 + amount_0, total_1 + amount_1)
+                })
+            };
+            let collected = plan.iter().filter(|step| step.command == CLMMCommand::CollectFees);
+            let available = token_order.to_pool(start.meme, start.stable);
+            let available = collected.fold(available, |(total_0, total_1), step| {
+                let fees = fees.get(step.range);
+                let (amount_0, amount_1) = token_order.to_pool(fees.meme, fees.stable);
+                (total_0 + amount_0, total_1 + amount_1)
+            });
+            let before = total(&liquidity, available);
+            let after = total(&after, wallet);
+            prop_assert!(after.0 <= before.0 && before.0 - after.0 <= 6);
+            prop_assert!(after.1 <= before.1 && before.1 - after.1 <= 6);
+        }
+    }
+}
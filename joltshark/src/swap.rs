@@ -0,0 +1,902 @@
+//! Step-by-step swap simulation for a concentrated liquidity pool.
+//!
+//! Follows the Raydium CLMM swap loop: the price moves toward the next
+//! initialized tick, each step consumes input or produces output within the
+//! active liquidity, and crossing a tick applies its net liquidity. The pool
+//! is a plain value, so a swap can be simulated on a copy without touching
+//! the original, for pre-trade slippage checks or to see where a rebalance
+//! leaves the price.
+//!
+//! `zero_for_one` swaps sell token0 for token1 and move the price down.
+//! Fees are charged on the input token and tracked with
+//! [`crate::fee_growth`].
+
+use crate::fee_growth::{FeeGrowth, fee_growth_delta, fee_growth_inside};
+use crate::full_math::{U256, mul_div_ceil, mul_div_floor};
+use crate::liquidity_math::{delta_amount_0, delta_amount_1};
+use crate::tick_math::{MAX_SQRT_PRICE_X64, MIN_SQRT_PRICE_X64, Q64, RESOLUTION};
+use crate::{JoltError, Liquidity, SqrtPriceX64, Tick};
+
+/// Denominator for fee rates, so `2500` is a 0.25% fee.
+pub const FEE_RATE_DENOMINATOR: u32 = 1_000_000;
+
+/// Liquidity referencing an initialized tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickInfo {
+    /// Liquidity added to the active liquidity when crossing upward
+    pub liquidity_net: i128,
+    /// Total liquidity of positions bounded by this tick
+    pub liquidity_gross: u128,
+    /// Fee growth on the side of this tick away from the current tick
+    pub fee_growth_outside: FeeGrowth,
+}
+
+/// Fixed-capacity map of initialized ticks, sorted by tick index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TickMap<const N: usize> {
+    ticks: [i32; N],
+    info: [TickInfo; N],
+    len: usize,
+}
+
+impl<const N: usize> TickMap<N> {
+    pub fn new() -> Self {
+        TickMap {
+            ticks: [0; N],
+            info: [TickInfo::default(); N],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, tick: Tick) -> Option<&TickInfo> {
+        let index = self.ticks[..self.len].binary_search(&tick.get()).ok()?;
+        Some(&self.info[index])
+    }
+
+    /// Initialized ticks in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = (Tick, &TickInfo)> {
+        self.ticks[..self.len]
+            .iter()
+            .map(|tick| Tick::new_unchecked(*tick))
+            .zip(self.info[..self.len].iter())
+    }
+
+    /// Applies a liquidity change at a position bound.
+    ///
+    /// `upper` selects the sign of the net change. A tick is initialized by
+    /// its first reference and removed once its gross liquidity returns to
+    /// zero.
+    pub fn update(
+        &mut self,
+        tick: Tick,
+        liquidity_delta: i128,
+        upper: bool,
+    ) -> Result<(), JoltError> {
+        let tick = tick.get();
+        let net_delta = if upper {
+            liquidity_delta.checked_neg()
+        } else {
+            Some(liquidity_delta)
+        }
+        .ok_or(JoltError::Overflow)?;
+        match self.ticks[..self.len].binary_search(&tick) {
+            Ok(index) => {
+                let info = &mut self.info[index];
+                let gross = info
+                    .liquidity_gross
+                    .checked_add_signed(liquidity_delta)
+                    .ok_or(JoltError::LiquidityUnderflow)?;
+                let net = info
+                    .liquidity_net
+                    .checked_add(net_delta)
+                    .ok_or(JoltError::Overflow)?;
+                if gross == 0 {
+                    self.ticks.copy_within(index + 1..self.len, index);
+                    self.info.copy_within(index + 1..self.len, index);
+                    self.len -= 1;
+                } else {
+                    info.liquidity_net = net;
+                    info.liquidity_gross = gross;
+                }
+            }
+            Err(index) => {
+                if liquidity_delta < 0 {
+                    return Err(JoltError::LiquidityUnderflow);
+                }
+                if liquidity_delta == 0 {
+                    return Ok(());
+                }
+                if self.len == N {
+                    return Err(JoltError::TickMapFull);
+                }
+                self.ticks.copy_within(index..self.len, index + 1);
+                self.info.copy_within(index..self.len, index + 1);
+                self.ticks[index] = tick;
+                self.info[index] = TickInfo {
+                    liquidity_net: net_delta,
+                    liquidity_gross: liquidity_delta as u128,
+                    fee_growth_outside: FeeGrowth::default(),
+                };
+                self.len += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Next initialized tick in the swap direction.
+    ///
+    /// Searches at or below `tick` when `zero_for_one`, and strictly above it
+    /// otherwise, matching the convention that the current tick's lower
+    /// boundary is crossed when the price moves down through it.
+    pub fn next_initialized(&self, tick: Tick, zero_for_one: bool) -> Option<(Tick, TickInfo)> {
+        let tick = tick.get();
+        let ticks = &self.ticks[..self.len];
+        let index = if zero_for_one {
+            ticks.partition_point(|t| *t <= tick).checked_sub(1)?
+        } else {
+            let index = ticks.partition_point(|t| *t <= tick);
+            (index < self.len).then_some(index)?
+        };
+        Some((Tick::new_unchecked(self.ticks[index]), self.info[index]))
+    }
+
+    /// Crosses an initialized tick, flipping its fee growth outside.
+    ///
+    /// Returns the tick's net liquidity, or `None` if it is not initialized.
+    pub fn cross(&mut self, tick: Tick, fee_growth_global: FeeGrowth) -> Option<i128> {
+        let index = self.ticks[..self.len].binary_search(&tick.get()).ok()?;
+        let info = &mut self.info[index];
+        info.fee_growth_outside = fee_growth_global.wrapping_sub(info.fee_growth_outside);
+        Some(info.liquidity_net)
+    }
+
+    /// Fee growth outside `tick`, zero if it is not initialized.
+    pub fn fee_growth_outside(&self, tick: Tick) -> FeeGrowth {
+        self.get(tick)
+            .map(|info| info.fee_growth_outside)
+            .unwrap_or_default()
+    }
+}
+
+impl<const N: usize> Default for TickMap<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of a single [`compute_swap_step`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next: SqrtPriceX64,
+    /// Input consumed, excluding the fee
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+}
+
+/// Square root price after adding or removing token0 at constant liquidity.
+///
+/// Rounds up so the price never moves further than the amount pays for.
+/// Computes `L * sqrt / (L ± amount * sqrt)` as the program does, falling
+/// back to `L / (L / sqrt + amount)` only if the sum overflows.
+fn next_sqrt_price_from_amount_0(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount: u64,
+    add: bool,
+) -> Result<u128, JoltError> {
+    if amount == 0 {
+        return Ok(sqrt_price_x64);
+    }
+    let numerator = U256::from_u128(liquidity).shl(RESOLUTION)?;
+    let product = U256::mul(amount as u128, sqrt_price_x64);
+    let denominator = if add {
+        match numerator.add(product) {
+            Ok(denominator) => denominator,
+            Err(_) => {
+                let scaled = numerator.div_rem(sqrt_price_x64).0;
+                let denominator = scaled.add(U256::from_u128(amount as u128))?;
+                return numerator.div_rounding_up(denominator)?.to_u128();
+            }
+        }
+    } else if product < numerator {
+        numerator.wrapping_sub(product)
+    } else {
+        return Err(JoltError::Overflow);
+    };
+    numerator
+        .mul_div_ceil_wide(sqrt_price_x64, denominator)?
+        .to_u128()
+}
+
+/// Square root price after adding or removing token1 at constant liquidity.
+///
+/// Rounds down so the price never moves further than the amount pays for.
+fn next_sqrt_price_from_amount_1(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount: u64,
+    add: bool,
+) -> Result<u128, JoltError> {
+    if add {
+        sqrt_price_x64
+            .checked_add(mul_div_floor(amount as u128, Q64, liquidity)?)
+            .ok_or(JoltError::Overflow)
+    } else {
+        sqrt_price_x64
+            .checked_sub(mul_div_ceil(amount as u128, Q64, liquidity)?)
+            .filter(|price| *price != 0)
+            .ok_or(JoltError::Overflow)
+    }
+}
+
+/// Input amount to move the price between two square root prices.
+fn delta_in(from: u128, to: u128, liquidity: u128, zero_for_one: bool) -> Result<u128, JoltError> {
+    match (from == to, zero_for_one) {
+        (true, _) => Ok(0),
+        (false, true) => delta_amount_0(to, from, liquidity, true),
+        (false, false) => delta_amount_1(from, to, liquidity, true),
+    }
+}
+
+/// Output amount from moving the price between two square root prices.
+fn delta_out(from: u128, to: u128, liquidity: u128, zero_for_one: bool) -> Result<u128, JoltError> {
+    match (from == to, zero_for_one) {
+        (true, _) => Ok(0),
+        (false, true) => delta_amount_1(to, from, liquidity, false),
+        (false, false) => delta_amount_0(from, to, liquidity, false),
+    }
+}
+
+/// Narrows an amount to u64, failing on overflow.
+fn to_u64(amount: u128) -> Result<u64, JoltError> {
+    u64::try_from(amount).map_err(|_| JoltError::Overflow)
+}
+
+/// Swaps within one liquidity segment, stopping at `sqrt_price_target`.
+///
+/// With `exact_input`, `amount_remaining` is the input left including the
+/// fee; otherwise it is the output still wanted. The direction follows from
+/// the target: a lower target is a `zero_for_one` swap. `fee_rate` is in
+/// units of [`FEE_RATE_DENOMINATOR`].
+pub fn compute_swap_step(
+    sqrt_price_current: SqrtPriceX64,
+    sqrt_price_target: SqrtPriceX64,
+    liquidity: Liquidity,
+    amount_remaining: u64,
+    fee_rate: u32,
+    exact_input: bool,
+) -> Result<SwapStep, JoltError> {
+    let zero_for_one = sqrt_price_target <= sqrt_price_current;
+    let current = sqrt_price_current.get();
+    let target = sqrt_price_target.get();
+    let liquidity = liquidity.get();
+    let fee_complement = (FEE_RATE_DENOMINATOR - fee_rate) as u128;
+
+    let sqrt_price_next_x64 = if exact_input {
+        let less_fee = amount_remaining as u128 * fee_complement / FEE_RATE_DENOMINATOR as u128;
+        if delta_in(current, target, liquidity, zero_for_one)? <= less_fee {
+            target
+        } else if zero_for_one {
+            next_sqrt_price_from_amount_0(current, liquidity, less_fee as u64, true)?
+        } else {
+            next_sqrt_price_from_amount_1(current, liquidity, less_fee as u64, true)?
+        }
+    } else if delta_out(current, target, liquidity, zero_for_one)? <= amount_remaining as u128 {
+        target
+    } else if zero_for_one {
+        next_sqrt_price_from_amount_1(current, liquidity, amount_remaining, false)?
+    } else {
+        next_sqrt_price_from_amount_0(current, liquidity, amount_remaining, false)?
+    };
+
+    let amount_in = to_u64(delta_in(
+        current,
+        sqrt_price_next_x64,
+        liquidity,
+        zero_for_one,
+    )?)?;
+    let mut amount_out = to_u64(delta_out(
+        current,
+        sqrt_price_next_x64,
+        liquidity,
+        zero_for_one,
+    )?)?;
+    if !exact_input {
+        amount_out = amount_out.min(amount_remaining);
+    }
+    let fee_amount = if exact_input && sqrt_price_next_x64 != target {
+        // The whole remainder is spent; what the price move did not take is fee
+        amount_remaining - amount_in
+    } else {
+        let fee = amount_in as u128 * fee_rate as u128;
+        to_u64(fee.div_ceil(fee_complement))?
+    };
+    Ok(SwapStep {
+        // The next price lies between the current price and the target
+        sqrt_price_next: SqrtPriceX64::new(sqrt_price_next_x64)?,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// Totals of a completed swap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapResult {
+    /// Input paid, including the fee
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    pub sqrt_price: SqrtPriceX64,
+    pub tick: Tick,
+}
+
+/// Pool state needed to simulate swaps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pool<const N: usize> {
+    sqrt_price: SqrtPriceX64,
+    tick_current: Tick,
+    liquidity: Liquidity,
+    fee_rate: u32,
+    fee_growth_global: FeeGrowth,
+    ticks: TickMap<N>,
+}
+
+impl<const N: usize> Pool<N> {
+    /// Empty pool at the given price.
+    pub fn new(sqrt_price: SqrtPriceX64, fee_rate: u32) -> Result<Self, JoltError> {
+        if fee_rate >= FEE_RATE_DENOMINATOR {
+            return Err(JoltError::InvalidFeeRate(fee_rate));
+        }
+        Ok(Pool {
+            sqrt_price,
+            tick_current: sqrt_price.to_tick()?,
+            liquidity: Liquidity::ZERO,
+            fee_rate,
+            fee_growth_global: FeeGrowth::default(),
+            ticks: TickMap::new(),
+        })
+    }
+
+    pub fn sqrt_price(&self) -> SqrtPriceX64 {
+        self.sqrt_price
+    }
+
+    pub fn tick_current(&self) -> Tick {
+        self.tick_current
+    }
+
+    /// Liquidity active at the current price.
+    pub fn liquidity(&self) -> Liquidity {
+        self.liquidity
+    }
+
+    pub fn fee_rate(&self) -> u32 {
+        self.fee_rate
+    }
+
+    pub fn ticks(&self) -> &TickMap<N> {
+        &self.ticks
+    }
+
+    /// Fee growth accumulated over all liquidity since the pool was created.
+    pub fn fee_growth_global(&self) -> FeeGrowth {
+        self.fee_growth_global
+    }
+
+    /// Fee growth inside `[tick_lower, tick_upper)`.
+    pub fn fee_growth_inside(&self, tick_lower: Tick, tick_upper: Tick) -> FeeGrowth {
+        fee_growth_inside(
+            tick_lower.get(),
+            self.ticks.fee_growth_outside(tick_lower),
+            tick_upper.get(),
+            self.ticks.fee_growth_outside(tick_upper),
+            self.tick_current.get(),
+            self.fee_growth_global,
+        )
+    }
+
+    /// Adds (positive delta) or removes liquidity over `[tick_lower, tick_upper)`.
+    ///
+    /// Leaves the pool unchanged on error.
+    pub fn update_liquidity(
+        &mut self,
+        tick_lower: Tick,
+        tick_upper: Tick,
+        liquidity_delta: i128,
+    ) -> Result<(), JoltError> {
+        if tick_lower >= tick_upper {
+            return Err(JoltError::RangeInverted {
+                tick_lower: tick_lower.get(),
+                tick_upper: tick_upper.get(),
+            });
+        }
+        let mut ticks = self.ticks;
+        for (tick, upper) in [(tick_lower, false), (tick_upper, true)] {
+            let initialized = ticks.get(tick).is_some();
+            ticks.update(tick, liquidity_delta, upper)?;
+            // By convention all growth so far happened below a newly initialized tick
+            if !initialized
+                && tick <= self.tick_current
+                && let Ok(index) = ticks.ticks[..ticks.len].binary_search(&tick.get())
+            {
+                ticks.info[index].fee_growth_outside = self.fee_growth_global;
+            }
+        }
+        let liquidity = if (tick_lower..tick_upper).contains(&self.tick_current) {
+            self.liquidity
+                .get()
+                .checked_add_signed(liquidity_delta)
+                .map(Liquidity::new)
+                .ok_or(JoltError::LiquidityUnderflow)?
+        } else {
+            self.liquidity
+        };
+        self.ticks = ticks;
+        self.liquidity = liquidity;
+        Ok(())
+    }
+
+    /// Executes a swap, crossing initialized ticks as needed.
+    ///
+    /// With `exact_input`, `amount` is the input to spend including fees;
+    /// otherwise it is the output to receive. The swap stops early if the
+    /// price reaches `sqrt_price_limit`, which defaults to the price
+    /// bounds. Leaves the pool unchanged on error.
+    pub fn swap(
+        &mut self,
+        amount: u64,
+        zero_for_one: bool,
+        exact_input: bool,
+        sqrt_price_limit: Option<SqrtPriceX64>,
+    ) -> Result<SwapResult, JoltError> {
+        let limit = match sqrt_price_limit {
+            Some(limit) => limit.get(),
+            None if zero_for_one => MIN_SQRT_PRICE_X64 + 1,
+            None => MAX_SQRT_PRICE_X64 - 1,
+        };
+        let current = self.sqrt_price.get();
+        let valid = if zero_for_one {
+            MIN_SQRT_PRICE_X64 < limit && limit < current
+        } else {
+            current < limit && limit < MAX_SQRT_PRICE_X64
+        };
+        if !valid {
+            return Err(JoltError::InvalidPriceLimit(limit));
+        }
+
+        let mut remaining = amount;
+        let (mut amount_in, mut amount_out, mut fee_amount) = (0u64, 0u64, 0u64);
+        let limit = SqrtPriceX64::new(limit)?;
+        let mut sqrt_price = self.sqrt_price;
+        let mut tick = self.tick_current;
+        let mut liquidity = self.liquidity;
+        let mut fee_growth_global = self.fee_growth_global;
+        let mut ticks = self.ticks;
+        let add = |total: u64, amount: u64| total.checked_add(amount).ok_or(JoltError::Overflow);
+
+        while remaining != 0 && sqrt_price != limit {
+            let next = ticks.next_initialized(tick, zero_for_one);
+            let tick_next = match next {
+                Some((tick_next, _)) => tick_next,
+                None if zero_for_one => Tick::MIN,
+                None => Tick::MAX,
+            };
+            let sqrt_price_next = SqrtPriceX64::from_tick(tick_next);
+            let target = if zero_for_one {
+                sqrt_price_next.max(limit)
+            } else {
+                sqrt_price_next.min(limit)
+            };
+
+            let step_start = sqrt_price;
+            let step = compute_swap_step(
+                sqrt_price,
+                target,
+                liquidity,
+                remaining,
+                self.fee_rate,
+                exact_input,
+            )?;
+            sqrt_price = step.sqrt_price_next;
+            let paid = add(step.amount_in, step.fee_amount)?;
+            if exact_input {
+                remaining -= paid;
+            } else {
+                remaining -= step.amount_out;
+            }
+            amount_in = add(amount_in, paid)?;
+            amount_out = add(amount_out, step.amount_out)?;
+            fee_amount = add(fee_amount, step.fee_amount)?;
+            let growth = fee_growth_delta(step.fee_amount, liquidity.get());
+            let growth = if zero_for_one {
+                FeeGrowth {
+                    token_0_x64: growth,
+                    token_1_x64: 0,
+                }
+            } else {
+                FeeGrowth {
+                    token_0_x64: 0,
+                    token_1_x64: growth,
+                }
+            };
+            fee_growth_global = fee_growth_global.wrapping_add(growth);
+
+            if sqrt_price == sqrt_price_next {
+                if let Some(net) = next.and_then(|_| ticks.cross(tick_next, fee_growth_global)) {
+                    let net = if zero_for_one { -net } else { net };
+                    liquidity = liquidity
+                        .get()
+                        .checked_add_signed(net)
+                        .map(Liquidity::new)
+                        .ok_or(JoltError::LiquidityUnderflow)?;
+                }
+                // The limit keeps the price above the lowest tick's price
+                tick = if zero_for_one {
+                    Tick::new(tick_next.get() - 1)?
+                } else {
+                    tick_next
+                };
+            } else if sqrt_price != step_start {
+                tick = sqrt_price.to_tick()?;
+            }
+        }
+
+        self.sqrt_price = sqrt_price;
+        self.tick_current = tick;
+        self.liquidity = liquidity;
+        self.fee_growth_global = fee_growth_global;
+        self.ticks = ticks;
+        Ok(SwapResult {
+            amount_in,
+            amount_out,
+            fee_amount,
+            sqrt_price,
+            tick,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fee_growth::PositionFees;
+    use crate::liquidity_math::{amounts_from_liquidity, liquidity_from_amounts};
+    use proptest::prelude::*;
+    use rstest::*;
+
+    const LIQUIDITY: Liquidity = Liquidity::new(1 << 30);
+
+    fn tick(tick: i32) -> Tick {
+        Tick::new(tick).unwrap()
+    }
+
+    fn sqrt_price(tick: i32) -> SqrtPriceX64 {
+        SqrtPriceX64::from_tick(self::tick(tick))
+    }
+
+    /// Pool at tick 0 with one wide position and one narrow position.
+    fn pool() -> Pool<8> {
+        let mut pool = Pool::new(sqrt_price(0), 2500).unwrap();
+        pool.update_liquidity(tick(-6000), tick(6000), LIQUIDITY.get() as i128)
+            .unwrap();
+        pool.update_liquidity(tick(-600), tick(600), LIQUIDITY.get() as i128)
+            .unwrap();
+        pool
+    }
+
+    // Cases of the Raydium `sqrt_price_math` tests, with the expected values
+    // recomputed exactly in Q64.64 from `ceil(L * sqrt / (L ± amount * sqrt))`
+    #[rstest]
+    #[case::zero_amount(Q64, 1_000_000_000_000_000_000, 0, true, Q64)]
+    #[case::input_of_0_1_token_0(
+        Q64,
+        1_000_000_000_000_000_000,
+        100_000_000_000_000_000,
+        true,
+        16_769_767_339_735_956_015
+    )]
+    #[case::output_of_0_1_token_0(
+        Q64,
+        1_000_000_000_000_000_000,
+        100_000_000_000_000_000,
+        false,
+        20_496_382_304_121_724_018
+    )]
+    #[case::max_input_gives_min_price(MAX_SQRT_PRICE_X64, 1, u64::MAX, true, 2)]
+    // The floored `L / sqrt` of the fallback formula gives ...777_717 here
+    #[case::input_off_unit_price(
+        18_459_089_752_610_786_183,
+        1_000_000_000_000,
+        987_654_321,
+        true,
+        18_440_864_363_862_207_418
+    )]
+    #[case::output_off_unit_price(
+        27_670_116_110_564_327_424,
+        1_000_000_000_000_000,
+        1_000_000_000,
+        false,
+        27_670_157_615_800_751_126
+    )]
+    fn test_next_sqrt_price_from_amount_0(
+        #[case] sqrt_price_x64: u128,
+        #[case] liquidity: u128,
+        #[case] amount: u64,
+        #[case] add: bool,
+        #[case] expected: u128,
+    ) {
+        assert_eq!(
+            next_sqrt_price_from_amount_0(sqrt_price_x64, liquidity, amount, add),
+            Ok(expected)
+        );
+    }
+
+    #[test]
+    fn test_next_sqrt_price_from_amount_0_output_exceeds_reserve() {
+        // Removing 1e18 token0 at price 1 needs L > 1e18
+        assert_eq!(
+            next_sqrt_price_from_amount_0(
+                Q64,
+                1_000_000_000_000_000_000,
+                1_000_000_000_000_000_000,
+                false
+            ),
+            Err(JoltError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_tick_map_update_and_remove() {
+        let mut ticks: TickMap<2> = TickMap::new();
+        ticks.update(tick(10), 5, false).unwrap();
+        ticks.update(tick(-10), 7, true).unwrap();
+        ticks.update(tick(10), 3, true).unwrap();
+        assert!(ticks.iter().map(|(t, _)| t).eq([tick(-10), tick(10)]));
+        assert_eq!(
+            ticks.get(tick(10)),
+            Some(&TickInfo {
+                liquidity_net: 2,
+                liquidity_gross: 8,
+                fee_growth_outside: FeeGrowth::default(),
+            })
+        );
+        assert_eq!(
+            ticks.update(tick(20), 1, false),
+            Err(JoltError::TickMapFull)
+        );
+        assert_eq!(
+            ticks.update(tick(10), -9, false),
+            Err(JoltError::LiquidityUnderflow)
+        );
+        ticks.update(tick(-10), -7, true).unwrap();
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks.get(tick(-10)), None);
+    }
+
+    #[rstest]
+    #[case::down_from_between(0, true, Some(-600))]
+    #[case::down_from_initialized(600, true, Some(600))]
+    #[case::down_past_all(-7000, true, None)]
+    #[case::up_from_between(0, false, Some(600))]
+    #[case::up_from_initialized(-600, false, Some(600))]
+    #[case::up_past_all(6000, false, None)]
+    fn test_next_initialized(
+        #[case] current: i32,
+        #[case] zero_for_one: bool,
+        #[case] expected: Option<i32>,
+    ) {
+        let next = pool().ticks().next_initialized(tick(current), zero_for_one);
+        assert_eq!(next.map(|(t, _)| t.get()), expected);
+    }
+
+    #[test]
+    fn test_update_liquidity_tracks_active_liquidity() {
+        let mut pool = pool();
+        assert_eq!(pool.liquidity().get(), 2 * LIQUIDITY.get());
+        pool.update_liquidity(tick(100), tick(200), 5).unwrap();
+        assert_eq!(pool.liquidity().get(), 2 * LIQUIDITY.get());
+        assert_eq!(
+            pool.update_liquidity(tick(200), tick(100), 5),
+            Err(JoltError::RangeInverted {
+                tick_lower: 200,
+                tick_upper: 100
+            })
+        );
+        let before = pool;
+        assert_eq!(
+            pool.update_liquidity(tick(-600), tick(600), -(LIQUIDITY.get() as i128) - 1),
+            Err(JoltError::LiquidityUnderflow)
+        );
+        assert_eq!(pool, before);
+    }
+
+    #[rstest]
+    #[case::zero_for_one(true)]
+    #[case::one_for_zero(false)]
+    fn test_step_within_range_matches_liquidity_math(#[case] zero_for_one: bool) {
+        let current = sqrt_price(0);
+        let target = sqrt_price(if zero_for_one { -600 } else { 600 });
+        let step = compute_swap_step(current, target, LIQUIDITY, 10_000, 0, true).unwrap();
+        assert_eq!(step.amount_in, 10_000);
+        assert_eq!(step.fee_amount, 0);
+        // Amounts backing the liquidity change by exactly what was swapped
+        let (lower, upper) = (sqrt_price(-600), sqrt_price(600));
+        let before = amounts_from_liquidity(current, lower, upper, LIQUIDITY, false).unwrap();
+        let after =
+            amounts_from_liquidity(step.sqrt_price_next, lower, upper, LIQUIDITY, false).unwrap();
+        let (gained, paid) = if zero_for_one {
+            (before.1 - after.1, after.0 - before.0)
+        } else {
+            (before.0 - after.0, after.1 - before.1)
+        };
+        assert!(paid <= 10_000 && 10_000 - paid <= 1);
+        assert!(gained.abs_diff(step.amount_out) <= 1);
+    }
+
+    #[test]
+    fn test_step_stops_at_target() {
+        let (current, target) = (sqrt_price(0), sqrt_price(-10));
+        let step = compute_swap_step(current, target, LIQUIDITY, u64::MAX, 3000, true).unwrap();
+        assert_eq!(step.sqrt_price_next, target);
+        assert_eq!(
+            step.fee_amount,
+            (step.amount_in as u128 * 3000).div_ceil(997_000) as u64
+        );
+    }
+
+    #[test]
+    fn test_exact_input_crosses_ticks() {
+        let mut pool = pool();
+        let result = pool.swap(300_000_000, true, true, None).unwrap();
+        assert_eq!(result.amount_in, 300_000_000);
+        assert!(result.tick < tick(-600));
+        assert_eq!(pool.tick_current(), result.tick);
+        assert_eq!(pool.liquidity(), LIQUIDITY);
+        // 0.25% of the input, rounded up once per step
+        assert!((750_000..=750_002).contains(&result.fee_amount));
+        assert!(result.amount_out < 300_000_000);
+    }
+
+    #[test]
+    fn test_exact_output_delivers_amount() {
+        let mut pool = pool();
+        let result = pool.swap(50_000_000, false, false, None).unwrap();
+        assert_eq!(result.amount_out, 50_000_000);
+        assert!(result.amount_in > result.amount_out);
+        assert!(result.tick >= Tick::ZERO);
+        // Paying the same input exactly yields at least the requested output
+        let mut replay = self::pool();
+        let exact_in = replay.swap(result.amount_in, false, true, None).unwrap();
+        assert!(exact_in.amount_out >= 50_000_000);
+    }
+
+    #[test]
+    fn test_price_limit_stops_swap() {
+        let mut pool = pool();
+        let limit = sqrt_price(-100);
+        let result = pool.swap(u64::MAX / 4, true, true, Some(limit)).unwrap();
+        assert_eq!(result.sqrt_price, limit);
+        assert_eq!(result.tick, tick(-100));
+        assert!(result.amount_in < u64::MAX / 4);
+        assert_eq!(
+            pool.swap(1, true, true, Some(sqrt_price(10))),
+            Err(JoltError::InvalidPriceLimit(sqrt_price(10).get()))
+        );
+    }
+
+    #[test]
+    fn test_swap_through_empty_pool_reaches_limit() {
+        let mut pool: Pool<4> = Pool::new(sqrt_price(0), 100).unwrap();
+        let limit = sqrt_price(500);
+        let result = pool.swap(1_000, false, true, Some(limit)).unwrap();
+        assert_eq!((result.amount_in, result.amount_out), (0, 0));
+        assert_eq!(result.tick, tick(500));
+    }
+
+    #[test]
+    fn test_invalid_fee_rate() {
+        assert_eq!(
+            Pool::<1>::new(sqrt_price(0), FEE_RATE_DENOMINATOR),
+            Err(JoltError::InvalidFeeRate(FEE_RATE_DENOMINATOR))
+        );
+    }
+
+    #[test]
+    fn test_fees_accrue_to_active_positions() {
+        let mut pool = pool();
+        let (wide_range, narrow_range) = ((tick(-6000), tick(6000)), (tick(-600), tick(600)));
+        let wide = PositionFees::new(
+            LIQUIDITY.get(),
+            pool.fee_growth_inside(wide_range.0, wide_range.1),
+        );
+        let narrow = PositionFees::new(
+            LIQUIDITY.get(),
+            pool.fee_growth_inside(narrow_range.0, narrow_range.1),
+        );
+        let result = pool.swap(300_000_000, true, true, None).unwrap();
+
+        let (wide_0, wide_1) = wide
+            .uncollected(pool.fee_growth_inside(wide_range.0, wide_range.1))
+            .unwrap();
+        let (narrow_0, narrow_1) = narrow
+            .uncollected(pool.fee_growth_inside(narrow_range.0, narrow_range.1))
+            .unwrap();
+        assert_eq!((wide_1, narrow_1), (0, 0));
+        // The narrow position only earned while the price was inside its range
+        assert!(0 < narrow_0 && narrow_0 < wide_0);
+        let earned = wide_0 + narrow_0;
+        assert!(earned <= result.fee_amount && result.fee_amount - earned <= 4);
+        // A position below the price earns nothing from swaps that stay above it
+        let below_range = (tick(-7000), tick(-6000));
+        pool.update_liquidity(below_range.0, below_range.1, LIQUIDITY.get() as i128)
+            .unwrap();
+        let below = PositionFees::new(
+            LIQUIDITY.get(),
+            pool.fee_growth_inside(below_range.0, below_range.1),
+        );
+        pool.swap(1_000_000, false, true, None).unwrap();
+        assert_eq!(
+            below.uncollected(pool.fee_growth_inside(below_range.0, below_range.1)),
+            Ok((0, 0))
+        );
+    }
+
+    #[test]
+    fn test_fee_growth_outside_flips_on_round_trip() {
+        let mut pool = pool();
+        let out = pool.swap(300_000_000, true, true, None).unwrap();
+        pool.swap(out.amount_out, false, true, None).unwrap();
+        assert!(pool.tick_current() > tick(-600));
+        let global = pool.fee_growth_global();
+        assert!(global.token_0_x64 > 0 && global.token_1_x64 > 0);
+        // Growth below -600 is the token0 fee charged while outside R_narrow
+        let below = pool.ticks().fee_growth_outside(tick(-600));
+        let inside = pool.fee_growth_inside(tick(-600), tick(600));
+        let wide = pool.fee_growth_inside(tick(-6000), tick(6000));
+        assert_eq!(inside.wrapping_add(below), wide);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip_is_not_profitable(
+            amount in 1..1_000_000_000_u64,
+            zero_for_one: bool,
+        ) {
+            let mut pool = pool();
+            let there = pool.swap(amount, zero_for_one, true, None).unwrap();
+            let back = pool.swap(there.amount_out.max(1), !zero_for_one, true, None).unwrap();
+            prop_assert!(back.amount_out <= amount);
+        }
+
+        #[test]
+        fn prop_exact_input_conserves_pool_value(
+            amount in 1..100_000_000_u64,
+            zero_for_one: bool,
+        ) {
+            // Without fees a single-position pool pays out exactly what its reserves lose
+            let (lower, upper) = (sqrt_price(-6000), sqrt_price(6000));
+            let mut pool: Pool<2> = Pool::new(sqrt_price(0), 0).unwrap();
+            let liquidity =
+                liquidity_from_amounts(sqrt_price(0), lower, upper, u32::MAX as u64, u32::MAX as u64)
+                    .unwrap();
+            pool.update_liquidity(tick(-6000), tick(6000), liquidity.get() as i128).unwrap();
+            let before = amounts_from_liquidity(pool.sqrt_price(), lower, upper, liquidity, false)
+                .unwrap();
+            let result = pool.swap(amount, zero_for_one, true, None).unwrap();
+            let after = amounts_from_liquidity(pool.sqrt_price(), lower, upper, liquidity, false)
+                .unwrap();
+            let reserve_out = if zero_for_one { before.1 - after.1 } else { before.0 - after.0 };
+            prop_assert!(reserve_out.abs_diff(result.amount_out) <= 1);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Range layout for the three-range liquidity strategy.
+//!
+//! The strategy keeps three adjacent, non-overlapping positions ordered by
+//! price: R_restock below R_fee below R_exit. [`ThreeRange`] validates these
+//! invariants once at construction, so every consumer can rely on them.
+
+use crate::{JoltError, Tick, TickSpacing};
+
+/// Identifies one of the three strategy ranges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RangeKind {
+    /// Range below R_fee that converts stable coin into meme coin.
+    Restock,
+    /// Central range that earns trading fees.
+    Fee,
+    /// Range above R_fee that converts meme coin into stable coin.
+    Exit,
+}
+
+impl RangeKind {
+    /// All ranges ordered by price, lowest first.
+    pub const ALL: [RangeKind; 3] = [RangeKind::Restock, RangeKind::Fee, RangeKind::Exit];
+}
+
+/// Half-open tick interval `[tick_lower, tick_upper)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TickRange {
+    /// Inclusive lower tick
+    pub tick_lower: Tick,
+    /// Exclusive upper tick
+    pub tick_upper: Tick,
+}
+
+impl TickRange {
+    pub fn new(tick_lower: Tick, tick_upper: Tick) -> Self {
+        TickRange {
+            tick_lower,
+            tick_upper,
+        }
+    }
+
+    /// Returns true if `tick` lies within the range.
+    pub fn contains(&self, tick: Tick) -> bool {
+        self.tick_lower <= tick && tick < self.tick_upper
+    }
+
+    /// Number of ticks covered by the range.
+    pub fn width(&self) -> i32 {
+        // Both bounds are within [MIN_TICK, MAX_TICK], so this cannot overflow
+        self.tick_upper.get() - self.tick_lower.get()
+    }
+}
+
+/// One value for each of the three ranges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PerRange<V> {
+    pub restock: V,
+    pub fee: V,
+    pub exit: V,
+}
+
+impl<V> PerRange<V> {
+    pub fn new(restock: V, fee: V, exit: V) -> Self {
+        PerRange { restock, fee, exit }
+    }
+
+    /// Returns the value for `kind`.
+    pub fn get(&self, kind: RangeKind) -> &V {
+        match kind {
+            RangeKind::Restock => &self.restock,
+            RangeKind::Fee => &self.fee,
+            RangeKind::Exit => &self.exit,
+        }
+    }
+
+    /// Returns the value for `kind` mutably.
+    pub fn get_mut(&mut self, kind: RangeKind) -> &mut V {
+        match kind {
+            RangeKind::Restock => &mut self.restock,
+            RangeKind::Fee => &mut self.fee,
+            RangeKind::Exit => &mut self.exit,
+        }
+    }
+
+    /// Applies `f` to each value.
+    pub fn map<W>(self, mut f: impl FnMut(V) -> W) -> PerRange<W> {
+        PerRange {
+            restock: f(self.restock),
+            fee: f(self.fee),
+            exit: f(self.exit),
+        }
+    }
+}
+
+/// Validated layout of the R_restock, R_fee and R_exit ranges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ThreeRange {
+    restock: TickRange,
+    fee: TickRange,
+    exit: TickRange,
+    tick_spacing: TickSpacing,
+}
+
+impl ThreeRange {
+    /// Builds a layout, checking every range invariant.
+    pub fn new(
+        restock: TickRange,
+        fee: TickRange,
+        exit: TickRange,
+        tick_spacing: TickSpacing,
+    ) -> Result<Self, JoltError> {
+        let layout = ThreeRange {
+            restock,
+            fee,
+            exit,
+            tick_spacing,
+        };
+        for kind in RangeKind::ALL {
+            let range = layout.range(kind);
+            if range.tick_lower >= range.tick_upper {
+                return Err(JoltError::RangeInverted {
+                    tick_lower: range.tick_lower.get(),
+                    tick_upper: range.tick_upper.get(),
+                });
+            }
+            for tick in [range.tick_lower, range.tick_upper] {
+                if !tick.is_aligned(tick_spacing) {
+                    return Err(JoltError::TickMisaligned {
+                        tick: tick.get(),
+                        spacing: tick_spacing.get(),
+                    });
+                }
+            }
+        }
+        for pair in RangeKind::ALL.windows(2) {
+            let (below, above) = (pair[0], pair[1]);
+            let (lower, upper) = (layout.range(below), layout.range(above));
+            if lower.tick_lower >= upper.tick_lower {
+                return Err(JoltError::RangesUnordered(below, above));
+            }
+            if lower.tick_upper > upper.tick_lower {
+                return Err(JoltError::RangesOverlap(below, above));
+            }
+            if lower.tick_upper < upper.tick_lower {
+                return Err(JoltError::RangesGap(below, above));
+            }
+        }
+        Ok(layout)
+    }
+
+    /// Builds a layout from its four boundaries, lowest first.
+    pub fn from_bounds(bounds: [i32; 4], tick_spacing: i32) -> Result<Self, JoltError> {
+        let tick_spacing = TickSpacing::new(tick_spacing)?;
+        let [a, b, c, d] = [
+            Tick::new(bounds[0])?,
+            Tick::new(bounds[1])?,
+            Tick::new(bounds[2])?,
+            Tick::new(bounds[3])?,
+        ];
+        ThreeRange::new(
+            TickRange::new(a, b),
+            TickRange::new(b, c),
+            TickRange::new(c, d),
+            tick_spacing,
+        )
+    }
+
+    pub fn restock(&self) -> TickRange {
+        self.restock
+    }
+
+    pub fn fee(&self) -> TickRange {
+        self.fee
+    }
+
+    pub fn exit(&self) -> TickRange {
+        self.exit
+    }
+
+    pub fn tick_spacing(&self) -> TickSpacing {
+        self.tick_spacing
+    }
+
+    /// Returns the tick range for `kind`.
+    pub fn range(&self, kind: RangeKind) -> TickRange {
+        match kind {
+            RangeKind::Restock => self.restock,
+            RangeKind::Fee => self.fee,
+            RangeKind::Exit => self.exit,
+        }
+    }
+
+    /// Lowest tick covered by any range.
+    pub fn tick_lower(&self) -> Tick {
+        self.restock.tick_lower
+    }
+
+    /// Highest tick covered by any range (exclusive).
+    pub fn tick_upper(&self) -> Tick {
+        self.exit.tick_upper
+    }
+
+    /// Returns the range containing `tick`, or `None` outside all three.
+    pub fn active_range(&self, tick: Tick) -> Option<RangeKind> {
+        RangeKind::ALL
+            .into_iter()
+            .find(|kind| self.range(*kind).contains(tick))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tick_math::MAX_TICK;
+    use rstest::*;
+
+    fn layout() -> ThreeRange {
+        ThreeRange::from_bounds([-300, -100, 100, 300], 10).unwrap()
+    }
+
+    fn tick(tick: i32) -> Tick {
+        Tick::new(tick).unwrap()
+    }
+
+    fn range(tick_lower: i32, tick_upper: i32) -> TickRange {
+        TickRange::new(tick(tick_lower), tick(tick_upper))
+    }
+
+    #[test]
+    fn test_three_range_accessors() {
+        let layout = layout();
+        assert_eq!(layout.restock(), range(-300, -100));
+        assert_eq!(layout.fee(), range(-100, 100));
+        assert_eq!(layout.exit(), range(100, 300));
+        assert_eq!(layout.tick_spacing().get(), 10);
+        assert_eq!(
+            (layout.tick_lower(), layout.tick_upper()),
+            (tick(-300), tick(300))
+        );
+    }
+
+    #[rstest]
+    #[case::below_layout(-301, None)]
+    #[case::restock_lower_bound(-300, Some(RangeKind::Restock))]
+    #[case::restock_inside(-200, Some(RangeKind::Restock))]
+    #[case::fee_lower_bound(-100, Some(RangeKind::Fee))]
+    #[case::fee_inside(0, Some(RangeKind::Fee))]
+    #[case::fee_upper_edge(99, Some(RangeKind::Fee))]
+    #[case::exit_lower_bound(100, Some(RangeKind::Exit))]
+    #[case::exit_upper_edge(299, Some(RangeKind::Exit))]
+    #[case::above_layout(300, None)]
+    fn test_active_range(#[case] current: i32, #[case] expected: Option<RangeKind>) {
+        assert_eq!(layout().active_range(tick(current)), expected);
+    }
+
+    #[rstest]
+    #[case::empty_fee(
+        [(-300, -100), (-100, -100), (-100, 300)],
+        JoltError::RangeInverted { tick_lower: -100, tick_upper: -100 }
+    )]
+    #[case::inverted_exit(
+        [(-300, -100), (-100, 100), (300, 100)],
+        JoltError::RangeInverted { tick_lower: 300, tick_upper: 100 }
+    )]
+    #[case::misaligned_restock(
+        [(-305, -100), (-100, 100), (100, 300)],
+        JoltError::TickMisaligned { tick: -305, spacing: 10 }
+    )]
+    #[case::unordered(
+        [(-100, 100), (-300, -100), (100, 300)],
+        JoltError::RangesUnordered(RangeKind::Restock, RangeKind::Fee)
+    )]
+    #[case::overlap(
+        [(-300, -50), (-100, 100), (100, 300)],
+        JoltError::RangesOverlap(RangeKind::Restock, RangeKind::Fee)
+    )]
+    #[case::gap(
+        [(-300, -100), (-100, 100), (150, 300)],
+        JoltError::RangesGap(RangeKind::Fee, RangeKind::Exit)
+    )]
+    fn test_three_range_invariants(#[case] ranges: [(i32, i32); 3], #[case] expected: JoltError) {
+        let [restock, fee, exit] = ranges.map(|(lower, upper)| range(lower, upper));
+        let tick_spacing = TickSpacing::new(10).unwrap();
+        assert_eq!(
+            ThreeRange::new(restock, fee, exit, tick_spacing),
+            Err(expected)
+        );
+    }
+
+    #[rstest]
+    #[case::zero_spacing([-300, -100, 100, 300], 0, JoltError::InvalidSpacing(0))]
+    #[case::negative_spacing([-300, -100, 100, 300], -10, JoltError::InvalidSpacing(-10))]
+    #[case::out_of_bounds(
+        [-300, -100, 100, MAX_TICK + 1], 1,
+        JoltError::TickOutOfBounds(MAX_TICK + 1)
+    )]
+    fn test_from_bounds_rejects_raw_values(
+        #[case] bounds: [i32; 4],
+        #[case] tick_spacing: i32,
+        #[case] expected: JoltError,
+    ) {
+        assert_eq!(ThreeRange::from_bounds(bounds, tick_spacing), Err(expected));
+    }
+}
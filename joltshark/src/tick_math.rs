@@ -0,0 +1,222 @@
+//! Integer tick math for Raydium CLMM pools.
+//!
+//! Converts between ticks and Q64.64 square root prices using the same
+//! fixed-point algorithm as the on-chain program, so results match the
+//! values Raydium accepts bit for bit. Unlike `crate::tick_at_price`,
+//! nothing here goes through floating point.
+
+use crate::JoltError;
+
+/// Number of fractional bits in a Q64.64 value.
+pub const RESOLUTION: u32 = 64;
+
+/// 1.0 in Q64.64 fixed point.
+pub const Q64: u128 = 1 << RESOLUTION;
+
+/// Lowest tick supported by the program.
+pub const MIN_TICK: i32 = -443636;
+
+/// Highest tick supported by the program.
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+/// Square root price at `MIN_TICK` in Q64.64.
+pub const MIN_SQRT_PRICE_X64: u128 = 4295048016;
+
+/// Square root price at `MAX_TICK` in Q64.64.
+pub const MAX_SQRT_PRICE_X64: u128 = 79226673521066979257578248091;
+
+/// Number of fractional bits refined when computing log2.
+const BIT_PRECISION: u32 = 16;
+
+/// 2^32 / log2(sqrt(1.0001)), used for the change of base to ticks.
+const LOG_B_2_X32: i128 = 59543866431248;
+
+/// 0.01 in Q64.64, the lower error margin of the log approximation.
+const LOG_B_P_ERR_MARGIN_LOWER_X64: i128 = 184467440737095516;
+
+/// 2^-14 / log2(sqrt(1.0001)) + 0.01 in Q64.64, the upper error margin.
+const LOG_B_P_ERR_MARGIN_UPPER_X64: i128 = 15793534762490258745;
+
+/// Returns `sqrt(1.0001^tick) * 2^64`.
+///
+/// Each set bit of `|tick|` multiplies in a precomputed Q64.64 factor of
+/// `1 / sqrt(1.0001)^(2^i)`. Positive ticks are inverted at the end.
+pub fn get_sqrt_price_at_tick(tick: i32) -> Result<u128, JoltError> {
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        return Err(JoltError::TickOutOfBounds(tick));
+    }
+
+    let mut ratio: u128 = if abs_tick & 0x1 != 0 {
+        0xfffcb933bd6fb800
+    } else {
+        Q64
+    };
+
+    const FACTORS: [(u32, u128); 18] = [
+        (0x2, 0xfff97272373d4000),
+        (0x4, 0xfff2e50f5f657000),
+        (0x8, 0xffe5caca7e10f000),
+        (0x10, 0xffcb9843d60f7000),
+        (0x20, 0xff973b41fa98e800),
+        (0x40, 0xff2ea16466c9b000),
+        (0x80, 0xfe5dee046a9a3800),
+        (0x100, 0xfcbe86c7900bb000),
+        (0x200, 0xf987a7253ac65800),
+        (0x400, 0xf3392b0822bb6000),
+        (0x800, 0xe7159475a2caf000),
+        (0x1000, 0xd097f3bdfd2f2000),
+        (0x2000, 0xa9f746462d9f8000),
+        (0x4000, 0x70d869a156f31c00),
+        (0x8000, 0x31be135f97ed3200),
+        (0x10000, 0x9aa508b5b85a500),
+        (0x20000, 0x5d6af8dedc582c),
+        (0x40000, 0x2216e584f5fa),
+    ];
+    for (mask, factor) in FACTORS {
+        if abs_tick & mask != 0 {
+            // ratio <= 2^64 and factor < 2^64, so the product fits in u128
+            ratio = (ratio * factor) >> RESOLUTION;
+        }
+    }
+
+    if tick > 0 {
+        ratio = u128::MAX / ratio;
+    }
+
+    Ok(ratio)
+}
+
+/// Returns the greatest tick whose square root price is `<= sqrt_price_x64`.
+///
+/// Computes log2 of the price with 16 bits of fractional precision, changes
+/// base to sqrt(1.0001) and resolves the remaining off-by-one ambiguity by
+/// checking the candidate tick with `get_sqrt_price_at_tick`. Fails with
+/// [`JoltError::SqrtPriceOutOfBounds`] outside
+/// `[MIN_SQRT_PRICE_X64, MAX_SQRT_PRICE_X64)`.
+pub fn get_tick_at_sqrt_price(sqrt_price_x64: u128) -> Result<i32, JoltError> {
+    // The upper bound is exclusive because the price can never reach MAX_TICK
+    if !(MIN_SQRT_PRICE_X64..MAX_SQRT_PRICE_X64).contains(&sqrt_price_x64) {
+        return Err(JoltError::SqrtPriceOutOfBounds(sqrt_price_x64));
+    }
+
+    // Integer part of log2 from the most significant bit
+    let msb = 127 - sqrt_price_x64.leading_zeros();
+    let log2p_integer_x32 = (msb as i128 - RESOLUTION as i128) << 32;
+
+    // Normalize to Q1.63 and square repeatedly to extract fractional bits
+    let mut r = if msb >= 63 {
+        sqrt_price_x64 >> (msb - 63)
+    } else {
+        sqrt_price_x64 << (63 - msb)
+    };
+    let mut bit: i128 = 0x8000_0000_0000_0000;
+    let mut log2p_fraction_x64: i128 = 0;
+    for _ in 0..BIT_PRECISION {
+        r *= r;
+        let is_r_more_than_two = (r >> 127) as u32;
+        r >>= 63 + is_r_more_than_two;
+        log2p_fraction_x64 += bit * is_r_more_than_two as i128;
+        bit >>= 1;
+    }
+    let log2p_x32 = log2p_integer_x32 + (log2p_fraction_x64 >> 32);
+
+    // Change of base from 2 to sqrt(1.0001)
+    let log_sqrt_10001_x64 = log2p_x32 * LOG_B_2_X32;
+    let tick_low = ((log_sqrt_10001_x64 - LOG_B_P_ERR_MARGIN_LOWER_X64) >> 64) as i32;
+    let tick_high = ((log_sqrt_10001_x64 + LOG_B_P_ERR_MARGIN_UPPER_X64) >> 64) as i32;
+
+    Ok(if tick_low == tick_high {
+        tick_low
+    } else if get_sqrt_price_at_tick(tick_high)? <= sqrt_price_x64 {
+        tick_high
+    } else {
+        tick_low
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::min_tick(MIN_TICK, MIN_SQRT_PRICE_X64)]
+    #[case::min_tick_plus_one(-443635, 4295262763)]
+    #[case::negative_100000(-100000, 124324258983086206)]
+    #[case::negative_10000(-10000, 11188795550325113405)]
+    #[case::negative_1000(-1000, 17547129613991882732)]
+    #[case::negative_100(-100, 18354745142194513203)]
+    #[case::negative_10(-10, 18437523468038803493)]
+    #[case::negative_1(-1, 18445821805675395072)]
+    #[case::zero(0, Q64)]
+    #[case::positive_1(1, 18447666387855957090)]
+    #[case::positive_10(10, 18455969290605287889)]
+    #[case::positive_100(100, 18539204128674375874)]
+    #[case::positive_1000(1000, 19392480388906522465)]
+    #[case::positive_10000(10000, 30412779051186690180)]
+    #[case::positive_100000(100000, 2737055259402209284734)]
+    #[case::max_tick_minus_one(443635, 79222712485061176096288712065)]
+    #[case::max_tick(MAX_TICK, MAX_SQRT_PRICE_X64)]
+    fn test_sqrt_price_at_tick(#[case] tick: i32, #[case] expected: u128) {
+        assert_eq!(get_sqrt_price_at_tick(tick), Ok(expected));
+    }
+
+    #[rstest]
+    #[case::below_min(MIN_TICK - 1)]
+    #[case::above_max(MAX_TICK + 1)]
+    #[case::i32_min(i32::MIN)]
+    #[case::i32_max(i32::MAX)]
+    fn test_sqrt_price_at_tick_out_of_bounds(#[case] tick: i32) {
+        assert_eq!(
+            get_sqrt_price_at_tick(tick),
+            Err(JoltError::TickOutOfBounds(tick))
+        );
+    }
+
+    #[rstest]
+    #[case::min_sqrt_price(MIN_SQRT_PRICE_X64, MIN_TICK)]
+    #[case::below_max_sqrt_price(MAX_SQRT_PRICE_X64 - 1, MAX_TICK - 1)]
+    #[case::one(Q64, 0)]
+    #[case::just_above_one(Q64 + 1, 0)]
+    #[case::just_below_one(Q64 - 1, -1)]
+    #[case::two_pow_80(1 << 80, 221818)]
+    #[case::two_pow_40(1 << 40, -332728)]
+    fn test_tick_at_sqrt_price(#[case] sqrt_price_x64: u128, #[case] expected: i32) {
+        assert_eq!(get_tick_at_sqrt_price(sqrt_price_x64), Ok(expected));
+    }
+
+    #[rstest]
+    #[case::below_min(MIN_SQRT_PRICE_X64 - 1)]
+    #[case::at_max(MAX_SQRT_PRICE_X64)]
+    #[case::zero(0)]
+    #[case::u128_max(u128::MAX)]
+    fn test_tick_at_sqrt_price_out_of_bounds(#[case] sqrt_price_x64: u128) {
+        assert_eq!(
+            get_tick_at_sqrt_price(sqrt_price_x64),
+            Err(JoltError::SqrtPriceOutOfBounds(sqrt_price_x64))
+        );
+    }
+
+    #[test]
+    fn test_sqrt_price_strictly_increasing() {
+        let mut previous = get_sqrt_price_at_tick(MIN_TICK).unwrap();
+        for tick in (MIN_TICK + 1..=MAX_TICK).step_by(997) {
+            let sqrt_price = get_sqrt_price_at_tick(tick).unwrap();
+            assert!(previous < sqrt_price, "not increasing at tick {tick}");
+            previous = sqrt_price;
+        }
+    }
+
+    #[test]
+    fn test_tick_round_trip() {
+        for tick in (MIN_TICK..MAX_TICK).step_by(331) {
+            let sqrt_price = get_sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(get_tick_at_sqrt_price(sqrt_price), Ok(tick));
+            assert_eq!(get_tick_at_sqrt_price(sqrt_price + 1), Ok(tick));
+            if tick > MIN_TICK {
+                assert_eq!(get_tick_at_sqrt_price(sqrt_price - 1), Ok(tick - 1));
+            }
+        }
+    }
+}
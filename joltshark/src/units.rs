@@ -0,0 +1,539 @@
+//! Typed ticks, square root prices, liquidity and token amounts.
+//!
+//! Each quantity the CLMM program distinguishes gets its own type, so passing
+//! a token amount where liquidity is expected, or an unchecked `i32` where a
+//! tick is expected, fails to compile. Constructors check the invariants the
+//! program enforces: ticks within `[MIN_TICK, MAX_TICK]`, positive spacing,
+//! square root prices within the tick range and spacing-aligned range
+//! bounds.
+//!
+//! ```compile_fail
+//! use joltshark::units::Stable;
+//! use joltshark::{CLMMCommand, TokenAmount};
+//!
+//! // A token amount is not liquidity
+//! let command: CLMMCommand = CLMMCommand::RemoveLiquidity {
+//!     amount: TokenAmount::<Stable>::new(5),
+//! };
+//! ```
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::JoltError;
+use crate::tick_math::{
+    MAX_SQRT_PRICE_X64, MAX_TICK, MIN_SQRT_PRICE_X64, MIN_TICK, get_sqrt_price_at_tick,
+    get_tick_at_sqrt_price,
+};
+
+/// A tick within `[MIN_TICK, MAX_TICK]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tick(i32);
+
+impl Tick {
+    pub const MIN: Tick = Tick(MIN_TICK);
+    pub const MAX: Tick = Tick(MAX_TICK);
+    pub const ZERO: Tick = Tick(0);
+
+    /// Rejects ticks outside `[MIN_TICK, MAX_TICK]`.
+    pub const fn new(tick: i32) -> Result<Self, JoltError> {
+        if MIN_TICK <= tick && tick <= MAX_TICK {
+            Ok(Tick(tick))
+        } else {
+            Err(JoltError::TickOutOfBounds(tick))
+        }
+    }
+
+    /// Like [`Tick::new`], also rejecting ticks that are not a multiple of
+    /// `spacing`, as position bounds must be.
+    pub fn aligned(tick: i32, spacing: TickSpacing) -> Result<Self, JoltError> {
+        let tick = Tick::new(tick)?;
+        if !tick.is_aligned(spacing) {
+            return Err(JoltError::TickMisaligned {
+                tick: tick.0,
+                spacing: spacing.get(),
+            });
+        }
+        Ok(tick)
+    }
+
+    /// Wraps a tick the caller has already checked against the bounds.
+    pub(crate) const fn new_unchecked(tick: i32) -> Self {
+        Tick(tick)
+    }
+
+    pub const fn get(self) -> i32 {
+        self.0
+    }
+
+    /// Returns true if the tick is a multiple of `spacing`.
+    pub fn is_aligned(self, spacing: TickSpacing) -> bool {
+        self.0 % spacing.get() == 0
+    }
+
+    /// Square root price at this tick.
+    pub fn sqrt_price(self) -> SqrtPriceX64 {
+        SqrtPriceX64::from_tick(self)
+    }
+}
+
+impl TryFrom<i32> for Tick {
+    type Error = JoltError;
+
+    fn try_from(tick: i32) -> Result<Self, JoltError> {
+        Tick::new(tick)
+    }
+}
+
+impl From<Tick> for i32 {
+    fn from(tick: Tick) -> Self {
+        tick.0
+    }
+}
+
+impl fmt::Display for Tick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A positive tick spacing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TickSpacing(i32);
+
+impl TickSpacing {
+    /// Rejects spacings that are not positive.
+    pub const fn new(spacing: i32) -> Result<Self, JoltError> {
+        if spacing > 0 {
+            Ok(TickSpacing(spacing))
+        } else {
+            Err(JoltError::InvalidSpacing(spacing))
+        }
+    }
+
+    pub const fn get(self) -> i32 {
+        self.0
+    }
+
+    /// Lowest multiple of the spacing within `[MIN_TICK, MAX_TICK]`.
+    pub const fn min_tick(self) -> Tick {
+        // Division truncates toward zero, so this rounds MIN_TICK up
+        Tick(MIN_TICK / self.0 * self.0)
+    }
+
+    /// Highest multiple of the spacing within `[MIN_TICK, MAX_TICK]`.
+    pub const fn max_tick(self) -> Tick {
+        Tick(MAX_TICK / self.0 * self.0)
+    }
+
+    /// Aligns any `tick` to a multiple of the spacing, clamped to
+    /// [`Self::min_tick`] and [`Self::max_tick`].
+    pub fn align(self, tick: i32, rounding: TickRounding) -> Tick {
+        // Widened so neither the rounding nor the clamp can overflow
+        let (tick, spacing) = (tick as i64, self.0 as i64);
+        let floor = tick.div_euclid(spacing) * spacing;
+        let aligned = match rounding {
+            TickRounding::Floor => floor,
+            TickRounding::Ceil if floor == tick => floor,
+            TickRounding::Nearest if 2 * (tick - floor) < spacing => floor,
+            TickRounding::Ceil | TickRounding::Nearest => floor + spacing,
+        };
+        let (min, max) = (self.min_tick().0 as i64, self.max_tick().0 as i64);
+        Tick(aligned.clamp(min, max) as i32)
+    }
+
+    /// Aligns `[tick_lower, tick_upper)` outward, so the range covers at
+    /// least the requested ticks wherever the bounds allow.
+    ///
+    /// Fails when the request is inverted, or lies entirely below
+    /// [`Self::min_tick`] or above [`Self::max_tick`] so that no aligned
+    /// range overlaps it.
+    pub fn align_range(self, tick_lower: i32, tick_upper: i32) -> Result<(Tick, Tick), JoltError> {
+        if tick_lower >= tick_upper {
+            return Err(JoltError::RangeInverted {
+                tick_lower,
+                tick_upper,
+            });
+        }
+        let lower = self.align(tick_lower, TickRounding::Floor);
+        let upper = self.align(tick_upper, TickRounding::Ceil);
+        if lower == upper {
+            return Err(JoltError::RangeBeyondBounds {
+                tick_lower,
+                tick_upper,
+            });
+        }
+        Ok((lower, upper))
+    }
+}
+
+/// Direction [`TickSpacing::align`] moves a tick between two multiples of
+/// the spacing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TickRounding {
+    /// Toward negative infinity
+    Floor,
+    /// Toward positive infinity
+    Ceil,
+    /// To the closer multiple, ties toward positive infinity
+    Nearest,
+}
+
+impl TryFrom<i32> for TickSpacing {
+    type Error = JoltError;
+
+    fn try_from(spacing: i32) -> Result<Self, JoltError> {
+        TickSpacing::new(spacing)
+    }
+}
+
+impl From<TickSpacing> for i32 {
+    fn from(spacing: TickSpacing) -> Self {
+        spacing.0
+    }
+}
+
+/// A Q64.64 square root price within `[MIN_SQRT_PRICE_X64, MAX_SQRT_PRICE_X64]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SqrtPriceX64(u128);
+
+impl SqrtPriceX64 {
+    pub const MIN: SqrtPriceX64 = SqrtPriceX64(MIN_SQRT_PRICE_X64);
+    pub const MAX: SqrtPriceX64 = SqrtPriceX64(MAX_SQRT_PRICE_X64);
+
+    /// Rejects square root prices outside the tick range.
+    pub const fn new(sqrt_price_x64: u128) -> Result<Self, JoltError> {
+        if MIN_SQRT_PRICE_X64 <= sqrt_price_x64 && sqrt_price_x64 <= MAX_SQRT_PRICE_X64 {
+            Ok(SqrtPriceX64(sqrt_price_x64))
+        } else {
+            Err(JoltError::SqrtPriceOutOfBounds(sqrt_price_x64))
+        }
+    }
+
+    /// Square root price at `tick`, as computed on-chain.
+    pub fn from_tick(tick: Tick) -> Self {
+        match get_sqrt_price_at_tick(tick.get()) {
+            Ok(sqrt_price_x64) => SqrtPriceX64(sqrt_price_x64),
+            Err(_) => unreachable!("ticks are within [MIN_TICK, MAX_TICK]"),
+        }
+    }
+
+    /// Greatest tick whose square root price is at most this one.
+    ///
+    /// Fails for `SqrtPriceX64::MAX`, which has no tick above it.
+    pub fn to_tick(self) -> Result<Tick, JoltError> {
+        Ok(Tick(get_tick_at_sqrt_price(self.0)?))
+    }
+
+    pub const fn get(self) -> u128 {
+        self.0
+    }
+}
+
+impl TryFrom<u128> for SqrtPriceX64 {
+    type Error = JoltError;
+
+    fn try_from(sqrt_price_x64: u128) -> Result<Self, JoltError> {
+        SqrtPriceX64::new(sqrt_price_x64)
+    }
+}
+
+impl From<SqrtPriceX64> for u128 {
+    fn from(sqrt_price: SqrtPriceX64) -> Self {
+        sqrt_price.0
+    }
+}
+
+/// Liquidity of a position or pool.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Liquidity(u128);
+
+impl Liquidity {
+    pub const ZERO: Liquidity = Liquidity(0);
+
+    pub const fn new(liquidity: u128) -> Self {
+        Liquidity(liquidity)
+    }
+
+    pub const fn get(self) -> u128 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Liquidity) -> Option<Liquidity> {
+        self.0.checked_add(other.0).map(Liquidity)
+    }
+
+    pub fn checked_sub(self, other: Liquidity) -> Option<Liquidity> {
+        self.0.checked_sub(other.0).map(Liquidity)
+    }
+}
+
+impl From<u128> for Liquidity {
+    fn from(liquidity: u128) -> Self {
+        Liquidity(liquidity)
+    }
+}
+
+impl From<Liquidity> for u128 {
+    fn from(liquidity: Liquidity) -> Self {
+        liquidity.0
+    }
+}
+
+/// Marker for the stable coin side of a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stable {}
+
+/// Marker for the meme coin side of a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Meme {}
+
+/// Which token a pool quotes as token0.
+///
+/// Pool prices are token1 per token0, so a range above the current price
+/// holds only token0 and a range below it holds only token1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenOrder {
+    /// Meme coin is token0; pool prices are stable per meme.
+    MemeFirst,
+    /// Stable coin is token0; pool prices are meme per stable.
+    StableFirst,
+}
+
+impl TokenOrder {
+    /// Meme and stable amounts as `(amount_0, amount_1)`.
+    pub fn to_pool(self, meme: TokenAmount<Meme>, stable: TokenAmount<Stable>) -> (u64, u64) {
+        match self {
+            TokenOrder::MemeFirst => (meme.get(), stable.get()),
+            TokenOrder::StableFirst => (stable.get(), meme.get()),
+        }
+    }
+
+    /// Pool amounts `(amount_0, amount_1)` as meme and stable amounts.
+    pub fn from_pool(
+        self,
+        amount_0: u64,
+        amount_1: u64,
+    ) -> (TokenAmount<Meme>, TokenAmount<Stable>) {
+        match self {
+            TokenOrder::MemeFirst => (TokenAmount::new(amount_0), TokenAmount::new(amount_1)),
+            TokenOrder::StableFirst => (TokenAmount::new(amount_1), TokenAmount::new(amount_0)),
+        }
+    }
+}
+
+/// An amount of one token in its smallest unit.
+///
+/// `Mint` is a marker such as [`Stable`] or [`Meme`], so amounts of
+/// different tokens cannot be added or compared.
+pub struct TokenAmount<Mint> {
+    amount: u64,
+    mint: PhantomData<Mint>,
+}
+
+impl<Mint> TokenAmount<Mint> {
+    pub const ZERO: TokenAmount<Mint> = TokenAmount::new(0);
+
+    pub const fn new(amount: u64) -> Self {
+        TokenAmount {
+            amount,
+            mint: PhantomData,
+        }
+    }
+
+    pub const fn get(self) -> u64 {
+        self.amount
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.amount.checked_add(other.amount).map(TokenAmount::new)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.amount.checked_sub(other.amount).map(TokenAmount::new)
+    }
+}
+
+// Implemented by hand so the marker needs none of these traits.
+impl<Mint> Clone for TokenAmount<Mint> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Mint> Copy for TokenAmount<Mint> {}
+
+impl<Mint> fmt::Debug for TokenAmount<Mint> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TokenAmount").field(&self.amount).finish()
+    }
+}
+
+impl<Mint> Default for TokenAmount<Mint> {
+    fn default() -> Self {
+        TokenAmount::ZERO
+    }
+}
+
+impl<Mint> PartialEq for TokenAmount<Mint> {
+    fn eq(&self, other: &Self) -> bool {
+        self.amount == other.amount
+    }
+}
+
+impl<Mint> Eq for TokenAmount<Mint> {}
+
+impl<Mint> PartialOrd for TokenAmount<Mint> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Mint> Ord for TokenAmount<Mint> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.amount.cmp(&other.amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::min(MIN_TICK, Ok(Tick::MIN))]
+    #[case::max(MAX_TICK, Ok(Tick::MAX))]
+    #[case::below_min(MIN_TICK - 1, Err(JoltError::TickOutOfBounds(MIN_TICK - 1)))]
+    #[case::above_max(MAX_TICK + 1, Err(JoltError::TickOutOfBounds(MAX_TICK + 1)))]
+    fn test_tick_bounds(#[case] tick: i32, #[case] expected: Result<Tick, JoltError>) {
+        assert_eq!(Tick::new(tick), expected);
+        assert_eq!(Tick::try_from(tick), expected);
+    }
+
+    #[rstest]
+    #[case::aligned(-120, Ok(-120))]
+    #[case::zero(0, Ok(0))]
+    #[case::misaligned(-125, Err(JoltError::TickMisaligned { tick: -125, spacing: 60 }))]
+    #[case::out_of_bounds(MAX_TICK + 4, Err(JoltError::TickOutOfBounds(MAX_TICK + 4)))]
+    fn test_tick_alignment(#[case] tick: i32, #[case] expected: Result<i32, JoltError>) {
+        let spacing = TickSpacing::new(60).unwrap();
+        assert_eq!(Tick::aligned(tick, spacing).map(Tick::get), expected);
+    }
+
+    #[rstest]
+    #[case::positive(1, Ok(1))]
+    #[case::zero(0, Err(JoltError::InvalidSpacing(0)))]
+    #[case::negative(-10, Err(JoltError::InvalidSpacing(-10)))]
+    fn test_tick_spacing(#[case] spacing: i32, #[case] expected: Result<i32, JoltError>) {
+        assert_eq!(TickSpacing::new(spacing).map(TickSpacing::get), expected);
+    }
+
+    #[rstest]
+    #[case::aligned(-120, [-120, -120, -120])]
+    #[case::positive(25, [0, 60, 0])]
+    #[case::positive_tie(30, [0, 60, 60])]
+    #[case::positive_above_tie(31, [0, 60, 60])]
+    #[case::negative(-25, [-60, 0, 0])]
+    #[case::negative_tie(-30, [-60, 0, 0])]
+    #[case::negative_below_tie(-31, [-60, 0, -60])]
+    #[case::near_min(MIN_TICK, [-443_580, -443_580, -443_580])]
+    #[case::near_max(MAX_TICK, [443_580, 443_580, 443_580])]
+    #[case::i32_min(i32::MIN, [-443_580, -443_580, -443_580])]
+    #[case::i32_max(i32::MAX, [443_580, 443_580, 443_580])]
+    fn test_align(#[case] tick: i32, #[case] expected: [i32; 3]) {
+        let spacing = TickSpacing::new(60).unwrap();
+        let aligned = [
+            TickRounding::Floor,
+            TickRounding::Ceil,
+            TickRounding::Nearest,
+        ]
+        .map(|rounding| spacing.align(tick, rounding).get());
+        assert_eq!(aligned, expected);
+    }
+
+    #[rstest]
+    #[case::unit(1, MIN_TICK, MAX_TICK)]
+    #[case::ten(10, -443_630, 443_630)]
+    #[case::sixty(60, -443_580, 443_580)]
+    #[case::wider_than_bounds(i32::MAX, 0, 0)]
+    fn test_spacing_bounds(#[case] spacing: i32, #[case] min: i32, #[case] max: i32) {
+        let spacing = TickSpacing::new(spacing).unwrap();
+        assert_eq!(
+            (spacing.min_tick().get(), spacing.max_tick().get()),
+            (min, max)
+        );
+        assert!(spacing.min_tick().is_aligned(spacing) && spacing.max_tick().is_aligned(spacing));
+    }
+
+    #[rstest]
+    #[case::aligned(-600, 600, Ok((-600, 600)))]
+    #[case::widens_both(-610, 590, Ok((-660, 600)))]
+    #[case::within_one_spacing(5, 6, Ok((0, 60)))]
+    #[case::clamped(i32::MIN, i32::MAX, Ok((-443_580, 443_580)))]
+    #[case::inverted(60, 0, Err(JoltError::RangeInverted { tick_lower: 60, tick_upper: 0 }))]
+    #[case::empty(0, 0, Err(JoltError::RangeInverted { tick_lower: 0, tick_upper: 0 }))]
+    #[case::above_max(
+        MAX_TICK, i32::MAX,
+        Err(JoltError::RangeBeyondBounds { tick_lower: MAX_TICK, tick_upper: i32::MAX })
+    )]
+    #[case::below_min(
+        i32::MIN, MIN_TICK,
+        Err(JoltError::RangeBeyondBounds { tick_lower: i32::MIN, tick_upper: MIN_TICK })
+    )]
+    #[case::past_last_multiple(
+        443_600, MAX_TICK,
+        Err(JoltError::RangeBeyondBounds { tick_lower: 443_600, tick_upper: MAX_TICK })
+    )]
+    fn test_align_range(
+        #[case] tick_lower: i32,
+        #[case] tick_upper: i32,
+        #[case] expected: Result<(i32, i32), JoltError>,
+    ) {
+        let spacing = TickSpacing::new(60).unwrap();
+        let range = spacing.align_range(tick_lower, tick_upper);
+        assert_eq!(
+            range.map(|(lower, upper)| (lower.get(), upper.get())),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::min(MIN_SQRT_PRICE_X64, true)]
+    #[case::max(MAX_SQRT_PRICE_X64, true)]
+    #[case::below_min(MIN_SQRT_PRICE_X64 - 1, false)]
+    #[case::above_max(MAX_SQRT_PRICE_X64 + 1, false)]
+    fn test_sqrt_price_bounds(#[case] sqrt_price_x64: u128, #[case] valid: bool) {
+        assert_eq!(SqrtPriceX64::new(sqrt_price_x64).is_ok(), valid);
+    }
+
+    #[test]
+    fn test_sqrt_price_tick_round_trip() {
+        for tick in [MIN_TICK, -1000, 0, 1, 443_635] {
+            let tick = Tick::new(tick).unwrap();
+            assert_eq!(tick.sqrt_price().to_tick(), Ok(tick));
+        }
+        assert_eq!(
+            SqrtPriceX64::MAX.to_tick(),
+            Err(JoltError::SqrtPriceOutOfBounds(MAX_SQRT_PRICE_X64))
+        );
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let liquidity = Liquidity::new(u128::MAX - 1);
+        assert_eq!(
+            liquidity.checked_add(Liquidity::new(1)),
+            Some(Liquidity::new(u128::MAX))
+        );
+        assert_eq!(liquidity.checked_add(Liquidity::new(2)), None);
+        assert_eq!(Liquidity::ZERO.checked_sub(Liquidity::new(1)), None);
+
+        let stable = TokenAmount::<Stable>::new(5);
+        assert_eq!(
+            stable.checked_sub(TokenAmount::new(2)),
+            Some(TokenAmount::new(3))
+        );
+        assert_eq!(stable.checked_sub(TokenAmount::new(6)), None);
+        assert!(TokenAmount::<Meme>::ZERO < TokenAmount::new(1));
+    }
+}
@@ -0,0 +1,334 @@
+//! Detection of unidirectional price movement for full liquidity withdrawal.
+//!
+//! A unidirectional move is a run of states whose velocity keeps the same
+//! sign, whose retracement from the run extreme stays within a drawdown
+//! limit, and whose cumulative move from the run start crosses a threshold.
+//! Once withdrawn, re-entry requires both a cool-down period and the
+//! velocity settling below a lower threshold, so the three-range positions
+//! are not churned by a move that merely pauses.
+
+use crate::{JoltError, Scalar, StateVector};
+
+/// Decision returned by [`WithdrawalDetector::update`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WithdrawalDecision {
+    /// No unidirectional move; positions stay deployed.
+    Deployed,
+    /// A unidirectional move started; withdraw all liquidity.
+    EnterWithdrawal,
+    /// Still withdrawn; the cool-down or hysteresis is not yet satisfied.
+    StayWithdrawn,
+    /// Conditions have settled; positions may be re-established.
+    ReEntryAllowed,
+}
+
+/// Thresholds for [`WithdrawalDetector`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WithdrawalConfig<T: Scalar> {
+    /// Consecutive same-sign velocity states required for a run
+    pub min_run: usize,
+    /// Cumulative move from the run start that triggers withdrawal
+    pub move_threshold: T,
+    /// Largest retracement from the run extreme before the run resets
+    pub max_drawdown: T,
+    /// Speed below which re-entry is allowed
+    pub reentry_speed: T,
+    /// Minimum time between withdrawal and re-entry
+    pub cooldown: T,
+}
+
+/// Stateful detector over a sequence of states.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WithdrawalDetector<T: Scalar> {
+    config: WithdrawalConfig<T>,
+    run: Option<Run<T>>,
+    withdrawn_at: Option<T>,
+}
+
+/// Current same-direction run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Run<T: Scalar> {
+    rising: bool,
+    length: usize,
+    start: T,
+    extreme: T,
+}
+
+impl<T: Scalar> WithdrawalDetector<T> {
+    /// Fails with [`JoltError::NonFinite`] for a non-finite threshold,
+    /// [`JoltError::InvalidInterval`] for a negative cool-down and
+    /// [`JoltError::InvalidThreshold`] for another negative threshold.
+    pub fn new(config: WithdrawalConfig<T>) -> Result<Self, JoltError> {
+        let thresholds = [
+            config.move_threshold,
+            config.max_drawdown,
+            config.reentry_speed,
+            config.cooldown,
+        ];
+        if !thresholds.iter().all(|value| value.is_finite()) {
+            return Err(JoltError::NonFinite);
+        }
+        if config.cooldown < T::zero() {
+            return Err(JoltError::InvalidInterval);
+        }
+        if thresholds.iter().any(|value| *value < T::zero()) {
+            return Err(JoltError::InvalidThreshold);
+        }
+        Ok(WithdrawalDetector {
+            config,
+            run: None,
+            withdrawn_at: None,
+        })
+    }
+
+    pub fn config(&self) -> &WithdrawalConfig<T> {
+        &self.config
+    }
+
+    /// True between `EnterWithdrawal` and `ReEntryAllowed`.
+    pub fn is_withdrawn(&self) -> bool {
+        self.withdrawn_at.is_some()
+    }
+
+    /// Cumulative move of the current run, signed by its direction.
+    pub fn run_move<const D: usize>(&self, state: &StateVector<T, D>) -> Option<T> {
+        Some(state.position()? - self.run?.start)
+    }
+
+    /// Feeds the state observed at `time`.
+    ///
+    /// Returns `None` if the state has no velocity and fails with
+    /// [`JoltError::NonFinite`] for a non-finite time, position or velocity;
+    /// the detector is left unchanged in both cases.
+    pub fn update<const D: usize>(
+        &mut self,
+        time: T,
+        state: &StateVector<T, D>,
+    ) -> Result<Option<WithdrawalDecision>, JoltError> {
+        let (Some(position), Some(velocity)) = (state.position(), state.velocity()) else {
+            return Ok(None);
+        };
+        if !time.is_finite() || !position.is_finite() || !velocity.is_finite() {
+            return Err(JoltError::NonFinite);
+        }
+        let moving = self.track(position, velocity);
+
+        let Some(withdrawn_at) = self.withdrawn_at else {
+            if moving {
+                self.withdrawn_at = Some(time);
+                return Ok(Some(WithdrawalDecision::EnterWithdrawal));
+            }
+            return Ok(Some(WithdrawalDecision::Deployed));
+        };
+        let cooled = self.config.cooldown <= time - withdrawn_at;
+        let settled = velocity.abs() <= self.config.reentry_speed;
+        if cooled && settled && !moving {
+            self.withdrawn_at = None;
+            self.run = None;
+            Ok(Some(WithdrawalDecision::ReEntryAllowed))
+        } else {
+            Ok(Some(WithdrawalDecision::StayWithdrawn))
+        }
+    }
+
+    /// Clears the run and any withdrawal.
+    pub fn reset(&mut self) {
+        self.run = None;
+        self.withdrawn_at = None;
+    }
+
+    /// Extends or restarts the run; true if it is a unidirectional move.
+    fn track(&mut self, position: T, velocity: T) -> bool {
+        if velocity == T::zero() {
+            self.run = None;
+            return false;
+        }
+        let rising = T::zero() < velocity;
+        let run = match self.run {
+            Some(mut run) if run.rising == rising => {
+                run.length += 1;
+                run.extreme = if rising {
+                    run.extreme.max(position)
+                } else {
+                    run.extreme.min(position)
+                };
+                if self.config.max_drawdown < (run.extreme - position).abs() {
+                    Run {
+                        rising,
+                        length: 1,
+                        start: position,
+                        extreme: position,
+                    }
+                } else {
+                    run
+                }
+            }
+            _ => Run {
+                rising,
+                length: 1,
+                start: position,
+                extreme: position,
+            },
+        };
+        self.run = Some(run);
+        self.config.min_run <= run.length
+            && self.config.move_threshold <= (position - run.start).abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const CONFIG: WithdrawalConfig<f64> = WithdrawalConfig {
+        min_run: 3,
+        move_threshold: 5.0,
+        max_drawdown: 1.0,
+        reentry_speed: 0.5,
+        cooldown: 10.0,
+    };
+
+    fn detector() -> WithdrawalDetector<f64> {
+        WithdrawalDetector::new(CONFIG).unwrap()
+    }
+
+    fn feed(
+        detector: &mut WithdrawalDetector<f64>,
+        states: &[(f64, f64, f64)],
+    ) -> Option<WithdrawalDecision> {
+        let mut decision = None;
+        for (time, position, velocity) in states {
+            decision = detector
+                .update(*time, &StateVector([*position, *velocity]))
+                .unwrap();
+        }
+        decision
+    }
+
+    #[test]
+    fn test_requires_velocity() {
+        let mut detector = detector();
+        assert_eq!(detector.update(0.0, &StateVector([1.0])), Ok(None));
+    }
+
+    #[rstest]
+    #[case::rally(&[(0.0, 100.0, 2.0), (1.0, 102.0, 2.0), (2.0, 105.0, 3.0)])]
+    #[case::selloff(&[(0.0, 100.0, -2.0), (1.0, 97.0, -3.0), (2.0, 94.5, -2.5)])]
+    fn test_sustained_move_enters_withdrawal(#[case] states: &[(f64, f64, f64)]) {
+        let mut detector = detector();
+        assert_eq!(
+            feed(&mut detector, states),
+            Some(WithdrawalDecision::EnterWithdrawal)
+        );
+        assert!(detector.is_withdrawn());
+    }
+
+    #[rstest]
+    #[case::short_run(&[(0.0, 100.0, 2.0), (1.0, 106.0, 6.0)])]
+    #[case::small_move(&[(0.0, 100.0, 1.0), (1.0, 101.0, 1.0), (2.0, 102.0, 1.0)])]
+    #[case::sign_flip(&[(0.0, 100.0, 2.0), (1.0, 103.0, 2.0), (2.0, 105.5, -1.0)])]
+    #[case::drawdown(&[(0.0, 100.0, 2.0), (1.0, 104.0, 2.0), (2.0, 102.5, 0.1), (3.0, 106.0, 2.0)])]
+    fn test_choppy_moves_stay_deployed(#[case] states: &[(f64, f64, f64)]) {
+        let mut detector = detector();
+        assert_eq!(
+            feed(&mut detector, states),
+            Some(WithdrawalDecision::Deployed)
+        );
+    }
+
+    #[test]
+    fn test_reentry_needs_cooldown_and_settling() {
+        let mut detector = detector();
+        let rally = [(0.0, 100.0, 2.0), (1.0, 103.0, 3.0), (2.0, 106.0, 3.0)];
+        assert_eq!(
+            feed(&mut detector, &rally),
+            Some(WithdrawalDecision::EnterWithdrawal)
+        );
+        // Settled but still cooling down
+        assert_eq!(
+            detector.update(5.0, &StateVector([107.0, 0.1])),
+            Ok(Some(WithdrawalDecision::StayWithdrawn))
+        );
+        // Cooled down, but speed is above the re-entry threshold
+        assert_eq!(
+            detector.update(12.0, &StateVector([108.0, -0.8])),
+            Ok(Some(WithdrawalDecision::StayWithdrawn))
+        );
+        assert_eq!(
+            detector.update(13.0, &StateVector([108.0, 0.2])),
+            Ok(Some(WithdrawalDecision::ReEntryAllowed))
+        );
+        assert!(!detector.is_withdrawn());
+        assert_eq!(
+            detector.update(14.0, &StateVector([108.1, 0.2])),
+            Ok(Some(WithdrawalDecision::Deployed))
+        );
+    }
+
+    #[test]
+    fn test_non_finite_state_changes_nothing() {
+        let mut detector = detector();
+        let rally = [(0.0, 100.0, 2.0), (1.0, 103.0, 3.0), (2.0, 106.0, 3.0)];
+        feed(&mut detector, &rally[..2]);
+        assert_eq!(
+            detector.update(f64::NAN, &StateVector([106.0, 3.0])),
+            Err(JoltError::NonFinite)
+        );
+        assert_eq!(
+            detector.update(2.0, &StateVector([106.0, f64::INFINITY])),
+            Err(JoltError::NonFinite)
+        );
+        assert_eq!(
+            feed(&mut detector, &rally[2..]),
+            Some(WithdrawalDecision::EnterWithdrawal)
+        );
+        // A NaN time no longer leaves the detector withdrawn for good
+        assert_eq!(
+            detector.update(f64::NAN, &StateVector([107.0, 0.1])),
+            Err(JoltError::NonFinite)
+        );
+        assert_eq!(
+            detector.update(13.0, &StateVector([107.0, -0.2])),
+            Ok(Some(WithdrawalDecision::ReEntryAllowed))
+        );
+    }
+
+    #[rstest]
+    #[case::nan_cooldown(WithdrawalConfig { cooldown: f64::NAN, ..CONFIG }, JoltError::NonFinite)]
+    #[case::nan_reentry_speed(
+        WithdrawalConfig { reentry_speed: f64::NAN, ..CONFIG },
+        JoltError::NonFinite
+    )]
+    #[case::infinite_move(
+        WithdrawalConfig { move_threshold: f64::INFINITY, ..CONFIG },
+        JoltError::NonFinite
+    )]
+    #[case::negative_cooldown(
+        WithdrawalConfig { cooldown: -1.0, ..CONFIG },
+        JoltError::InvalidInterval
+    )]
+    #[case::negative_reentry_speed(
+        WithdrawalConfig { reentry_speed: -0.5, ..CONFIG },
+        JoltError::InvalidThreshold
+    )]
+    #[case::negative_drawdown(
+        WithdrawalConfig { max_drawdown: -1.0, ..CONFIG },
+        JoltError::InvalidThreshold
+    )]
+    fn test_invalid_config(#[case] config: WithdrawalConfig<f64>, #[case] expected: JoltError) {
+        assert_eq!(WithdrawalDetector::new(config), Err(expected));
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut detector = detector();
+        feed(
+            &mut detector,
+            &[(0.0, 100.0, 2.0), (1.0, 103.0, 3.0), (2.0, 106.0, 3.0)],
+        );
+        detector.reset();
+        assert!(!detector.is_withdrawn());
+        assert_eq!(detector.run_move(&StateVector([106.0, 1.0])), None);
+    }
+}